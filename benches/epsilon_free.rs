@@ -0,0 +1,48 @@
+//! Compares matching the same language with and without epsilon transitions.
+//!
+//! Run with `cargo bench --bench epsilon_free`.
+use regexxx::matcher::{CompiledMatcher, Matcher};
+use regexxx::parser::parse;
+use std::time::{Duration, Instant};
+
+const PATTERN: &str = "a(b|c)d";
+const ITERATIONS: u32 = 10_000;
+
+fn time(matcher: &CompiledMatcher, inputs: &[String]) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        for input in inputs {
+            std::hint::black_box(matcher.is_match(std::hint::black_box(input)));
+        }
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let inputs: Vec<String> = ["abd", "acd", "abc", "add", "a"]
+        .iter()
+        .map(|input| input.to_string())
+        .collect();
+    let machine = parse(PATTERN).unwrap();
+    let mut epsilon_free = parse(PATTERN).unwrap();
+    epsilon_free.remove_epsilons().unwrap();
+    let closure = CompiledMatcher::new(machine, Matcher::default());
+    let direct = CompiledMatcher::new(epsilon_free, Matcher::default());
+    assert!(direct.is_epsilon_free(), "{} is epsilon free", PATTERN);
+    for input in inputs.iter() {
+        assert_eq!(direct.is_match(input), closure.is_match(input));
+    }
+    let closure = time(&closure, &inputs);
+    let direct = time(&direct, &inputs);
+    println!(
+        "{} x {} inputs",
+        PATTERN,
+        inputs.len() * ITERATIONS as usize
+    );
+    println!("  epsilon closure: {:?}", closure);
+    println!("  epsilon free:    {:?}", direct);
+    println!(
+        "  speedup:         {:.2}x",
+        closure.as_secs_f64() / direct.as_secs_f64()
+    );
+}
@@ -0,0 +1,187 @@
+use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use crate::elimination::Expr;
+use crate::error::CompileError;
+use crate::{Label, ANFA};
+use alloc::boxed::Box;
+
+/// A regular expression as an owned tree, e.g. to rewrite a pattern with
+/// simplification rules before compiling it.
+///
+/// `ANFA::to_ast` reads one from an automaton and `Ast::compile` builds one
+/// into an automaton.
+///
+/// ```rust
+/// use regexxx::ast::Ast;
+/// // RE ab*
+/// let ast = Ast::Concat(
+///     Box::new(Ast::Literal('a')),
+///     Box::new(Ast::Star(Box::new(Ast::Literal('b')))),
+/// );
+/// let machine = ast.compile();
+/// assert!(machine.is_match("abb"));
+/// assert_eq!(machine.to_ast().unwrap(), ast);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ast {
+    /// Accepts nothing
+    Empty,
+    /// Accepts only the empty string
+    Epsilon,
+    /// Accepts exactly this char
+    Literal(char),
+    /// Accepts `a` followed by `b`
+    Concat(Box<Ast>, Box<Ast>),
+    /// Accepts `a` or `b`, preferring `a`
+    Union(Box<Ast>, Box<Ast>),
+    /// Accepts `a` repeated 0 or more times
+    Star(Box<Ast>),
+}
+
+impl Ast {
+    /// Returns an ANFA with one machine accepting the language of the tree,
+    /// built with `ForwardCompiler` from the leaves up.
+    pub fn compile(&self) -> ANFA {
+        let mut anfa = ANFA::new();
+        // every operator finds its operands on the stack, so compiling cannot fail
+        self.push(&mut anfa)
+            .expect("Every operator has its operands.");
+        anfa
+    }
+
+    /// Pushes a machine accepting the language of the tree.
+    fn push(&self, anfa: &mut ANFA) -> Result<(), CompileError> {
+        match self {
+            Ast::Empty => ForwardCompiler::expr_0(anfa),
+            Ast::Epsilon => ForwardCompiler::expr_1(anfa),
+            Ast::Literal(c) => ForwardCompiler::expr_a(anfa, *c),
+            Ast::Concat(a, b) => {
+                a.push(anfa)?;
+                b.push(anfa)?;
+                ForwardCompiler::concatenate(anfa)
+            }
+            Ast::Union(a, b) => {
+                a.push(anfa)?;
+                b.push(anfa)?;
+                ForwardCompiler::union(anfa)
+            }
+            Ast::Star(a) => {
+                a.push(anfa)?;
+                ForwardCompiler::star(anfa)
+            }
+        }
+    }
+
+    /// Returns the tree of an expression, failing on labels a tree cannot hold.
+    fn from_expr(expr: Expr) -> Result<Ast, CompileError> {
+        Ok(match expr {
+            Expr::Empty => Ast::Empty,
+            Expr::Epsilon => Ast::Epsilon,
+            Expr::Label(Label::Char(c)) => Ast::Literal(c),
+            Expr::Concat(a, b) => {
+                Ast::Concat(Box::new(Ast::from_expr(*a)?), Box::new(Ast::from_expr(*b)?))
+            }
+            Expr::Union(a, b) => {
+                Ast::Union(Box::new(Ast::from_expr(*a)?), Box::new(Ast::from_expr(*b)?))
+            }
+            Expr::Star(a) => Ast::Star(Box::new(Ast::from_expr(*a)?)),
+            Expr::Label(_) => {
+                return Err(CompileError::Invalid(
+                    "An AST holds only chars and epsilon.",
+                ));
+            }
+        })
+    }
+}
+
+impl ANFA {
+    /// Returns the top machine as an `Ast`, by state elimination. See
+    /// `ANFA::to_regex_string`.
+    ///
+    /// The tree is not minimal, but `ε` and `∅` operands are simplified away.
+    /// Fails when the machine holds `.`, a class, or an anchor, which an `Ast`
+    /// cannot hold. Without a machine the tree is `Ast::Empty`.
+    ///
+    /// ```rust
+    /// use regexxx::ast::Ast;
+    /// use regexxx::parser::parse;
+    /// let ast = parse("a|b").unwrap().to_ast().unwrap();
+    /// assert_eq!(ast, Ast::Union(Box::new(Ast::Literal('a')), Box::new(Ast::Literal('b'))));
+    /// assert!(parse("a.").unwrap().to_ast().is_err());
+    /// ```
+    pub fn to_ast(&self) -> Result<Ast, CompileError> {
+        Ast::from_expr(self.eliminate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::Ast;
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::parser::parse;
+    use crate::ANFA;
+    use alloc::boxed::Box;
+
+    #[test]
+    fn test_ast_compile() {
+        // RE a(b|c)*d
+        let ast = Ast::Concat(
+            Box::new(Ast::Concat(
+                Box::new(Ast::Literal('a')),
+                Box::new(Ast::Star(Box::new(Ast::Union(
+                    Box::new(Ast::Literal('b')),
+                    Box::new(Ast::Literal('c')),
+                )))),
+            )),
+            Box::new(Ast::Literal('d')),
+        );
+        let machine = ast.compile();
+        let parsed = parse("a(b|c)*d").unwrap();
+        for input in ["ad", "abd", "acbcd", "", "a", "abc", "dad"] {
+            assert_eq!(
+                machine.is_match(input),
+                parsed.is_match(input),
+                "The AST and the parser agree on {:?}",
+                input
+            );
+        }
+        assert!(machine.equivalent(&parsed), "The AST is a(b|c)*d");
+        assert_eq!(machine.automata_refs.len(), 1, "The AST is one machine");
+    }
+
+    #[test]
+    fn test_to_ast() {
+        for pattern in ["a(b|c)*d", "(a|b)*abb", "a|", "()", "(ab|a)(ba|b)*"] {
+            let machine = parse(pattern).unwrap();
+            assert!(
+                machine.to_ast().unwrap().compile().equivalent(&machine),
+                "{} round trips through its AST",
+                pattern
+            );
+        }
+        assert_eq!(
+            parse("a[]").unwrap().to_ast().unwrap(),
+            Ast::Empty,
+            "Nothing simplifies to Empty"
+        );
+        assert_eq!(
+            ANFA::new().to_ast().unwrap(),
+            Ast::Empty,
+            "No machine is Empty"
+        );
+        for pattern in [".", "^a", "a$"] {
+            assert!(
+                parse(pattern).unwrap().to_ast().is_err(),
+                "{} has no AST",
+                pattern
+            );
+        }
+        let mut machine = ANFA::new();
+        ForwardCompiler::expr_range(&mut machine, 'a', 'z').unwrap();
+        assert!(machine.to_ast().is_err(), "A class has no AST");
+        assert!(
+            Ast::Empty.compile().is_empty_language(),
+            "Empty accepts nothing"
+        );
+        assert!(Ast::Epsilon.compile().is_match(""), "Epsilon accepts ''");
+    }
+}
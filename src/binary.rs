@@ -0,0 +1,284 @@
+use crate::error::CompileError;
+use crate::{AnchorKind, Label, QId, ANFA};
+use alloc::vec;
+
+// label tags, the low nibble of each transition's tag byte
+const EPSILON: u8 = 0;
+const CHAR: u8 = 1;
+const ANY: u8 = 2;
+const CLASS: u8 = 3;
+const START: u8 = 4;
+const END: u8 = 5;
+
+impl ANFA {
+    /// Returns the ANFA in a compact binary format, smaller and faster to read
+    /// than `ANFA::to_json`.
+    ///
+    /// ```text
+    /// states         varint
+    /// per state      tag byte: label in the low nibble, target count in the high nibble
+    ///                label payload: UTF-8 char, or for a class a negated byte,
+    ///                a varint range count, and two UTF-8 chars per range
+    ///                targets: varint each
+    /// machines       varint
+    /// per machine    q0 and f: varint each
+    /// ```
+    ///
+    /// Varints are little-endian base 128: seven bits per byte, low bits first,
+    /// and the high bit set on every byte but the last.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.save_binary(), [2, 0x11, b'a', 1, 0, 1, 0, 1]);
+    /// ```
+    pub fn save_binary(&self) -> vec::Vec<u8> {
+        let mut bytes = vec::Vec::with_capacity(4 * self.delta.len());
+        write_varint(&mut bytes, self.delta.len());
+        for (label, targets) in self.delta.iter() {
+            let tag = match label {
+                Label::Epsilon => EPSILON,
+                Label::Char(_) => CHAR,
+                Label::Any => ANY,
+                Label::Class(_, _) => CLASS,
+                Label::Anchor(AnchorKind::Start) => START,
+                Label::Anchor(AnchorKind::End) => END,
+            };
+            let count = targets.iter().flatten().count() as u8;
+            bytes.push(tag | count << 4);
+            match label {
+                Label::Char(c) => write_char(&mut bytes, *c),
+                Label::Class(ranges, negated) => {
+                    bytes.push(*negated as u8);
+                    write_varint(&mut bytes, ranges.len());
+                    for &(lo, hi) in ranges.iter() {
+                        write_char(&mut bytes, lo);
+                        write_char(&mut bytes, hi);
+                    }
+                }
+                _ => {}
+            }
+            for &target in targets.iter().flatten() {
+                write_varint(&mut bytes, target);
+            }
+        }
+        write_varint(&mut bytes, self.automata_refs.len());
+        for &[q0, f] in self.automata_refs.iter() {
+            write_varint(&mut bytes, q0);
+            write_varint(&mut bytes, f);
+        }
+        bytes
+    }
+
+    /// Reads an ANFA written by `ANFA::save_binary`.
+    ///
+    /// Fails when the bytes are truncated or followed by more bytes, a tag or
+    /// char is invalid, or a target or machine refers to a missing state.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// use regexxx::ANFA;
+    /// let machine = parse("a|b").unwrap();
+    /// let copy = ANFA::load_binary(&machine.save_binary()).unwrap();
+    /// assert_eq!(copy.delta, machine.delta);
+    /// ```
+    pub fn load_binary(bytes: &[u8]) -> Result<ANFA, CompileError> {
+        let mut reader = Reader { bytes, at: 0 };
+        let states = reader.varint()?;
+        // every state takes at least one byte, so a corrupt count cannot over-allocate
+        if states > bytes.len() {
+            return Err(CompileError::Invalid("Truncated binary."));
+        }
+        let mut anfa = ANFA::with_capacity(states, 1);
+        for _ in 0..states {
+            let tag = reader.byte()?;
+            let label = match tag & 0xf {
+                EPSILON => Label::Epsilon,
+                CHAR => Label::Char(reader.char()?),
+                ANY => Label::Any,
+                CLASS => {
+                    let negated = match reader.byte()? {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(CompileError::Invalid("Invalid binary class.")),
+                    };
+                    let count = reader.varint()?;
+                    let mut ranges = vec::Vec::new();
+                    for _ in 0..count {
+                        ranges.push((reader.char()?, reader.char()?));
+                    }
+                    Label::Class(ranges, negated)
+                }
+                START => Label::Anchor(AnchorKind::Start),
+                END => Label::Anchor(AnchorKind::End),
+                _ => return Err(CompileError::Invalid("Invalid binary tag.")),
+            };
+            let mut targets = [None, None];
+            match tag >> 4 {
+                count @ 0..=2 => {
+                    for target in targets.iter_mut().take(count as usize) {
+                        *target = Some(reader.varint()?);
+                    }
+                }
+                _ => return Err(CompileError::Invalid("Invalid binary tag.")),
+            }
+            anfa.delta.push((label, targets));
+        }
+        for _ in 0..reader.varint()? {
+            let q0 = reader.varint()?;
+            let f = reader.varint()?;
+            anfa.mark_machine(q0, f)?;
+        }
+        if reader.at != bytes.len() {
+            return Err(CompileError::Invalid("Trailing bytes after binary."));
+        }
+        for (_, targets) in anfa.delta.iter() {
+            let targets: vec::Vec<QId> = targets.iter().flatten().copied().collect();
+            anfa.check_states(&targets)?;
+        }
+        Ok(anfa)
+    }
+}
+
+fn write_varint(bytes: &mut vec::Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        bytes.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+fn write_char(bytes: &mut vec::Vec<u8>, c: char) {
+    let mut buffer = [0; 4];
+    bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, CompileError> {
+        let byte = *self
+            .bytes
+            .get(self.at)
+            .ok_or(CompileError::Invalid("Truncated binary."))?;
+        self.at += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<usize, CompileError> {
+        let mut n: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            let bits = (byte & 0x7f) as usize;
+            if shift >= usize::BITS || (bits << shift) >> shift != bits {
+                return Err(CompileError::Invalid("Binary varint overflows."));
+            }
+            n |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads one UTF-8 char, whose length is given by its first byte
+    fn char(&mut self) -> Result<char, CompileError> {
+        let len = match self.bytes.get(self.at) {
+            None => return Err(CompileError::Invalid("Truncated binary.")),
+            Some(0x00..=0x7f) => 1,
+            Some(0xc0..=0xdf) => 2,
+            Some(0xe0..=0xef) => 3,
+            Some(0xf0..=0xf7) => 4,
+            Some(_) => return Err(CompileError::Invalid("Invalid binary char.")),
+        };
+        let encoded = self
+            .bytes
+            .get(self.at..self.at + len)
+            .ok_or(CompileError::Invalid("Truncated binary."))?;
+        let c = core::str::from_utf8(encoded)
+            .ok()
+            .and_then(|s| s.chars().next())
+            .ok_or(CompileError::Invalid("Invalid binary char."))?;
+        self.at += len;
+        Ok(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+    use crate::{AnchorKind, Label, ANFA};
+    use alloc::vec;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mut machine = parse("^a(b|c)*.$").unwrap();
+        let class = Label::Class(vec![('a', 'z'), ('é', '😀')], true);
+        machine.delta.push((class, [Some(0), None]));
+        let bytes = machine.save_binary();
+        let copy = ANFA::load_binary(&bytes).unwrap();
+        assert_eq!(copy.delta, machine.delta, "Every transition is restored");
+        assert_eq!(
+            copy.automata_refs, machine.automata_refs,
+            "Every machine is restored"
+        );
+        assert!(
+            copy.delta
+                .iter()
+                .any(|(label, _)| *label == Label::Anchor(AnchorKind::Start)),
+            "Anchors are restored"
+        );
+        assert!(
+            bytes.len() < machine.to_json().len(),
+            "Binary is smaller than JSON"
+        );
+    }
+
+    #[test]
+    fn test_binary_varint() {
+        let mut machine = ANFA::new();
+        for _ in 0..300 {
+            machine.push_state();
+        }
+        machine.set_epsilon(0, 299).unwrap();
+        machine.mark_machine(0, 299).unwrap();
+        let copy = ANFA::load_binary(&machine.save_binary()).unwrap();
+        assert_eq!(copy.targets_at(0), [Some(299), None], "Varints span bytes");
+        assert!(
+            ANFA::load_binary(&[0xff; 11]).is_err(),
+            "Varints cannot overflow"
+        );
+    }
+
+    #[test]
+    fn test_binary_corruption() {
+        let bytes = parse("a(b|c)*d").unwrap().save_binary();
+        for len in 0..bytes.len() {
+            assert!(
+                ANFA::load_binary(&bytes[..len]).is_err(),
+                "Truncated input fails at {} bytes",
+                len
+            );
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(ANFA::load_binary(&trailing).is_err(), "Trailing bytes fail");
+        // two states, the first targeting state 9
+        assert!(
+            ANFA::load_binary(&[2, 0x10, 9, 0, 0]).is_err(),
+            "Targets must exist"
+        );
+        assert!(
+            ANFA::load_binary(&[1, 0x0f, 0]).is_err(),
+            "Tags must be known"
+        );
+        assert!(
+            ANFA::load_binary(&[1, 0x01, 0xff, 0]).is_err(),
+            "Chars must be UTF-8"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use crate::error::CompileError;
+use crate::{transition, Label, QId, ANFA};
+use alloc::vec;
+
+impl ANFA {
+    /// Pushes a state that never transitions and returns its id.
+    ///
+    /// Together with `set_epsilon`, `set_labeled`, `set_union`, `set_targets`, and `mark_machine`,
+    /// this builds automata the compiler operators cannot express directly. Every
+    /// setter replaces the transition of `from` and checks that each state exists.
+    /// Targets are always stored in order, so the two-target invariant of
+    /// `Transition` holds.
+    ///
+    /// ```rust
+    /// use regexxx::ANFA;
+    /// // RE a*, where q0 loops on 'a' and is also f
+    /// let mut machine = ANFA::with_capacity(1, 1);
+    /// let q0 = machine.push_state();
+    /// machine.set_labeled(q0, 'a', q0).unwrap();
+    /// machine.mark_machine(q0, q0).unwrap();
+    /// assert!(machine.is_match("aaa"));
+    /// ```
+    pub fn push_state(&mut self) -> QId {
+        self.delta.push(transition::nothing());
+        self.delta.len() - 1
+    }
+
+    /// Sets the transition of `from` to an epsilon transition to `to`.
+    pub fn set_epsilon(&mut self, from: QId, to: QId) -> Result<(), CompileError> {
+        self.check_states(&[from, to])?;
+        self.delta[from] = transition::epsilon(to);
+        Ok(())
+    }
+
+    /// Sets the transition of `from` to a transition along `c` to `to`.
+    pub fn set_labeled(&mut self, from: QId, c: char, to: QId) -> Result<(), CompileError> {
+        self.check_states(&[from, to])?;
+        self.delta[from] = transition::labeled(Label::Char(c), to);
+        Ok(())
+    }
+
+    /// Sets the transition of `from` to an epsilon transition to `a` and `b`,
+    /// preferring `a`.
+    pub fn set_union(&mut self, from: QId, a: QId, b: QId) -> Result<(), CompileError> {
+        self.check_states(&[from, a, b])?;
+        self.delta[from] = (Label::Epsilon, [Some(a), Some(b)]);
+        Ok(())
+    }
+
+    /// Sets the transition of `from` to a transition along `label` to every one
+    /// of `targets`, in order of preference.
+    ///
+    /// More than two targets are split across epsilon fan-out states with
+    /// `ANFA::max_outgoing_targets_guard`, so any number of edges can be added
+    /// without breaking the two-target invariant.
+    pub fn set_targets(
+        &mut self,
+        from: QId,
+        label: Label,
+        targets: &[QId],
+    ) -> Result<(), CompileError> {
+        self.check_states(&[from])?;
+        self.check_states(targets)?;
+        let targets = self.max_outgoing_targets_guard(targets);
+        self.delta[from] = (label, targets);
+        Ok(())
+    }
+
+    /// Pushes the machine `[q0, f]` so it becomes the top machine.
+    pub fn mark_machine(&mut self, q0: QId, f: QId) -> Result<(), CompileError> {
+        self.check_states(&[q0, f])?;
+        self.automata_refs.push([q0, f]);
+        Ok(())
+    }
+
+    /// Returns a machine accepting any of `literals`, built as a prefix trie.
+    ///
+    /// Literals sharing a prefix share its states and branch where they diverge,
+    /// so a set of keywords needs fewer states than a union of literal chains.
+    /// Each trie node is an epsilon state fanning out to one labeled state per
+    /// next char, and to `f` when a literal ends there. Branches keep the order of
+    /// `literals`. No literals accept nothing, and `""` accepts the empty string.
+    ///
+    /// ```rust
+    /// use regexxx::ANFA;
+    /// let machine = ANFA::from_literals(&["if", "in", "else"]);
+    /// assert!(machine.is_match("in"));
+    /// assert!(!machine.is_match("i"));
+    /// ```
+    pub fn from_literals(literals: &[&str]) -> ANFA {
+        // the trie, where node 0 is the root
+        let mut children: vec::Vec<vec::Vec<(char, usize)>> = vec![vec::Vec::new()];
+        let mut ends = vec![false];
+        for literal in literals.iter() {
+            let mut node = 0;
+            for c in literal.chars() {
+                node = match children[node].iter().find(|&&(label, _)| label == c) {
+                    Some(&(_, child)) => child,
+                    None => {
+                        children.push(vec::Vec::new());
+                        ends.push(false);
+                        let child = children.len() - 1;
+                        children[node].push((c, child));
+                        child
+                    }
+                };
+            }
+            ends[node] = true;
+        }
+        let mut anfa = ANFA::with_capacity(2 * children.len() + 1, 1);
+        let q0 = anfa.push_state();
+        let f = anfa.push_state();
+        let mut states = vec![q0; children.len()];
+        // children are numbered after their parent, so each node has a state before it is visited
+        for node in 0..children.len() {
+            let mut targets = vec::Vec::with_capacity(children[node].len() + 1);
+            for &(c, child) in children[node].iter() {
+                let labeled = anfa.push_state();
+                states[child] = anfa.push_state();
+                anfa.delta[labeled] = transition::labeled(Label::Char(c), states[child]);
+                targets.push(labeled);
+            }
+            if ends[node] {
+                targets.push(f);
+            }
+            let targets = anfa.max_outgoing_targets_guard(&targets);
+            anfa.delta[states[node]] = (Label::Epsilon, targets);
+        }
+        anfa.automata_refs.push([q0, f]);
+        anfa
+    }
+
+    pub(crate) fn check_states(&self, states: &[QId]) -> Result<(), CompileError> {
+        match states.iter().find(|&&q| q >= self.delta.len()) {
+            None => Ok(()),
+            Some(&q) => Err(CompileError::StateOutOfBounds(q)),
+        }
+    }
+
+    /// Returns an error when a transition targets a missing state or has a second
+    /// target without a first, or when a machine refers to a missing state.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("a|b").unwrap();
+    /// assert!(machine.check_invariants().is_ok());
+    /// machine.delta[0].1 = [None, Some(0)];
+    /// assert!(machine.check_invariants().is_err());
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), CompileError> {
+        self.check_transitions(0..self.delta.len())?;
+        for machine in self.automata_refs.iter() {
+            self.check_states(machine)?;
+        }
+        Ok(())
+    }
+
+    /// Panics when `ANFA::check_invariants` fails, to catch construction bugs
+    /// early. Only debug builds check, so release builds pay nothing.
+    #[track_caller]
+    pub fn debug_assert_invariants(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.check_invariants() {
+                panic!("ANFA invariant violated: {}", error);
+            }
+        }
+    }
+
+    /// Like `ANFA::debug_assert_invariants`, but only checks the transitions of
+    /// `states` and the top machine, so an operator can check the states it
+    /// touched without scanning `delta`.
+    #[track_caller]
+    pub(crate) fn debug_assert_touched(&self, states: &[QId]) {
+        if cfg!(debug_assertions) {
+            let top = self.top_machine().unwrap_or_default();
+            let checked = self
+                .check_states(&top)
+                .and_then(|()| self.check_transitions(states.iter().chain(top.iter()).copied()));
+            if let Err(error) = checked {
+                panic!("ANFA invariant violated: {}", error);
+            }
+        }
+    }
+
+    /// Returns an error when a transition of `states` is malformed. See
+    /// `ANFA::check_invariants`.
+    fn check_transitions(&self, states: impl Iterator<Item = QId>) -> Result<(), CompileError> {
+        for q in states {
+            self.check_states(&[q])?;
+            match self.delta[q].1 {
+                [None, Some(_)] => {
+                    return Err(CompileError::Invalid(
+                        "A second target requires a first target.",
+                    ))
+                }
+                targets => {
+                    for &target in targets.iter().flatten() {
+                        self.check_states(&[target])?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::compilers::CompilerExt;
+    use crate::error::CompileError;
+    use crate::{Label, ANFA};
+
+    #[test]
+    fn test_hand_built_literal() {
+        let mut machine = ANFA::new();
+        let q0 = machine.push_state();
+        let f = machine.push_state();
+        machine.set_labeled(q0, 'a', f).unwrap();
+        machine.mark_machine(q0, f).unwrap();
+        let expected = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(machine.delta, expected.delta, "Same states as expr_a");
+        assert_eq!(
+            machine.automata_refs, expected.automata_refs,
+            "Same machine as expr_a"
+        );
+        assert!(machine.is_match("a"), "Hand-built 'a' matches 'a'");
+        assert!(!machine.is_match("aa"), "Hand-built 'a' rejects 'aa'");
+    }
+
+    #[test]
+    fn test_hand_built_union() {
+        // RE a|b sharing one final state
+        let mut machine = ANFA::new();
+        let q0 = machine.push_state();
+        let a = machine.push_state();
+        let b = machine.push_state();
+        let f = machine.push_state();
+        machine.set_union(q0, a, b).unwrap();
+        machine.set_labeled(a, 'a', f).unwrap();
+        machine.set_labeled(b, 'b', f).unwrap();
+        machine.mark_machine(q0, f).unwrap();
+        assert!(machine.is_match("a"), "Hand-built a|b matches 'a'");
+        assert!(machine.is_match("b"), "Hand-built a|b matches 'b'");
+        machine.set_epsilon(q0, a).unwrap();
+        assert!(!machine.is_match("b"), "Setting replaces the transition");
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let mut machine = ANFA::new();
+        let q0 = machine.push_state();
+        assert_eq!(
+            machine.set_labeled(q0, 'a', 1),
+            Err(CompileError::StateOutOfBounds(1)),
+            "Targets must exist"
+        );
+        assert_eq!(
+            machine.set_union(q0, q0, 2),
+            Err(CompileError::StateOutOfBounds(2)),
+            "Both union targets must exist"
+        );
+        assert_eq!(
+            machine.set_epsilon(3, q0),
+            Err(CompileError::StateOutOfBounds(3)),
+            "The source must exist"
+        );
+        assert_eq!(
+            machine.mark_machine(q0, 1),
+            Err(CompileError::StateOutOfBounds(1)),
+            "f must exist"
+        );
+        assert!(machine.automata_refs.is_empty(), "Nothing is marked");
+    }
+
+    #[test]
+    fn test_set_targets() {
+        // RE a|b|c where q0 needs three targets
+        let mut machine = ANFA::new();
+        let q0 = machine.push_state();
+        let f = machine.push_state();
+        let branches = [
+            machine.push_state(),
+            machine.push_state(),
+            machine.push_state(),
+        ];
+        for (&q, c) in branches.iter().zip(['a', 'b', 'c']) {
+            machine.set_labeled(q, c, f).unwrap();
+        }
+        machine.set_targets(q0, Label::Epsilon, &branches).unwrap();
+        machine.mark_machine(q0, f).unwrap();
+        assert_eq!(machine.count_states(), 6, "One fan-out state is pushed");
+        assert_eq!(
+            machine.delta[5],
+            (Label::Epsilon, [Some(branches[1]), Some(branches[2])]),
+            "The fan-out state takes the last two targets"
+        );
+        let reachable = machine.reachable_from(q0);
+        assert!(
+            branches.iter().all(|&q| reachable[q]),
+            "Every target stays reachable"
+        );
+        for input in ["a", "b", "c"] {
+            assert!(machine.is_match(input), "a|b|c accepts each branch");
+        }
+        assert_eq!(
+            machine.set_targets(q0, Label::Epsilon, &[f, 9]),
+            Err(CompileError::StateOutOfBounds(9)),
+            "Every target must exist"
+        );
+    }
+
+    #[test]
+    fn test_from_literals() {
+        let machine = ANFA::from_literals(&["car", "cat", "can"]);
+        for input in ["car", "cat", "can"] {
+            assert!(machine.is_match(input), "Every literal is accepted");
+        }
+        for input in ["", "ca", "cars", "cab"] {
+            assert!(!machine.is_match(input), "Only the literals are accepted");
+        }
+        let mut naive = ANFA::new();
+        for literal in ["car", "cat", "can"] {
+            ForwardCompiler::literal_str(&mut naive, literal).unwrap();
+        }
+        naive.union_all_balanced(3).unwrap();
+        assert!(
+            machine.equivalent(&naive),
+            "The trie is the union of the literals"
+        );
+        assert!(
+            machine.count_states() < naive.count_states(),
+            "The trie shares the prefix ca"
+        );
+    }
+
+    #[test]
+    fn test_from_literals_edge_cases() {
+        let machine = ANFA::from_literals(&[]);
+        assert!(!machine.is_match(""), "No literals accept nothing");
+        let machine = ANFA::from_literals(&["", "a", "ab", "a"]);
+        for input in ["", "a", "ab"] {
+            assert!(
+                machine.is_match(input),
+                "Prefixes of literals end on trie nodes"
+            );
+        }
+        assert!(!machine.is_match("b"), "Only the literals are accepted");
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(machine.check_invariants(), Ok(()), "A literal is valid");
+        machine.delta[0].1 = [Some(9), None];
+        assert_eq!(
+            machine.check_invariants(),
+            Err(CompileError::StateOutOfBounds(9)),
+            "Targets must exist"
+        );
+        machine.delta[0].1 = [None, Some(1)];
+        assert!(
+            machine.check_invariants().is_err(),
+            "A second target requires a first"
+        );
+        machine.delta[0].1 = [Some(1), None];
+        machine.automata_refs.push([0, 9]);
+        assert_eq!(
+            machine.check_invariants(),
+            Err(CompileError::StateOutOfBounds(9)),
+            "Machines must exist"
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "ANFA invariant violated")]
+    fn test_operators_assert_invariants() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        // corrupt q0 of 'a', which becomes q0 of 'a' ⋅ 'b'
+        machine.delta[0].1 = [Some(9), None];
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        let _ = ForwardCompiler::concatenate(&mut machine);
+    }
+}
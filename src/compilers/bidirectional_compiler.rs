@@ -3,42 +3,59 @@
 use crate::compilers::coverage_compiler::CoverageCompiler;
 use crate::compilers::forward_compiler::ForwardCompiler;
 pub use crate::compilers::Compiler;
-use crate::ANFA;
+use crate::error::CompileError;
+use crate::{AnchorKind, ANFA};
 
 pub trait Compilers {
-  fn from_expr_0() -> Result<[ANFA; 2], &'static str>;
-  fn from_expr_1() -> Result<[ANFA; 2], &'static str>;
-  fn from_expr_a(c: char) -> Result<[ANFA; 2], &'static str>;
+  fn from_expr_0() -> Result<[ANFA; 2], CompileError>;
+  fn from_expr_1() -> Result<[ANFA; 2], CompileError>;
+  fn from_expr_a(c: char) -> Result<[ANFA; 2], CompileError>;
+  fn from_expr_any() -> Result<[ANFA; 2], CompileError>;
   fn expr_0(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str>;
+  ) -> Result<((), ()), CompileError>;
   fn expr_1(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str>;
+  ) -> Result<((), ()), CompileError>;
   fn expr_a(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
     c: char,
-  ) -> Result<((), ()), &'static str>;
+  ) -> Result<((), ()), CompileError>;
+  fn expr_any(
+    forward_machine: &mut ANFA,
+    coverage_machine: &mut ANFA,
+  ) -> Result<((), ()), CompileError>;
   fn concatenate(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str>;
+  ) -> Result<((), ()), CompileError>;
+  fn expr_anchor(
+    forward_machine: &mut ANFA,
+    coverage_machine: &mut ANFA,
+    kind: AnchorKind,
+  ) -> Result<((), ()), CompileError>;
+  fn expr_range(
+    forward_machine: &mut ANFA,
+    coverage_machine: &mut ANFA,
+    start: char,
+    end: char,
+  ) -> Result<((), ()), CompileError>;
   fn star(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str>;
+  ) -> Result<((), ()), CompileError>;
   fn union(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str>;
+  ) -> Result<((), ()), CompileError>;
 }
 
 pub struct BidirectionalCompiler {}
 impl Compilers for BidirectionalCompiler {
-  fn from_expr_0() -> Result<[ANFA; 2], &'static str> {
+  fn from_expr_0() -> Result<[ANFA; 2], CompileError> {
     let mut forward_machine_a = ANFA::new();
     let mut coverage_machine_a = ANFA::new();
     match [
@@ -51,7 +68,7 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
-  fn from_expr_1() -> Result<[ANFA; 2], &'static str> {
+  fn from_expr_1() -> Result<[ANFA; 2], CompileError> {
     let mut forward_machine_a = ANFA::new();
     let mut coverage_machine_a = ANFA::new();
     match [
@@ -64,7 +81,7 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
-  fn from_expr_a(c: char) -> Result<[ANFA; 2], &'static str> {
+  fn from_expr_a(c: char) -> Result<[ANFA; 2], CompileError> {
     let mut forward_machine_a = ANFA::new();
     let mut coverage_machine_a = ANFA::new();
     match [
@@ -77,10 +94,23 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
+  fn from_expr_any() -> Result<[ANFA; 2], CompileError> {
+    let mut forward_machine_a = ANFA::new();
+    let mut coverage_machine_a = ANFA::new();
+    match [
+      ForwardCompiler::expr_any(&mut forward_machine_a),
+      CoverageCompiler::expr_any(&mut coverage_machine_a),
+    ] {
+      [Ok(()), Ok(())] => Ok([forward_machine_a, coverage_machine_a]),
+      [Err(forward_machine_error), _] => Err(forward_machine_error),
+      [_, Err(coverage_machine_error)] => Err(coverage_machine_error),
+    }
+  }
+
   fn expr_0(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str> {
+  ) -> Result<((), ()), CompileError> {
     match [
       ForwardCompiler::expr_0(forward_machine),
       CoverageCompiler::expr_0(coverage_machine),
@@ -94,7 +124,7 @@ impl Compilers for BidirectionalCompiler {
   fn expr_1(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str> {
+  ) -> Result<((), ()), CompileError> {
     match [
       ForwardCompiler::expr_1(forward_machine),
       CoverageCompiler::expr_1(coverage_machine),
@@ -109,7 +139,7 @@ impl Compilers for BidirectionalCompiler {
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
     c: char,
-  ) -> Result<((), ()), &'static str> {
+  ) -> Result<((), ()), CompileError> {
     match [
       ForwardCompiler::expr_a(forward_machine, c),
       CoverageCompiler::expr_a(coverage_machine, c),
@@ -120,10 +150,55 @@ impl Compilers for BidirectionalCompiler {
     }
   }
 
+  fn expr_any(
+    forward_machine: &mut ANFA,
+    coverage_machine: &mut ANFA,
+  ) -> Result<((), ()), CompileError> {
+    match [
+      ForwardCompiler::expr_any(forward_machine),
+      CoverageCompiler::expr_any(coverage_machine),
+    ] {
+      [Ok(()), Ok(())] => Ok(((), ())),
+      [Err(forward_machine_error), _] => Err(forward_machine_error),
+      [_, Err(coverage_machine_error)] => Err(coverage_machine_error),
+    }
+  }
+
+  fn expr_anchor(
+    forward_machine: &mut ANFA,
+    coverage_machine: &mut ANFA,
+    kind: AnchorKind,
+  ) -> Result<((), ()), CompileError> {
+    match [
+      ForwardCompiler::expr_anchor(forward_machine, kind),
+      CoverageCompiler::expr_anchor(coverage_machine, kind),
+    ] {
+      [Ok(()), Ok(())] => Ok(((), ())),
+      [Err(forward_machine_error), _] => Err(forward_machine_error),
+      [_, Err(coverage_machine_error)] => Err(coverage_machine_error),
+    }
+  }
+
+  fn expr_range(
+    forward_machine: &mut ANFA,
+    coverage_machine: &mut ANFA,
+    start: char,
+    end: char,
+  ) -> Result<((), ()), CompileError> {
+    match [
+      ForwardCompiler::expr_range(forward_machine, start, end),
+      CoverageCompiler::expr_range(coverage_machine, start, end),
+    ] {
+      [Ok(()), Ok(())] => Ok(((), ())),
+      [Err(forward_machine_error), _] => Err(forward_machine_error),
+      [_, Err(coverage_machine_error)] => Err(coverage_machine_error),
+    }
+  }
+
   fn concatenate(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str> {
+  ) -> Result<((), ()), CompileError> {
     match [
       ForwardCompiler::concatenate(forward_machine),
       CoverageCompiler::concatenate(coverage_machine),
@@ -137,7 +212,7 @@ impl Compilers for BidirectionalCompiler {
   fn star(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str> {
+  ) -> Result<((), ()), CompileError> {
     match [
       ForwardCompiler::star(forward_machine),
       CoverageCompiler::star(coverage_machine),
@@ -151,7 +226,7 @@ impl Compilers for BidirectionalCompiler {
   fn union(
     forward_machine: &mut ANFA,
     coverage_machine: &mut ANFA,
-  ) -> Result<((), ()), &'static str> {
+  ) -> Result<((), ()), CompileError> {
     match [
       ForwardCompiler::union(forward_machine),
       CoverageCompiler::union(coverage_machine),
@@ -166,6 +241,7 @@ impl Compilers for BidirectionalCompiler {
 #[cfg(test)]
 mod tests {
   use crate::compilers::bidirectional_compiler::{BidirectionalCompiler, Compilers};
+  use crate::AnchorKind;
 
   #[test]
   fn test_expr_0() {
@@ -180,4 +256,63 @@ mod tests {
     BidirectionalCompiler::concatenate(&mut forward_machine, &mut coverage_machine).unwrap();
     BidirectionalCompiler::concatenate(&mut forward_machine, &mut coverage_machine).unwrap();
   }
+
+  #[test]
+  fn test_from_expr_any() {
+    let [forward_machine, coverage_machine] = BidirectionalCompiler::from_expr_any().unwrap();
+    for machine in [forward_machine, coverage_machine] {
+      assert!(machine.is_match("a"), "Expression '.' (any) accepts one char");
+      assert!(!machine.is_match(""), "Expression '.' (any) rejects ''");
+    }
+  }
+
+  #[test]
+  fn test_expr_anchor() {
+    let [mut forward_machine, mut coverage_machine] =
+      BidirectionalCompiler::from_expr_a('a').unwrap();
+    BidirectionalCompiler::expr_anchor(
+      &mut forward_machine,
+      &mut coverage_machine,
+      AnchorKind::End,
+    )
+    .unwrap();
+    BidirectionalCompiler::union(&mut forward_machine, &mut coverage_machine).unwrap();
+    for machine in [forward_machine, coverage_machine] {
+      assert!(machine.is_match("a"), "Expression 'a|$' accepts 'a'");
+      assert!(machine.is_match(""), "Expression 'a|$' accepts ''");
+    }
+  }
+
+  #[test]
+  fn test_expr_anchor_concatenate() {
+    // RE ^a
+    let [mut forward_machine, mut coverage_machine] =
+      BidirectionalCompiler::from_expr_a('a').unwrap();
+    BidirectionalCompiler::expr_anchor(
+      &mut forward_machine,
+      &mut coverage_machine,
+      AnchorKind::Start,
+    )
+    .unwrap();
+    BidirectionalCompiler::expr_a(&mut forward_machine, &mut coverage_machine, 'a').unwrap();
+    BidirectionalCompiler::concatenate(&mut forward_machine, &mut coverage_machine).unwrap();
+    for machine in [&forward_machine, &coverage_machine] {
+      assert!(machine.is_match("a"), "Expression '^a' accepts 'a'");
+      assert!(!machine.is_match("ba"), "Expression '^a' rejects 'ba'");
+    }
+
+    // RE a$
+    BidirectionalCompiler::expr_a(&mut forward_machine, &mut coverage_machine, 'a').unwrap();
+    BidirectionalCompiler::expr_anchor(
+      &mut forward_machine,
+      &mut coverage_machine,
+      AnchorKind::End,
+    )
+    .unwrap();
+    BidirectionalCompiler::concatenate(&mut forward_machine, &mut coverage_machine).unwrap();
+    for machine in [&forward_machine, &coverage_machine] {
+      assert!(machine.is_match("a"), "Expression 'a$' accepts 'a'");
+      assert!(!machine.is_match("ab"), "Expression 'a$' rejects 'ab'");
+    }
+  }
 }
@@ -1,7 +1,9 @@
 // size of QId
 // size of label
 pub use crate::compilers::Compiler;
-use crate::ANFA;
+use crate::error::CompileError;
+use crate::{AnchorKind, Label, ANFA};
+use alloc::vec;
 
 pub struct CoverageCompiler {} // ForwardCompiler, but backwards!
 impl Compiler for CoverageCompiler {
@@ -11,7 +13,7 @@ impl Compiler for CoverageCompiler {
     /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
     /// let machine = CoverageCompiler::from_expr_0().unwrap(); // always safe!
     /// ```
-    fn from_expr_0() -> Result<ANFA, &'static str> {
+    fn from_expr_0() -> Result<ANFA, CompileError> {
         let mut machine_a = ANFA::new();
         match CoverageCompiler::expr_0(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -25,7 +27,7 @@ impl Compiler for CoverageCompiler {
     /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
     /// let machine = CoverageCompiler::from_expr_1().unwrap(); // always safe!
     /// ```
-    fn from_expr_1() -> Result<ANFA, &'static str> {
+    fn from_expr_1() -> Result<ANFA, CompileError> {
         let mut machine_a = ANFA::new();
         match CoverageCompiler::expr_1(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -39,7 +41,7 @@ impl Compiler for CoverageCompiler {
     /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
     /// let mut machine = CoverageCompiler::from_expr_a('a').unwrap(); // always safe!
     /// ```
-    fn from_expr_a(c: char) -> Result<ANFA, &'static str> {
+    fn from_expr_a(c: char) -> Result<ANFA, CompileError> {
         let mut machine_a = ANFA::new();
         match CoverageCompiler::expr_a(&mut machine_a, c) {
             Ok(()) => Ok(machine_a),
@@ -47,6 +49,20 @@ impl Compiler for CoverageCompiler {
         }
     }
 
+    /// Returns a new ANFA that transitions to a final state on any char.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    /// let machine = CoverageCompiler::from_expr_any().unwrap(); // always safe!
+    /// ```
+    fn from_expr_any() -> Result<ANFA, CompileError> {
+        let mut machine_a = ANFA::new();
+        match CoverageCompiler::expr_any(&mut machine_a) {
+            Ok(()) => Ok(machine_a),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Pushes an acceptor that never transitions, i.e. accept nothing
     ///
     /// ```rust
@@ -72,18 +88,18 @@ impl Compiler for CoverageCompiler {
     /// Graph:
     /// --> ( 1 )  (( 0 ))
     /// ```
-    fn expr_0(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_0(anfa: &mut ANFA) -> Result<(), CompileError> {
         let f = anfa.delta.len();
         let q0 = f + 1;
         let machine_a = [q0, f];
         anfa.delta.push((
             // push non-transitioning state
-            None,
+            Label::Epsilon,
             [None, None],
         ));
         anfa.delta.push((
             // push final state
-            None,
+            Label::Epsilon,
             [None, None],
         ));
         anfa.automata_refs.push(machine_a);
@@ -114,13 +130,13 @@ impl Compiler for CoverageCompiler {
     /// Graph:
     /// --> (( 0 ))
     /// ```
-    fn expr_1(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_1(anfa: &mut ANFA) -> Result<(), CompileError> {
         let q0 = anfa.delta.len();
         let f = q0;
         let machine_a = [q0, f];
         anfa.delta.push((
             // push final state
-            None,
+            Label::Epsilon,
             [None, None],
         ));
         anfa.automata_refs.push(machine_a);
@@ -152,18 +168,160 @@ impl Compiler for CoverageCompiler {
     /// Graph:
     /// --> ( 1 ) -- 'a' --> (( 0 ))
     /// ```
-    fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), &'static str> {
+    fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), CompileError> {
         let f = anfa.delta.len();
         let q0 = f + 1;
         let machine_a = [q0, f];
         anfa.delta.push((
             // push final state
-            None,
+            Label::Epsilon,
             [None, None],
         ));
         anfa.delta.push((
             // push transition to Q `f` along Label `c`
-            Some(c),
+            Label::Char(c),
+            [Some(f), None],
+        ));
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
+    /// Pushes an automaton that transitions to a final state on any char, i.e. `.`
+    ///
+    /// ```rust
+    /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    /// let mut machine = CoverageCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// match CoverageCompiler::expr_any(&mut machine) {
+    ///     Ok(()) => {}
+    ///     Err(err) => {
+    ///       println!("expr_any error: {}", err);
+    ///     }
+    /// };
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `.`:
+    ///
+    /// State table:
+    /// | Q | T | Q |
+    /// |---|---|---|
+    /// | 0 |   |   | (f)
+    /// | 1 | . | 0 | (q0)
+    ///
+    /// Graph:
+    /// --> ( 1 ) -- '.' --> (( 0 ))
+    /// ```
+    fn expr_any(anfa: &mut ANFA) -> Result<(), CompileError> {
+        let f = anfa.delta.len();
+        let q0 = f + 1;
+        let machine_a = [q0, f];
+        anfa.delta.push((
+            // push final state
+            Label::Epsilon,
+            [None, None],
+        ));
+        anfa.delta.push((
+            // push transition to Q `f` along any Label
+            Label::Any,
+            [Some(f), None],
+        ));
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
+    /// Pushes an automaton that transitions to a final state without consuming
+    /// input, but only at the start or end of the input, i.e. `^` or `$`. The
+    /// coverage machine reads the input backwards, so the anchor is mirrored:
+    /// `^` is pushed as `$` and `$` as `^`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    /// use regexxx::AnchorKind;
+    /// let mut machine = CoverageCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// match CoverageCompiler::expr_anchor(&mut machine, AnchorKind::End) {
+    ///     Ok(()) => {}
+    ///     Err(err) => {
+    ///       println!("expr_anchor error: {}", err);
+    ///     }
+    /// };
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `$`:
+    ///
+    /// State table:
+    /// | Q | T | Q |
+    /// |---|---|---|
+    /// | 0 |   |   | (f)
+    /// | 1 | ^ | 0 | (q0)
+    ///
+    /// Graph:
+    /// --> ( 1 ) -- '^' --> (( 0 ))
+    /// ```
+    fn expr_anchor(anfa: &mut ANFA, kind: AnchorKind) -> Result<(), CompileError> {
+        let f = anfa.delta.len();
+        let q0 = f + 1;
+        let machine_a = [q0, f];
+        anfa.delta.push((
+            // push final state
+            Label::Epsilon,
+            [None, None],
+        ));
+        anfa.delta.push((
+            // push zero-width transition to Q `f` at the mirrored position
+            Label::Anchor(match kind {
+                AnchorKind::Start => AnchorKind::End,
+                AnchorKind::End => AnchorKind::Start,
+            }),
+            [Some(f), None],
+        ));
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
+    /// Pushes an automaton that transitions to a final state on any char from
+    /// `start` to `end` inclusive, i.e. `[start-end]`. `start` must not exceed `end`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    /// let mut machine = CoverageCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// match CoverageCompiler::expr_range(&mut machine, '0', '9') {
+    ///     Ok(()) => {}
+    ///     Err(err) => {
+    ///       println!("expr_range error: {}", err);
+    ///     }
+    /// };
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `[0-9]`:
+    ///
+    /// State table:
+    /// | Q | T     | Q |
+    /// |---|-------|---|
+    /// | 0 |       |   | (f)
+    /// | 1 | [0-9] | 0 | (q0)
+    ///
+    /// Graph:
+    /// --> ( 1 ) -- '[0-9]' --> (( 0 ))
+    /// ```
+    fn expr_range(anfa: &mut ANFA, start: char, end: char) -> Result<(), CompileError> {
+        if start > end {
+            return Err(CompileError::Invalid(
+                "Range start must not exceed its end.",
+            ));
+        }
+        let f = anfa.delta.len();
+        let q0 = f + 1;
+        let machine_a = [q0, f];
+        anfa.delta.push((
+            // push final state
+            Label::Epsilon,
+            [None, None],
+        ));
+        anfa.delta.push((
+            // push transition to Q `f` along any char in the range
+            Label::Class(vec![(start, end)], false),
             [Some(f), None],
         ));
         anfa.automata_refs.push(machine_a);
@@ -205,31 +363,37 @@ impl Compiler for CoverageCompiler {
     /// Expression 'a' ⋅ 'b'
     /// --> ( 3 ) -- 'b' --> ( 2 ) -- ε --> ( 1 ) -- 'a' --> (( 0 ))
     /// ```
-    fn concatenate(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn concatenate(anfa: &mut ANFA) -> Result<(), CompileError> {
         match anfa.automata_refs.len() {
             0 | 1 => {
-                return Err("Concatenation requires two operands.");
+                return Err(CompileError::Invalid(
+                    "Concatenation requires two operands.",
+                ));
             }
             _ => {}
         };
         let [machine_b_q0, machine_b_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Concatenation requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Concatenation requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_b) => machine_b,
         };
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Concatenation requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Concatenation requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_a) => machine_a,
         };
         let machine_c = [machine_b_q0, machine_a_f];
         anfa.delta[machine_b_f] = (
             // point 'b' at 'a'
-            None,
+            Label::Epsilon,
             [Some(machine_a_q0), None],
         );
         anfa.automata_refs.push(machine_c);
@@ -271,17 +435,16 @@ impl Compiler for CoverageCompiler {
     /// --> ( 4 ) -- ε --> ( 3 ) <------------ ε ------------|
     ///                        \-- 1 --> (( 2 ))
     /// ```
-    fn star(anfa: &mut ANFA) -> Result<(), &'static str> {
-        match anfa.automata_refs.len() {
-            0 => {
-                return Err("Star requires one operand.");
-            }
-            _ => {}
-        };
+    fn star(anfa: &mut ANFA) -> Result<(), CompileError> {
+        if anfa.automata_refs.is_empty() {
+            return Err(CompileError::Invalid("Star requires one operand."));
+        }
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Star requires one operand. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Star requires one operand. (Race condition.)",
+                ));
             }
             Some(machine_a) => machine_a,
         };
@@ -291,22 +454,22 @@ impl Compiler for CoverageCompiler {
         let machine_b = [machine_b_q0, machine_b_f];
         anfa.delta.push((
             // push final state
-            None,
+            Label::Epsilon,
             [None, None],
         ));
         anfa.delta.push((
             // push union of machine_a and final state
-            None,
+            Label::Epsilon,
             [Some(machine_a_q0), Some(machine_b_f)],
         ));
         anfa.delta.push((
             // push epsilon transition to union
-            None,
+            Label::Epsilon,
             [Some(machine_b_q), None],
         ));
         anfa.delta[machine_a_f] = (
             // point machine_a at union
-            None,
+            Label::Epsilon,
             [Some(machine_b_q), None],
         );
         anfa.automata_refs.push(machine_b);
@@ -351,11 +514,11 @@ impl Compiler for CoverageCompiler {
     /// --> ( 5 )                                    ε --> (( 4 ))
     ///         \ -- 1 --> ( 3 ) -- 'b' --> ( 2 ) --/
     /// ```
-    fn union(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn union(anfa: &mut ANFA) -> Result<(), CompileError> {
         let machine_c_f = anfa.delta.len();
         match machine_c_f {
             0 | 1 => {
-                return Err("Union requires two operands.");
+                return Err(CompileError::Invalid("Union requires two operands."));
             }
             _ => {}
         };
@@ -364,35 +527,39 @@ impl Compiler for CoverageCompiler {
         let [machine_b_q0, machine_b_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Union requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Union requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_b) => machine_b,
         };
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Union requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Union requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_a) => machine_a,
         };
         anfa.delta.push((
             // push final state
-            None,
+            Label::Epsilon,
             [None, None],
         ));
         anfa.delta.push((
             // push union transition
-            None,
+            Label::Epsilon,
             [Some(machine_a_q0), Some(machine_b_q0)],
         ));
         anfa.delta[machine_a_f] = (
             // point machine_a at machine_c
-            None,
+            Label::Epsilon,
             [Some(machine_c_f), None],
         );
         anfa.delta[machine_b_f] = (
             // point machine_b at machine_c
-            None,
+            Label::Epsilon,
             [Some(machine_c_f), None],
         );
         anfa.automata_refs.push(machine_c);
@@ -403,6 +570,8 @@ impl Compiler for CoverageCompiler {
 #[cfg(test)]
 mod tests {
     use crate::compilers::coverage_compiler::{Compiler, CoverageCompiler};
+    use crate::{AnchorKind, Label};
+    use alloc::vec;
 
     #[test]
     fn test_expr_0() {
@@ -420,12 +589,12 @@ mod tests {
         let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_a_q0],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 0 (nothing) cannot transition from q0"
         );
         assert_eq!(
             machine.delta[machine_a_f],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 0 (nothing) cannot transition from f"
         );
         assert_ne!(
@@ -463,7 +632,7 @@ mod tests {
         );
         assert_eq!(
             machine.delta[0],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 1 (epsilon) does not transition from q0"
         );
         let [machine_a_q0, machine_a_f] = &machine.automata_refs[machine.automata_refs.len() - 1];
@@ -502,12 +671,12 @@ mod tests {
         let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_a_q0],
-            (Some('a'), [Some(machine_a_f), None]),
+            (Label::Char('a'), [Some(machine_a_f), None]),
             "Expression 'a' (literal) transitions from q0 to f along 'a'"
         );
         assert_eq!(
             machine.delta[machine_a_f],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 'a' (literal) cannot transition from f"
         );
         assert_ne!(
@@ -528,6 +697,119 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_expr_any() {
+        let mut machine = CoverageCompiler::from_expr_any().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Expression '.' (any) pushes one automata ref"
+        );
+        assert_eq!(
+            machine.delta.len(),
+            2,
+            "Expression '.' (any) pushes two states"
+        );
+        let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        assert_eq!(
+            machine.delta[machine_a_q0],
+            (Label::Any, [Some(machine_a_f), None]),
+            "Expression '.' (any) transitions from q0 to f along any char"
+        );
+        for input in ["a", "z", "😀"] {
+            assert!(
+                machine.is_match(input),
+                "Expression '.' (any) accepts one char"
+            );
+        }
+        assert!(!machine.is_match(""), "Expression '.' (any) rejects ''");
+        assert!(
+            !machine.is_match("ab"),
+            "Expression '.' (any) rejects two chars"
+        );
+        // run twice to make sure pushing expressions isn't affected by prior pushed expressions
+        CoverageCompiler::expr_any(&mut machine).unwrap();
+        assert_eq!(
+            machine.delta.len(),
+            4,
+            "(Repeated) Expression '.' (any) pushes two states"
+        );
+    }
+
+    #[test]
+    fn test_expr_anchor() {
+        let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
+        CoverageCompiler::expr_anchor(&mut machine, AnchorKind::Start).unwrap();
+        let [machine_b_q0, machine_b_f] = machine.automata_refs[1];
+        assert_eq!(
+            machine.delta[machine_b_q0],
+            (Label::Anchor(AnchorKind::End), [Some(machine_b_f), None]),
+            "Expression '^' (anchor) is mirrored to '$' in the coverage machine"
+        );
+        assert!(machine.is_match(""), "Expression '^' (anchor) accepts ''");
+        assert!(
+            !machine.is_match("a"),
+            "Expression '^' (anchor) rejects 'a'"
+        );
+
+        // RE ^a
+        CoverageCompiler::expr_a(&mut machine, 'a').unwrap();
+        CoverageCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("a"), "Expression '^a' accepts 'a'");
+        assert!(!machine.is_match("ba"), "Expression '^a' rejects 'ba'");
+
+        // RE a$
+        CoverageCompiler::expr_a(&mut machine, 'a').unwrap();
+        CoverageCompiler::expr_anchor(&mut machine, AnchorKind::End).unwrap();
+        CoverageCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("a"), "Expression 'a$' accepts 'a'");
+        assert!(!machine.is_match("ab"), "Expression 'a$' rejects 'ab'");
+    }
+
+    #[test]
+    fn test_expr_range() {
+        let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
+        CoverageCompiler::expr_range(&mut machine, '0', '9').unwrap();
+        let [machine_b_q0, machine_b_f] = machine.automata_refs[1];
+        assert_eq!(
+            machine.delta[machine_b_q0],
+            (
+                Label::Class(vec![('0', '9')], false),
+                [Some(machine_b_f), None]
+            ),
+            "Expression [0-9] (range) transitions from q0 to f along the range"
+        );
+        for input in ["0", "5", "9"] {
+            assert!(
+                machine.is_match(input),
+                "Expression [0-9] (range) accepts {:?}",
+                input
+            );
+        }
+        assert!(
+            !machine.is_match("a"),
+            "Expression [0-9] (range) rejects 'a'"
+        );
+        assert!(
+            !machine.is_match("55"),
+            "Expression [0-9] (range) rejects two chars"
+        );
+        assert!(
+            CoverageCompiler::expr_range(&mut machine, '9', '0').is_err(),
+            "Expression [9-0] (range) is invalid"
+        );
+        assert_eq!(
+            machine.automata_refs.len(),
+            2,
+            "Invalid ranges push nothing"
+        );
+        CoverageCompiler::expr_range(&mut machine, 'x', 'x').unwrap();
+        assert!(
+            machine.is_match("x"),
+            "Expression [x-x] (range) accepts 'x'"
+        );
+    }
+
     #[test]
     fn test_concatenate() {
         let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
@@ -558,11 +840,51 @@ mod tests {
         let [_machine_c_q0, _machine_c_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_b_f],
-            (None, [Some(machine_a_q0), None]),
+            (Label::Epsilon, [Some(machine_a_q0), None]),
             "Concatenation transitions machine_b to machine_a along epsilon"
         );
     }
 
+    #[test]
+    fn test_concatenate_epsilon_and_nothing() {
+        // RE ()b
+        let mut machine = CoverageCompiler::from_expr_1().unwrap();
+        CoverageCompiler::expr_a(&mut machine, 'b').unwrap();
+        CoverageCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("b"), "Epsilon ⋅ 'b' accepts 'b'");
+        assert!(!machine.is_match(""), "Epsilon ⋅ 'b' rejects ''");
+        assert!(!machine.is_match("bb"), "Epsilon ⋅ 'b' rejects 'bb'");
+        // RE b()
+        let mut machine = CoverageCompiler::from_expr_a('b').unwrap();
+        CoverageCompiler::expr_1(&mut machine).unwrap();
+        CoverageCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("b"), "'b' ⋅ epsilon accepts 'b'");
+        assert!(!machine.is_match(""), "'b' ⋅ epsilon rejects ''");
+        // RE ()()
+        let mut machine = CoverageCompiler::from_expr_1().unwrap();
+        CoverageCompiler::expr_1(&mut machine).unwrap();
+        CoverageCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match(""), "Epsilon ⋅ epsilon accepts ''");
+        assert!(!machine.is_match("b"), "Epsilon ⋅ epsilon rejects 'b'");
+        // RE []b and b[]
+        for nothing_first in [true, false] {
+            let mut machine = CoverageCompiler::from_expr_a('b').unwrap();
+            CoverageCompiler::expr_0(&mut machine).unwrap();
+            if nothing_first {
+                machine.automata_refs.swap(0, 1);
+            }
+            CoverageCompiler::concatenate(&mut machine).unwrap();
+            for input in ["", "b", "bb"] {
+                assert!(
+                    !machine.is_match(input),
+                    "Concatenation with nothing accepts nothing, not {:?}",
+                    input
+                );
+            }
+            assert!(machine.to_dfa().is_empty(), "Language is empty");
+        }
+    }
+
     #[test]
     fn test_star() {
         let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
@@ -588,7 +910,7 @@ mod tests {
         let machine_b_intermediary_q = machine.delta[machine_a_f].1[0].unwrap();
         assert_eq!(
             machine.delta[machine_b_intermediary_q],
-            (None, [Some(machine_a_q0), Some(machine_b_f)]),
+            (Label::Epsilon, [Some(machine_a_q0), Some(machine_b_f)]),
             "(1) New intermediary state is a union of machine_a's q0 and new f, \
              (2) machine_a's f transtions to new intermediary state along epsilon"
         );
@@ -626,17 +948,17 @@ mod tests {
         let [machine_c_q0, machine_c_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_c_q0],
-            (None, [Some(machine_a_q0), Some(machine_b_q0)]),
+            (Label::Epsilon, [Some(machine_a_q0), Some(machine_b_q0)]),
             "q0 of machine_c transitions to q0 of machine_a and machine_b along epsilon"
         );
         assert_eq!(
             machine.delta[machine_a_f],
-            (None, [Some(machine_c_f), None]),
+            (Label::Epsilon, [Some(machine_c_f), None]),
             "f of machine_a transitions to f of machine_c along epsilon"
         );
         assert_eq!(
             machine.delta[machine_b_f],
-            (None, [Some(machine_c_f), None]),
+            (Label::Epsilon, [Some(machine_c_f), None]),
             "f of machine_b transitions to f of machine_c along epsilon"
         );
     }
@@ -1,7 +1,10 @@
 // size of QId
 // size of label
 pub use crate::compilers::Compiler;
-use crate::ANFA;
+use crate::error::CompileError;
+use crate::{transition, AnchorKind, AutomataRef, Label, QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::vec;
 
 pub struct ForwardCompiler {}
 impl Compiler for ForwardCompiler {
@@ -11,7 +14,7 @@ impl Compiler for ForwardCompiler {
     /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
     /// let machine = ForwardCompiler::from_expr_0().unwrap(); // always safe!
     /// ```
-    fn from_expr_0() -> Result<ANFA, &'static str> {
+    fn from_expr_0() -> Result<ANFA, CompileError> {
         let mut machine_a = ANFA::new();
         match ForwardCompiler::expr_0(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -25,7 +28,7 @@ impl Compiler for ForwardCompiler {
     /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
     /// let machine = ForwardCompiler::from_expr_1().unwrap(); // always safe!
     /// ```
-    fn from_expr_1() -> Result<ANFA, &'static str> {
+    fn from_expr_1() -> Result<ANFA, CompileError> {
         let mut machine_a = ANFA::new();
         match ForwardCompiler::expr_1(&mut machine_a) {
             Ok(()) => Ok(machine_a),
@@ -39,7 +42,7 @@ impl Compiler for ForwardCompiler {
     /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
     /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap(); // always safe!
     /// ```
-    fn from_expr_a(c: char) -> Result<ANFA, &'static str> {
+    fn from_expr_a(c: char) -> Result<ANFA, CompileError> {
         let mut machine_a = ANFA::new();
         match ForwardCompiler::expr_a(&mut machine_a, c) {
             Ok(()) => Ok(machine_a),
@@ -47,6 +50,20 @@ impl Compiler for ForwardCompiler {
         }
     }
 
+    /// Returns a new ANFA that transitions to a final state on any char.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_any().unwrap(); // always safe!
+    /// ```
+    fn from_expr_any() -> Result<ANFA, CompileError> {
+        let mut machine_a = ANFA::new();
+        match ForwardCompiler::expr_any(&mut machine_a) {
+            Ok(()) => Ok(machine_a),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Pushes an acceptor that never transitions, i.e. accept nothing
     ///
     /// ```rust
@@ -72,20 +89,14 @@ impl Compiler for ForwardCompiler {
     /// Graph:
     /// --> ( 0 )  (( 1 ))
     /// ```
-    fn expr_0(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_0(anfa: &mut ANFA) -> Result<(), CompileError> {
         let q0 = anfa.delta.len();
         let f = q0 + 1;
         let machine_a = [q0, f];
-        anfa.delta.push((
-            // push non-transitioning state
-            None,
-            [None, None],
-        ));
-        anfa.delta.push((
-            // push final state
-            None,
-            [None, None],
-        ));
+        // push non-transitioning state
+        anfa.delta.push(transition::nothing());
+        // push final state
+        anfa.delta.push(transition::nothing());
         anfa.automata_refs.push(machine_a);
         Ok(())
     }
@@ -114,15 +125,12 @@ impl Compiler for ForwardCompiler {
     /// Graph:
     /// --> (( 0 ))
     /// ```
-    fn expr_1(anfa: &mut ANFA) -> Result<(), &'static str> {
+    fn expr_1(anfa: &mut ANFA) -> Result<(), CompileError> {
         let q0 = anfa.delta.len();
         let f = q0;
         let machine_a = [q0, f];
-        anfa.delta.push((
-            // push final state
-            None,
-            [None, None],
-        ));
+        // push final state
+        anfa.delta.push(transition::nothing());
         anfa.automata_refs.push(machine_a);
         Ok(())
     }
@@ -152,20 +160,136 @@ impl Compiler for ForwardCompiler {
     /// Graph:
     /// --> ( 0 ) -- 'a' --> (( 1 ))
     /// ```
-    fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), &'static str> {
+    fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), CompileError> {
         let q0 = anfa.delta.len();
         let f = q0 + 1;
         let machine_a = [q0, f];
-        anfa.delta.push((
-            // push transition to Q `f` along Label `c`
-            Some(c),
-            [Some(f), None],
-        ));
-        anfa.delta.push((
-            // push final state
-            None,
-            [None, None],
+        // push transition to Q `f` along Label `c`
+        anfa.delta.push(transition::labeled(Label::Char(c), f));
+        // push final state
+        anfa.delta.push(transition::nothing());
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
+    /// Pushes an automaton that transitions to a final state on any char, i.e. `.`
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// match ForwardCompiler::expr_any(&mut machine) {
+    ///     Ok(()) => {}
+    ///     Err(err) => {
+    ///       println!("expr_any error: {}", err);
+    ///     }
+    /// };
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `.`:
+    ///
+    /// State table:
+    /// | Q | T | Q |
+    /// |---|---|---|
+    /// | 0 | . | 1 | (q0)
+    /// | 1 |   |   | (f)
+    ///
+    /// Graph:
+    /// --> ( 0 ) -- '.' --> (( 1 ))
+    /// ```
+    fn expr_any(anfa: &mut ANFA) -> Result<(), CompileError> {
+        let q0 = anfa.delta.len();
+        let f = q0 + 1;
+        let machine_a = [q0, f];
+        // push transition to Q `f` along any Label
+        anfa.delta.push(transition::labeled(Label::Any, f));
+        // push final state
+        anfa.delta.push(transition::nothing());
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
+    /// Pushes an automaton that transitions to a final state without consuming
+    /// input, but only at the start or end of the input, i.e. `^` or `$`
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// use regexxx::AnchorKind;
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// match ForwardCompiler::expr_anchor(&mut machine, AnchorKind::End) {
+    ///     Ok(()) => {}
+    ///     Err(err) => {
+    ///       println!("expr_anchor error: {}", err);
+    ///     }
+    /// };
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `$`:
+    ///
+    /// State table:
+    /// | Q | T | Q |
+    /// |---|---|---|
+    /// | 0 | $ | 1 | (q0)
+    /// | 1 |   |   | (f)
+    ///
+    /// Graph:
+    /// --> ( 0 ) -- '$' --> (( 1 ))
+    /// ```
+    fn expr_anchor(anfa: &mut ANFA, kind: AnchorKind) -> Result<(), CompileError> {
+        let q0 = anfa.delta.len();
+        let f = q0 + 1;
+        let machine_a = [q0, f];
+        // push zero-width transition to Q `f` at the anchored position
+        anfa.delta.push(transition::labeled(Label::Anchor(kind), f));
+        // push final state
+        anfa.delta.push(transition::nothing());
+        anfa.automata_refs.push(machine_a);
+        Ok(())
+    }
+
+    /// Pushes an automaton that transitions to a final state on any char from
+    /// `start` to `end` inclusive, i.e. `[start-end]`. `start` must not exceed `end`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap(); // always safe!
+    /// match ForwardCompiler::expr_range(&mut machine, '0', '9') {
+    ///     Ok(()) => {}
+    ///     Err(err) => {
+    ///       println!("expr_range error: {}", err);
+    ///     }
+    /// };
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `[0-9]`:
+    ///
+    /// State table:
+    /// | Q | T     | Q |
+    /// |---|-------|---|
+    /// | 0 | [0-9] | 1 | (q0)
+    /// | 1 |       |   | (f)
+    ///
+    /// Graph:
+    /// --> ( 0 ) -- '[0-9]' --> (( 1 ))
+    /// ```
+    fn expr_range(anfa: &mut ANFA, start: char, end: char) -> Result<(), CompileError> {
+        if start > end {
+            return Err(CompileError::Invalid(
+                "Range start must not exceed its end.",
+            ));
+        }
+        let q0 = anfa.delta.len();
+        let f = q0 + 1;
+        let machine_a = [q0, f];
+        // push transition to Q `f` along any char in the range
+        anfa.delta.push(transition::labeled(
+            Label::Class(vec![(start, end)], false),
+            f,
         ));
+        // push final state
+        anfa.delta.push(transition::nothing());
         anfa.automata_refs.push(machine_a);
         Ok(())
     }
@@ -212,34 +336,78 @@ impl Compiler for ForwardCompiler {
     /// machine_n = (machine_a ⋅ machine_b) ⋅ machine_c
     /// machine_n = machine_a ⋅ (machine_b ⋅ machine_c)
     /// ```
-    fn concatenate(anfa: &mut ANFA) -> Result<(), &'static str> {
+    ///
+    /// The transition of `machine_a_f` is overwritten. Machines built by the
+    /// compiler never transition from `f`, but if `delta[machine_a_f]` was
+    /// edited, its transition is lost. See `ANFA::concat_into_existing`.
+    ///
+    /// A machine has one `f`. Every accepting state of a machine with many, e.g.
+    /// one imported by `ANFA::from_dfa_table`, transitions to `f` along epsilon,
+    /// so bridging `machine_a_f` to `machine_b_q0` routes all of them to 'b'.
+    ///
+    /// Nothing absorbs concatenation, `x ⋅ 0 = 0 ⋅ x = 0`, so when either operand
+    /// is built like `Compiler::expr_0`, see `is_expr_0`, it is pushed as the
+    /// result and no transition is overwritten. Likewise `x ⋅ 1 = x`: when 'b' is
+    /// built like `Compiler::expr_1`, 'a' is pushed, and the state of 'b' is
+    /// popped when it is the last state and nothing else refers to it, i.e. it is
+    /// not a state of 'a', of another machine, or the target of a transition.
+    /// Otherwise the unused state is left in place.
+    fn concatenate(anfa: &mut ANFA) -> Result<(), CompileError> {
         match anfa.automata_refs.len() {
             0 | 1 => {
-                return Err("Concatenation requires two operands.");
+                return Err(CompileError::Invalid(
+                    "Concatenation requires two operands.",
+                ));
             }
             _ => {}
         };
         let [machine_b_q0, machine_b_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Concatenation requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Concatenation requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_b) => machine_b,
         };
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Concatenation requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Concatenation requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_a) => machine_a,
         };
+        for machine in [[machine_a_q0, machine_a_f], [machine_b_q0, machine_b_f]] {
+            if is_expr_0(anfa, machine) {
+                anfa.automata_refs.push(machine);
+                return Ok(());
+            }
+        }
+        if accepts_only_epsilon(anfa, [machine_b_q0, machine_b_f]) {
+            let referenced = machine_b_q0 == machine_a_q0
+                || machine_b_q0 == machine_a_f
+                || anfa
+                    .automata_refs
+                    .iter()
+                    .any(|machine| machine.contains(&machine_b_q0))
+                || anfa
+                    .delta
+                    .iter()
+                    .any(|state| transition::targets(state).any(|q| q == machine_b_q0));
+            if machine_b_q0 + 1 == anfa.delta.len() && !referenced {
+                // pop the state of 'b', nothing else refers to it
+                anfa.delta.pop();
+            }
+            anfa.automata_refs.push([machine_a_q0, machine_a_f]);
+            return Ok(());
+        }
         let machine_c = [machine_a_q0, machine_b_f];
-        anfa.delta[machine_a_f] = (
-            // point 'a' at 'b'
-            None,
-            [Some(machine_b_q0), None],
-        );
+        // point 'a' at 'b'
+        anfa.delta[machine_a_f] = transition::epsilon(machine_b_q0);
         anfa.automata_refs.push(machine_c);
+        anfa.debug_assert_touched(&[machine_a_f]);
         Ok(())
     }
 
@@ -277,45 +445,41 @@ impl Compiler for ForwardCompiler {
     /// --> ( 2 ) -- ε --> ( 3 ) <------------ ε ------------|
     ///                         \-- 1 --> (( 4 ))
     /// ```
-    fn star(anfa: &mut ANFA) -> Result<(), &'static str> {
-        match anfa.automata_refs.len() {
-            0 => {
-                return Err("Star requires one operand.");
-            }
-            _ => {}
-        };
+    ///
+    /// Star is idempotent, `(a*)* = a*`, so a machine built by `star` is pushed
+    /// back unchanged.
+    fn star(anfa: &mut ANFA) -> Result<(), CompileError> {
+        if anfa.automata_refs.is_empty() {
+            return Err(CompileError::Invalid("Star requires one operand."));
+        }
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Star requires one operand. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Star requires one operand. (Race condition.)",
+                ));
             }
             Some(machine_a) => machine_a,
         };
+        if is_star(anfa, [machine_a_q0, machine_a_f]) {
+            anfa.automata_refs.push([machine_a_q0, machine_a_f]);
+            return Ok(());
+        }
         let machine_b_q0 = anfa.delta.len();
         let machine_b_q = machine_b_q0 + 1;
         let machine_b_f = machine_b_q0 + 2;
         let machine_b = [machine_b_q0, machine_b_f];
-        anfa.delta.push((
-            // push epsilon transition to union
-            None,
-            [Some(machine_b_q), None],
-        ));
-        anfa.delta.push((
-            // push union of machine_a and final state
-            None,
-            [Some(machine_a_q0), Some(machine_b_f)],
-        ));
-        anfa.delta.push((
-            // push final state
-            None,
-            [None, None],
-        ));
-        anfa.delta[machine_a_f] = (
-            // point machine_a at union
-            None,
-            [Some(machine_b_q), None],
-        );
+        // push epsilon transition to union
+        anfa.delta.push(transition::epsilon(machine_b_q));
+        // push union of machine_a and final state
+        anfa.delta
+            .push(transition::union([Some(machine_a_q0), Some(machine_b_f)])?);
+        // push final state
+        anfa.delta.push(transition::nothing());
+        // point machine_a at union
+        anfa.delta[machine_a_f] = transition::epsilon(machine_b_q);
         anfa.automata_refs.push(machine_b);
+        anfa.debug_assert_touched(&[machine_a_f, machine_b_q]);
         Ok(())
     }
 
@@ -342,7 +506,6 @@ impl Compiler for ForwardCompiler {
     /// | 2 | b | 3    |
     /// | 3 | ε | 5    |
     /// | 4 | ε | 0, 2 | (q0)
-    /// | 4 | ε | 2    | (q0)
     /// | 5 |   |      | (f)
     ///
     /// Graph:
@@ -357,11 +520,22 @@ impl Compiler for ForwardCompiler {
     /// ( 4 )                                    ε --> (( 5 ))
     ///     \ -- 1 --> ( 2 ) -- 'b' --> ( 3 ) --/
     /// ```
-    fn union(anfa: &mut ANFA) -> Result<(), &'static str> {
+    ///
+    /// 'a', the machine pushed first and popped last, always takes `targets[0]`
+    /// of the new `q0`. Priority-ordered simulation, see `Matcher::find_first` and
+    /// `CompiledRegex::captures`, therefore prefers the left alternative, as in PCRE.
+    ///
+    /// Nothing is the identity of union, `x ∪ 0 = 0 ∪ x = x`, so when one operand
+    /// accepts nothing, see `accepts_nothing`, the other is pushed as the result.
+    /// Union is also idempotent, `x ∪ x = x`: when both operands have the same
+    /// structure, 'a' is pushed. The states of the discarded operand are left in
+    /// `delta`, unreachable, because callers may still refer to them, e.g. the
+    /// groups of `parser::parse_groups`.
+    fn union(anfa: &mut ANFA) -> Result<(), CompileError> {
         let machine_c_q0 = anfa.delta.len();
         match machine_c_q0 {
             0 | 1 => {
-                return Err("Union requires two operands.");
+                return Err(CompileError::Invalid("Union requires two operands."));
             }
             _ => {}
         };
@@ -370,45 +544,146 @@ impl Compiler for ForwardCompiler {
         let [machine_b_q0, machine_b_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Union requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Union requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_b) => machine_b,
         };
         let [machine_a_q0, machine_a_f] = match anfa.automata_refs.pop() {
             None => {
                 // exhaustive sanity check, should be impossible
-                return Err("Union requires two operands. (Race condition.)");
+                return Err(CompileError::Invalid(
+                    "Union requires two operands. (Race condition.)",
+                ));
             }
             Some(machine_a) => machine_a,
         };
-        anfa.delta.push((
-            // push union transition
-            None,
-            [Some(machine_a_q0), Some(machine_b_q0)],
-        ));
-        anfa.delta.push((
-            // push final state
-            None,
-            [None, None],
-        ));
-        anfa.delta[machine_a_f] = (
-            // point machine_a at machine_c
-            None,
-            [Some(machine_c_f), None],
-        );
-        anfa.delta[machine_b_f] = (
-            // point machine_b at machine_c
-            None,
-            [Some(machine_c_f), None],
-        );
+        let machine_a = [machine_a_q0, machine_a_f];
+        let machine_b = [machine_b_q0, machine_b_f];
+        for (nothing, other) in [(machine_b, machine_a), (machine_a, machine_b)] {
+            if accepts_nothing(anfa, nothing) {
+                anfa.automata_refs.push(other);
+                return Ok(());
+            }
+        }
+        if machine_a == machine_b {
+            anfa.automata_refs.push(machine_a);
+            return Ok(());
+        }
+        if same_structure(anfa, machine_a, machine_b) {
+            anfa.automata_refs.push(machine_a);
+            return Ok(());
+        }
+        // push union transition
+        anfa.delta
+            .push(transition::union([Some(machine_a_q0), Some(machine_b_q0)])?);
+        // push final state
+        anfa.delta.push(transition::nothing());
+        // point machine_a at machine_c
+        anfa.delta[machine_a_f] = transition::epsilon(machine_c_f);
+        // point machine_b at machine_c
+        anfa.delta[machine_b_f] = transition::epsilon(machine_c_f);
         anfa.automata_refs.push(machine_c);
+        anfa.debug_assert_touched(&[machine_a_f, machine_b_f]);
         Ok(())
     }
 }
 
+/// Returns true when `machine` is built like `Compiler::expr_0`: `q0` is not `f`
+/// and never transitions, so the machine accepts nothing.
+fn is_expr_0(anfa: &ANFA, machine: AutomataRef) -> bool {
+    let [q0, f] = machine;
+    q0 != f && transition::targets(&anfa.delta[q0]).next().is_none()
+}
+
+/// Returns true when `f` of `machine` is unreachable from `q0`, so the machine
+/// accepts nothing, e.g. `Compiler::expr_0` or `'a' ⋅ 0`.
+fn accepts_nothing(anfa: &ANFA, machine: AutomataRef) -> bool {
+    let [q0, f] = machine;
+    q0 != f && !anfa.reachable_from(q0)[f]
+}
+
+/// Returns true when `machine` is built like `Compiler::expr_1`: `q0` is `f` and
+/// never transitions, so the machine accepts only the empty string.
+fn accepts_only_epsilon(anfa: &ANFA, machine: AutomataRef) -> bool {
+    let [q0, f] = machine;
+    q0 == f && transition::targets(&anfa.delta[q0]).next().is_none()
+}
+
+/// Returns true when `machine` is built like `Compiler::star`: `q0` moves to a
+/// union `q` preferring the repeated machine over `f`, the repeated machine
+/// loops back to `q` along epsilon, `f` never transitions, and only `q` enters
+/// `f`. Every path to `f` then repeats the loop 0 or more times, so the machine
+/// accepts its own star.
+fn is_star(anfa: &ANFA, machine: AutomataRef) -> bool {
+    let [q0, f] = machine;
+    let (q, inner) = match anfa.delta[q0] {
+        (Label::Epsilon, [Some(q), None]) if q != f => match anfa.delta[q] {
+            (Label::Epsilon, [Some(inner), Some(b)]) if b == f => (q, inner),
+            _ => return false,
+        },
+        _ => return false,
+    };
+    if transition::targets(&anfa.delta[f]).next().is_some() {
+        return false;
+    }
+    let loops = anfa
+        .reachable_from(inner)
+        .iter()
+        .enumerate()
+        .any(|(p, &reachable)| reachable && p != q0 && anfa.delta[p] == transition::epsilon(q));
+    let entries = anfa
+        .delta
+        .iter()
+        .filter(|(_, targets)| targets.contains(&Some(f)))
+        .count();
+    loops && entries == 1
+}
+
+/// Returns true when `machine_b` has the same structure as `machine_a`: walking
+/// both from `q0` in step, every pair of states has the same label and pairs of
+/// targets, and the `f`s are paired.
+fn same_structure(anfa: &ANFA, machine_a: AutomataRef, machine_b: AutomataRef) -> bool {
+    let mut pairs = BTreeMap::new();
+    let mut stack = vec![(machine_a[0], machine_b[0])];
+    while let Some((a, b)) = stack.pop() {
+        match pairs.get(&a) {
+            Some(&paired) if paired == b => continue,
+            Some(_) => return false,
+            None => {}
+        }
+        pairs.insert(a, b);
+        let ((label_a, targets_a), (label_b, targets_b)) = (&anfa.delta[a], &anfa.delta[b]);
+        if label_a != label_b {
+            return false;
+        }
+        for (&target_a, &target_b) in targets_a.iter().zip(targets_b.iter()) {
+            match (target_a, target_b) {
+                (Some(a), Some(b)) => stack.push((a, b)),
+                (None, None) => {}
+                _ => return false,
+            }
+        }
+    }
+    if pairs.get(&machine_a[1]) != Some(&machine_b[1]) {
+        return false;
+    }
+    let states_b: vec::Vec<QId> = pairs.into_values().collect();
+    let mut sorted = states_b.clone();
+    sorted.sort_unstable();
+    sorted.dedup();
+    // two states of 'a' paired with one state of 'b' are not the same structure
+    sorted.len() == states_b.len()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::parser::parse;
+    use crate::{transition, AnchorKind, Label, ANFA};
+    use alloc::collections::BTreeMap;
+    use alloc::vec;
 
     #[test]
     fn test_expr_0() {
@@ -426,12 +701,12 @@ mod tests {
         let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_a_q0],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 0 (nothing) cannot transition from q0"
         );
         assert_eq!(
             machine.delta[machine_a_f],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 0 (nothing) cannot transition from f"
         );
         assert_ne!(
@@ -469,7 +744,7 @@ mod tests {
         );
         assert_eq!(
             machine.delta[0],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 1 (epsilon) does not transition from q0"
         );
         let [machine_a_q0, machine_a_f] = &machine.automata_refs[machine.automata_refs.len() - 1];
@@ -508,12 +783,12 @@ mod tests {
         let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_a_q0],
-            (Some('a'), [Some(machine_a_f), None]),
+            (Label::Char('a'), [Some(machine_a_f), None]),
             "Expression 'a' (literal) transitions from q0 to f along 'a'"
         );
         assert_eq!(
             machine.delta[machine_a_f],
-            (None, [None, None]),
+            (Label::Epsilon, [None, None]),
             "Expression 'a' (literal) cannot transition from f"
         );
         assert_ne!(
@@ -534,6 +809,113 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_expr_any() {
+        let mut machine = ForwardCompiler::from_expr_any().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Expression '.' (any) pushes one automata ref"
+        );
+        assert_eq!(
+            machine.delta.len(),
+            2,
+            "Expression '.' (any) pushes two states"
+        );
+        let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        assert_eq!(
+            machine.delta[machine_a_q0],
+            (Label::Any, [Some(machine_a_f), None]),
+            "Expression '.' (any) transitions from q0 to f along any char"
+        );
+        for input in ["a", "z", "😀"] {
+            assert!(
+                machine.is_match(input),
+                "Expression '.' (any) accepts one char"
+            );
+        }
+        assert!(!machine.is_match(""), "Expression '.' (any) rejects ''");
+        assert!(
+            !machine.is_match("ab"),
+            "Expression '.' (any) rejects two chars"
+        );
+        // run twice to make sure pushing expressions isn't affected by prior pushed expressions
+        ForwardCompiler::expr_any(&mut machine).unwrap();
+        assert_eq!(
+            machine.delta.len(),
+            4,
+            "(Repeated) Expression '.' (any) pushes two states"
+        );
+    }
+
+    #[test]
+    fn test_expr_anchor() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_anchor(&mut machine, AnchorKind::End).unwrap();
+        let [machine_b_q0, machine_b_f] = machine.automata_refs[1];
+        assert_eq!(
+            machine.delta[machine_b_q0],
+            (Label::Anchor(AnchorKind::End), [Some(machine_b_f), None]),
+            "Expression '$' (anchor) transitions from q0 to f at the end"
+        );
+        assert!(machine.is_match(""), "Expression '$' (anchor) accepts ''");
+        assert!(
+            !machine.is_match("a"),
+            "Expression '$' (anchor) rejects 'a'"
+        );
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("a"), "Expression 'a$' accepts 'a'");
+        ForwardCompiler::expr_anchor(&mut machine, AnchorKind::End).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(!machine.is_match("ab"), "Expression 'a$b' rejects 'ab'");
+    }
+
+    #[test]
+    fn test_expr_range() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_range(&mut machine, '0', '9').unwrap();
+        let [machine_b_q0, machine_b_f] = machine.automata_refs[1];
+        assert_eq!(
+            machine.delta[machine_b_q0],
+            (
+                Label::Class(vec![('0', '9')], false),
+                [Some(machine_b_f), None]
+            ),
+            "Expression [0-9] (range) transitions from q0 to f along the range"
+        );
+        for input in ["0", "5", "9"] {
+            assert!(
+                machine.is_match(input),
+                "Expression [0-9] (range) accepts {:?}",
+                input
+            );
+        }
+        assert!(
+            !machine.is_match("a"),
+            "Expression [0-9] (range) rejects 'a'"
+        );
+        assert!(
+            !machine.is_match("55"),
+            "Expression [0-9] (range) rejects two chars"
+        );
+        assert!(
+            ForwardCompiler::expr_range(&mut machine, '9', '0').is_err(),
+            "Expression [9-0] (range) is invalid"
+        );
+        assert_eq!(
+            machine.automata_refs.len(),
+            2,
+            "Invalid ranges push nothing"
+        );
+        ForwardCompiler::expr_range(&mut machine, 'x', 'x').unwrap();
+        assert!(
+            machine.is_match("x"),
+            "Expression [x-x] (range) accepts 'x'"
+        );
+    }
+
     #[test]
     fn test_concatenate() {
         let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
@@ -564,11 +946,115 @@ mod tests {
         let [_machine_c_q0, _machine_c_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_a_f],
-            (None, [Some(machine_b_q0), None]),
+            (Label::Epsilon, [Some(machine_b_q0), None]),
             "Concatenation transitions machine_a to machine_b along epsilon"
         );
     }
 
+    #[test]
+    fn test_concatenate_multiple_finals() {
+        // RE a|bc, accepting after a and after c
+        let transitions = [
+            BTreeMap::from([('a', 1), ('b', 2)]),
+            BTreeMap::new(),
+            BTreeMap::from([('c', 3)]),
+            BTreeMap::new(),
+        ];
+        let mut machine =
+            ANFA::from_dfa_table(&transitions, &[false, true, false, true], 0).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'd').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(
+            machine.equivalent(&parse("(a|bc)d").unwrap()),
+            "Every final of a|bc is followed by d"
+        );
+        for input in ["ad", "bcd"] {
+            assert!(machine.is_match(input), "(a|bc)d accepts {}", input);
+        }
+        for input in ["a", "bc", "bd", "add"] {
+            assert!(!machine.is_match(input), "(a|bc)d rejects {}", input);
+        }
+    }
+
+    #[test]
+    fn test_concatenate_epsilon_and_nothing() {
+        // RE ()b
+        let mut machine = ForwardCompiler::from_expr_1().unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("b"), "Epsilon ⋅ 'b' accepts 'b'");
+        assert!(!machine.is_match(""), "Epsilon ⋅ 'b' rejects ''");
+        assert!(!machine.is_match("bb"), "Epsilon ⋅ 'b' rejects 'bb'");
+        // RE b()
+        let mut machine = ForwardCompiler::from_expr_a('b').unwrap();
+        ForwardCompiler::expr_1(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("b"), "'b' ⋅ epsilon accepts 'b'");
+        assert!(!machine.is_match(""), "'b' ⋅ epsilon rejects ''");
+        // RE ()()
+        let mut machine = ForwardCompiler::from_expr_1().unwrap();
+        ForwardCompiler::expr_1(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match(""), "Epsilon ⋅ epsilon accepts ''");
+        assert!(!machine.is_match("b"), "Epsilon ⋅ epsilon rejects 'b'");
+        // RE () concatenated with itself, 'b' aliases the state of 'a'
+        let mut machine = ForwardCompiler::from_expr_1().unwrap();
+        machine.mark_machine(0, 0).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(
+            machine.check_invariants(),
+            Ok(()),
+            "The aliased state of 'b' is not popped"
+        );
+        assert_eq!(machine.delta.len(), 1, "The aliased state is kept");
+        assert!(machine.is_match(""), "Epsilon ⋅ epsilon accepts ''");
+        // RE []b and b[]
+        for nothing_first in [true, false] {
+            let mut machine = ForwardCompiler::from_expr_a('b').unwrap();
+            ForwardCompiler::expr_0(&mut machine).unwrap();
+            if nothing_first {
+                machine.automata_refs.swap(0, 1);
+            }
+            ForwardCompiler::concatenate(&mut machine).unwrap();
+            for input in ["", "b", "bb"] {
+                assert!(
+                    !machine.is_match(input),
+                    "Concatenation with nothing accepts nothing, not {:?}",
+                    input
+                );
+            }
+            assert!(machine.to_dfa().is_empty(), "Language is empty");
+        }
+    }
+
+    #[test]
+    fn test_concatenate_associative() {
+        // (a⋅b)⋅c
+        let mut left = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut left, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut left).unwrap();
+        ForwardCompiler::expr_a(&mut left, 'c').unwrap();
+        ForwardCompiler::concatenate(&mut left).unwrap();
+        // a⋅(b⋅c)
+        let mut right = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut right, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut right, 'c').unwrap();
+        ForwardCompiler::concatenate(&mut right).unwrap();
+        ForwardCompiler::concatenate(&mut right).unwrap();
+        assert!(left.equivalent(&right), "(a⋅b)⋅c = a⋅(b⋅c)");
+        for machine in [&left, &right] {
+            assert_eq!(
+                machine.automata_refs,
+                [[0, 5]],
+                "Both groupings start at a and end at c"
+            );
+            assert!(machine.is_match("abc"), "Both groupings accept abc");
+            for input in ["ab", "bc", "acb", "cba"] {
+                assert!(!machine.is_match(input), "Both groupings keep the order");
+            }
+        }
+    }
+
     #[test]
     fn test_star() {
         let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
@@ -594,7 +1080,7 @@ mod tests {
         let machine_b_intermediary_q = machine.delta[machine_a_f].1[0].unwrap();
         assert_eq!(
             machine.delta[machine_b_intermediary_q],
-            (None, [Some(machine_a_q0), Some(machine_b_f)]),
+            (Label::Epsilon, [Some(machine_a_q0), Some(machine_b_f)]),
             "(1) New intermediary state is a union of machine_a's q0 and new f, \
              (2) machine_a's f transtions to new intermediary state along epsilon"
         );
@@ -632,18 +1118,184 @@ mod tests {
         let [machine_c_q0, machine_c_f] = machine.automata_refs[0];
         assert_eq!(
             machine.delta[machine_c_q0],
-            (None, [Some(machine_a_q0), Some(machine_b_q0)]),
+            (Label::Epsilon, [Some(machine_a_q0), Some(machine_b_q0)]),
             "q0 of machine_c transitions to q0 of machine_a and machine_b along epsilon"
         );
         assert_eq!(
             machine.delta[machine_a_f],
-            (None, [Some(machine_c_f), None]),
+            (Label::Epsilon, [Some(machine_c_f), None]),
             "f of machine_a transitions to f of machine_c along epsilon"
         );
         assert_eq!(
             machine.delta[machine_b_f],
-            (None, [Some(machine_c_f), None]),
+            (Label::Epsilon, [Some(machine_c_f), None]),
             "f of machine_b transitions to f of machine_c along epsilon"
         );
     }
+
+    #[test]
+    fn test_nothing_simplifies() {
+        // RE a ∪ 0 and 0 ∪ a
+        for nothing_first in [true, false] {
+            let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+            ForwardCompiler::expr_0(&mut machine).unwrap();
+            if nothing_first {
+                machine.automata_refs.swap(0, 1);
+            }
+            ForwardCompiler::union(&mut machine).unwrap();
+            assert_eq!(machine.automata_refs, [[0, 1]], "Union with nothing is 'a'");
+            assert_eq!(
+                machine.machine_states([0, 1]).len(),
+                ForwardCompiler::from_expr_a('a').unwrap().delta.len(),
+                "Union with nothing has the states of 'a'"
+            );
+            assert_eq!(machine.delta.len(), 4, "No union states are pushed");
+            assert!(machine.is_match("a"), "Union with nothing accepts a");
+            assert!(!machine.is_match(""), "Union with nothing rejects ''");
+            assert!(
+                machine.equivalent(&ForwardCompiler::from_expr_a('a').unwrap()),
+                "Union with nothing accepts 'a'"
+            );
+        }
+        // RE a ⋅ 0 and 0 ⋅ a
+        for nothing_first in [true, false] {
+            let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+            ForwardCompiler::expr_0(&mut machine).unwrap();
+            if nothing_first {
+                machine.automata_refs.swap(0, 1);
+            }
+            ForwardCompiler::concatenate(&mut machine).unwrap();
+            assert_eq!(
+                machine.automata_refs,
+                [[2, 3]],
+                "Concatenation with nothing is nothing"
+            );
+            assert_eq!(
+                machine.delta[1],
+                transition::nothing(),
+                "The f of 'a' is not overwritten"
+            );
+            assert!(machine.is_empty_language(), "Language is empty");
+        }
+        // RE 0 ∪ (a ∪ 0)
+        let mut machine = ForwardCompiler::from_expr_0().unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.automata_refs, [[2, 3]], "0 ∪ (a ∪ 0) is 'a'");
+        assert_eq!(machine.delta.len(), 6, "No union states are pushed");
+        assert!(machine.is_match("a"), "0 ∪ (a ∪ 0) accepts a");
+        // RE (a ⋅ 0) ∪ b, nothing is found by reachability
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        machine.delta[1] = transition::epsilon(2);
+        machine.automata_refs = vec![[0, 3]];
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        let machine_b = machine.automata_refs[1];
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.automata_refs, [machine_b], "(a ⋅ 0) ∪ b is 'b'");
+        assert!(machine.is_match("b"), "(a ⋅ 0) ∪ b accepts b");
+        assert!(!machine.is_match("a"), "(a ⋅ 0) ∪ b rejects a");
+    }
+
+    #[test]
+    fn test_star_idempotent() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let star = machine.automata_refs.clone();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(machine.automata_refs, star, "(a*)* is a*");
+        assert_eq!(machine.delta.len(), 5, "(a*)* adds no states");
+        let mut expected = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut expected).unwrap();
+        assert!(machine.equivalent(&expected), "(a*)* accepts a*");
+        // RE a?, shaped like a star without the loop
+        let mut machine = ANFA::with_capacity(4, 1);
+        for _ in 0..4 {
+            machine.push_state();
+        }
+        machine.set_epsilon(0, 1).unwrap();
+        machine.set_union(1, 2, 3).unwrap();
+        machine.set_labeled(2, 'a', 3).unwrap();
+        machine.mark_machine(0, 3).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_ne!(machine.automata_refs, [[0, 3]], "(a?)* is not a?");
+        assert!(machine.is_match("aa"), "(a?)* accepts aa");
+    }
+
+    #[test]
+    fn test_union_idempotent() {
+        // RE ab ∪ ab
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        machine.duplicate_top().unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.automata_refs, [[0, 3]], "ab ∪ ab is ab");
+        assert_eq!(
+            machine.machine_states([0, 3]),
+            [0, 1, 2, 3],
+            "ab ∪ ab is the first ab"
+        );
+        assert_eq!(machine.delta.len(), 8, "No union states are pushed");
+        assert!(machine.is_match("ab"), "ab ∪ ab accepts ab");
+        // RE a ∪ b
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.delta.len(), 6, "Different operands are unioned");
+        // RE a* ∪ a*
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.delta.len(), 10, "Loops are compared in step");
+    }
+
+    #[test]
+    fn test_union_identical_literals() {
+        // RE a ∪ a
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let literal = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(
+            machine.automata_refs, literal.automata_refs,
+            "a ∪ a is the first a"
+        );
+        assert_eq!(
+            machine.machine_states([0, 1]).len(),
+            2,
+            "a ∪ a is one literal"
+        );
+        assert!(machine.is_match("a"), "a ∪ a accepts a");
+        for input in ["", "aa", "b"] {
+            assert!(!machine.is_match(input), "a ∪ a rejects {:?}", input);
+        }
+        let machine = parse("a|a|a").unwrap();
+        assert_eq!(
+            machine.automata_refs, literal.automata_refs,
+            "a|a|a is the first a"
+        );
+        assert_eq!(
+            machine.machine_states([0, 1]).len(),
+            2,
+            "Each repeated literal is discarded"
+        );
+    }
+
+    #[test]
+    fn test_concatenate_epsilon_identity() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_1(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(machine.automata_refs, [[0, 1]], "a ⋅ ε is a");
+        assert_eq!(machine.delta.len(), 2, "The state of ε is popped");
+        assert!(
+            machine.equivalent(&ForwardCompiler::from_expr_a('a').unwrap()),
+            "a ⋅ ε accepts a"
+        );
+    }
 }
@@ -1,17 +1,368 @@
-use crate::ANFA;
+use crate::error::CompileError;
+use crate::{transition, AnchorKind, QId, ANFA};
+use alloc::vec;
 
 pub mod bidirectional_compiler;
 pub mod coverage_compiler;
 pub mod forward_compiler;
 
+/// Most copies `CompilerExt::repeat` pushes of its operand.
+pub const DEFAULT_MAX_EXPANSION: usize = 10_000;
+
 pub trait Compiler {
-  fn from_expr_0() -> Result<ANFA, &'static str>;
-  fn from_expr_1() -> Result<ANFA, &'static str>;
-  fn from_expr_a(c: char) -> Result<ANFA, &'static str>;
-  fn expr_0(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn expr_1(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), &'static str>;
-  fn concatenate(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn star(anfa: &mut ANFA) -> Result<(), &'static str>;
-  fn union(anfa: &mut ANFA) -> Result<(), &'static str>;
+  fn from_expr_0() -> Result<ANFA, CompileError>;
+  fn from_expr_1() -> Result<ANFA, CompileError>;
+  fn from_expr_a(c: char) -> Result<ANFA, CompileError>;
+  fn from_expr_any() -> Result<ANFA, CompileError>;
+  fn expr_0(anfa: &mut ANFA) -> Result<(), CompileError>;
+  fn expr_1(anfa: &mut ANFA) -> Result<(), CompileError>;
+  fn expr_a(anfa: &mut ANFA, c: char) -> Result<(), CompileError>;
+  fn expr_any(anfa: &mut ANFA) -> Result<(), CompileError>;
+  fn expr_anchor(anfa: &mut ANFA, kind: AnchorKind) -> Result<(), CompileError>;
+  fn expr_range(anfa: &mut ANFA, start: char, end: char) -> Result<(), CompileError>;
+  fn concatenate(anfa: &mut ANFA) -> Result<(), CompileError>;
+  fn star(anfa: &mut ANFA) -> Result<(), CompileError>;
+  fn union(anfa: &mut ANFA) -> Result<(), CompileError>;
+}
+
+/// Higher-level operations built from the `Compiler` primitives.
+///
+/// Every `Compiler` implements `CompilerExt`, so each operation is written once and
+/// shared by `ForwardCompiler`, `CoverageCompiler`, and future compilers. A compiler
+/// may override a method when it can build the machine more efficiently.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::compilers::CompilerExt;
+/// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+/// ForwardCompiler::optional(&mut machine).unwrap();
+/// assert!(machine.is_match(""));
+/// assert!(machine.is_match("a"));
+/// ```
+pub trait CompilerExt: Compiler {
+  /// Repeats the last machine 1 or more times, i.e. `a+ = a ⋅ a*`
+  fn plus(anfa: &mut ANFA) -> Result<(), CompileError> {
+    anfa.duplicate_top()?;
+    Self::star(anfa)?;
+    Self::concatenate(anfa)
+  }
+
+  /// Accepts the last machine 0 or 1 times, i.e. `a? = a ∪ ε`
+  fn optional(anfa: &mut ANFA) -> Result<(), CompileError> {
+    if anfa.automata_refs.is_empty() {
+      return Err(CompileError::Invalid("Optional requires one operand."));
+    }
+    Self::expr_1(anfa)?;
+    Self::union(anfa)
+  }
+
+  /// Repeats the last machine 0 or more times, preferring fewer repetitions, i.e. `a*?`
+  ///
+  /// The machine is built by `Compiler::star`, then each union it pushed prefers
+  /// its second branch. Only priority-aware matching, e.g. `Matcher::find_first`,
+  /// tells a lazy star from a greedy one.
+  fn star_lazy(anfa: &mut ANFA) -> Result<(), CompileError> {
+    let pushed = anfa.delta.len();
+    Self::star(anfa)?;
+    prefer_second_branch(anfa, pushed);
+    Ok(())
+  }
+
+  /// Repeats the last machine 1 or more times, preferring fewer repetitions, i.e. `a+?`
+  fn plus_lazy(anfa: &mut ANFA) -> Result<(), CompileError> {
+    anfa.duplicate_top()?;
+    Self::star_lazy(anfa)?;
+    Self::concatenate(anfa)
+  }
+
+  /// Accepts the last machine 0 or 1 times, preferring 0, i.e. `a??`
+  fn optional_lazy(anfa: &mut ANFA) -> Result<(), CompileError> {
+    let pushed = anfa.delta.len();
+    Self::optional(anfa)?;
+    prefer_second_branch(anfa, pushed);
+    Ok(())
+  }
+
+  /// Repeats the last machine from `min` to `max` times, or `min` or more times
+  /// without a `max`, i.e. `a{min,max}` or `a{min,}`
+  ///
+  /// Bounded repetition is expanded into copies of the operand, so at most
+  /// `DEFAULT_MAX_EXPANSION` copies are pushed. See `CompilerExt::repeat_with_limit`
+  /// to raise the limit for large but intentional expansions.
+  fn repeat(anfa: &mut ANFA, min: usize, max: Option<usize>) -> Result<(), CompileError> {
+    Self::repeat_with_limit(anfa, min, max, DEFAULT_MAX_EXPANSION)
+  }
+
+  /// Repeats the last machine like `CompilerExt::repeat`, pushing at most `limit` copies.
+  ///
+  /// `a{2,4}` is built as `a ⋅ a ⋅ a? ⋅ a?` and `a{2,}` as `a ⋅ a ⋅ a*`. A repetition
+  /// needing more than `limit` copies returns `CompileError::ExpansionTooLarge`
+  /// before any state is pushed.
+  ///
+  /// The limit counts every copy, `max` or `min + 1`, not only the `max - min`
+  /// optional copies: the `min` required copies are expanded too, so `a{1000000}`
+  /// would otherwise exhaust memory with no optional copy at all.
+  fn repeat_with_limit(
+    anfa: &mut ANFA,
+    min: usize,
+    max: Option<usize>,
+    limit: usize,
+  ) -> Result<(), CompileError> {
+    if max.is_some_and(|max| min > max) {
+      return Err(CompileError::Invalid("Repeat requires min <= max."));
+    }
+    // a{min,} is min copies and one starred copy
+    let copies = match max.or_else(|| min.checked_add(1)) {
+      Some(copies) if copies <= limit => copies,
+      copies => {
+        return Err(CompileError::ExpansionTooLarge {
+          requested: copies.unwrap_or(usize::MAX),
+          limit,
+        });
+      }
+    };
+    let machine_a = match anfa.automata_refs.pop() {
+      None => {
+        return Err(CompileError::Invalid("Repeat requires one operand."));
+      }
+      Some(machine_a) => machine_a,
+    };
+    if copies == 0 {
+      return Self::expr_1(anfa);
+    }
+    // every copy is taken before any operator changes machine_a
+    let states = anfa.copied_states(machine_a);
+    let mut parts = vec::Vec::with_capacity(copies);
+    parts.push(machine_a);
+    for _ in 1..copies {
+      parts.push(anfa.copy_states(&states, machine_a));
+    }
+    for (i, part) in parts.into_iter().enumerate() {
+      anfa.automata_refs.push(part);
+      if i >= min {
+        match max {
+          Some(_) => Self::optional(anfa)?,
+          None => Self::star(anfa)?,
+        }
+      }
+      if i > 0 {
+        Self::concatenate(anfa)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Pushes a machine accepting exactly `s`. The empty string is epsilon.
+  fn literal_str(anfa: &mut ANFA, s: &str) -> Result<(), CompileError> {
+    let mut chars = s.chars();
+    match chars.next() {
+      None => Self::expr_1(anfa),
+      Some(c) => {
+        Self::expr_a(anfa, c)?;
+        for c in chars {
+          Self::expr_a(anfa, c)?;
+          Self::concatenate(anfa)?;
+        }
+        Ok(())
+      }
+    }
+  }
+
+  /// Pushes a machine accepting `s` with its ASCII letters in either case, e.g.
+  /// `"Select"` accepts `"SELECT"` and `"select"`.
+  ///
+  /// Each ASCII letter is the union of its lowercase and uppercase literals, and
+  /// the chars are concatenated in order. Other chars, including non-ASCII
+  /// letters, only match themselves. The empty string is epsilon.
+  fn concat_str_insensitive(anfa: &mut ANFA, s: &str) -> Result<(), CompileError> {
+    let mut operands = 0;
+    for c in s.chars() {
+      if c.is_ascii_alphabetic() {
+        Self::expr_a(anfa, c.to_ascii_lowercase())?;
+        Self::expr_a(anfa, c.to_ascii_uppercase())?;
+        Self::union(anfa)?;
+      } else {
+        Self::expr_a(anfa, c)?;
+      }
+      operands += 1;
+      if operands > 1 {
+        Self::concatenate(anfa)?;
+      }
+    }
+    if operands == 0 {
+      Self::expr_1(anfa)?;
+    }
+    Ok(())
+  }
+
+  /// Unions the top `count` machines into one, i.e. `a ∪ (b ∪ (c ∪ ...))`
+  ///
+  /// See `ANFA::union_all_balanced` for a balanced tree of unions.
+  fn union_all(anfa: &mut ANFA, count: usize) -> Result<(), CompileError> {
+    if count == 0 || count > anfa.automata_refs.len() {
+      return Err(CompileError::Invalid("Union requires count operands."));
+    }
+    for _ in 1..count {
+      Self::union(anfa)?;
+    }
+    Ok(())
+  }
+}
+
+impl<T: Compiler> CompilerExt for T {}
+
+/// Swaps the branches of every union pushed at or after state `pushed`.
+fn prefer_second_branch(anfa: &mut ANFA, pushed: QId) {
+  for transition in anfa.delta[pushed..].iter_mut() {
+    if transition::is_union(transition) {
+      transition.1.swap(0, 1);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::compilers::coverage_compiler::CoverageCompiler;
+  use crate::compilers::forward_compiler::ForwardCompiler;
+  use crate::compilers::{Compiler, CompilerExt, DEFAULT_MAX_EXPANSION};
+  use crate::error::CompileError;
+  use crate::parser::parse;
+
+  #[test]
+  fn test_optional() {
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::optional(&mut machine).unwrap();
+    assert_eq!(machine.automata_refs.len(), 1, "Optional leaves one machine");
+    assert!(machine.is_match(""), "a? accepts ''");
+    assert!(machine.is_match("a"), "a? accepts 'a'");
+    assert!(!machine.is_match("aa"), "a? rejects 'aa'");
+  }
+
+  #[test]
+  fn test_plus() {
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    ForwardCompiler::union(&mut machine).unwrap();
+    ForwardCompiler::plus(&mut machine).unwrap();
+    assert!(
+      machine.equivalent(&parse("(a|b)(a|b)*").unwrap()),
+      "(a|b)+ = (a|b)(a|b)*"
+    );
+    let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
+    CoverageCompiler::plus(&mut machine).unwrap();
+    assert!(
+      machine.equivalent(&parse("aa*").unwrap()),
+      "Coverage a+ = aa*"
+    );
+  }
+
+  #[test]
+  fn test_star_lazy() {
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::star_lazy(&mut machine).unwrap();
+    assert_eq!(machine.find_first("aaa"), Some((0, 0)), "a*? matches ''");
+    assert_eq!(machine.find("aaa"), Some((0, 3)), "Leftmost-longest ignores laziness");
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::star(&mut machine).unwrap();
+    assert_eq!(machine.find_first("aaa"), Some((0, 3)), "a* matches 'aaa'");
+    let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
+    CoverageCompiler::star_lazy(&mut machine).unwrap();
+    assert_eq!(machine.find_first("aaa"), Some((0, 0)), "Coverage a*? matches ''");
+  }
+
+  #[test]
+  fn test_plus_and_optional_lazy() {
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::plus_lazy(&mut machine).unwrap();
+    assert_eq!(machine.find_first("aaa"), Some((0, 1)), "a+? matches 'a'");
+    assert!(machine.equivalent(&parse("aa*").unwrap()), "a+? accepts a+");
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::optional_lazy(&mut machine).unwrap();
+    assert_eq!(machine.find_first("a"), Some((0, 0)), "a?? matches ''");
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::optional(&mut machine).unwrap();
+    assert_eq!(machine.find_first("a"), Some((0, 1)), "a? matches 'a'");
+  }
+
+  #[test]
+  fn test_concat_str_insensitive() {
+    let mut machine = ForwardCompiler::from_expr_0().unwrap();
+    ForwardCompiler::concat_str_insensitive(&mut machine, "Select 1").unwrap();
+    assert_eq!(machine.automata_refs.len(), 2, "One machine is pushed");
+    for input in ["Select 1", "SELECT 1", "select 1", "sElEcT 1"] {
+      assert!(machine.is_match(input), "Case is ignored in {:?}", input);
+    }
+    assert!(!machine.is_match("selecZ 1"), "Letters still differ");
+    assert!(!machine.is_match("select"), "Every char is required");
+    ForwardCompiler::concat_str_insensitive(&mut machine, "").unwrap();
+    assert!(machine.is_match(""), "The empty string is epsilon");
+  }
+
+  #[test]
+  fn test_repeat() {
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    ForwardCompiler::repeat(&mut machine, 2, Some(4)).unwrap();
+    assert_eq!(machine.automata_refs.len(), 1, "Repeat leaves one machine");
+    assert!(
+      machine.equivalent(&parse("aa|aaa|aaaa").unwrap()),
+      "Repeating a 2 to 4 times = aa|aaa|aaaa"
+    );
+    let mut machine = parse("ab").unwrap();
+    ForwardCompiler::repeat(&mut machine, 1, None).unwrap();
+    assert!(
+      machine.equivalent(&parse("ab(ab)*").unwrap()),
+      "Repeating ab 1 or more times = ab(ab)*"
+    );
+    let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
+    CoverageCompiler::repeat(&mut machine, 0, Some(0)).unwrap();
+    assert!(machine.accepts_exactly(""), "Repeating a 0 times = ε");
+    assert!(
+      ForwardCompiler::repeat(&mut machine, 3, Some(2)).is_err(),
+      "Repeat requires min <= max"
+    );
+  }
+
+  #[test]
+  fn test_repeat_limit() {
+    let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    assert_eq!(
+      ForwardCompiler::repeat(&mut machine, 0, Some(2_000_000)),
+      Err(CompileError::ExpansionTooLarge {
+        requested: 2_000_000,
+        limit: DEFAULT_MAX_EXPANSION,
+      }),
+      "Large expansions are rejected"
+    );
+    assert_eq!(machine.delta.len(), 2, "Nothing is pushed");
+    ForwardCompiler::repeat_with_limit(&mut machine, 0, Some(12_000), 12_000).unwrap();
+    assert!(machine.is_match("aaa"), "Callers can raise the limit");
+    for limit in [DEFAULT_MAX_EXPANSION, usize::MAX] {
+      assert_eq!(
+        ForwardCompiler::repeat_with_limit(&mut machine, usize::MAX, None, limit),
+        Err(CompileError::ExpansionTooLarge {
+          requested: usize::MAX,
+          limit,
+        }),
+        "a{{usize::MAX,}} does not overflow"
+      );
+    }
+  }
+
+  #[test]
+  fn test_literal_str_and_union_all() {
+    let mut machine = ForwardCompiler::from_expr_0().unwrap();
+    for s in ["ab", "", "cde"] {
+      ForwardCompiler::literal_str(&mut machine, s).unwrap();
+    }
+    assert!(
+      ForwardCompiler::union_all(&mut machine, 5).is_err(),
+      "Union of five machines requires five operands"
+    );
+    ForwardCompiler::union_all(&mut machine, 3).unwrap();
+    assert_eq!(machine.automata_refs.len(), 2, "Only three machines are unioned");
+    assert!(
+      machine.equivalent(&parse("ab||cde").unwrap()),
+      "Literals are unioned"
+    );
+  }
 }
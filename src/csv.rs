@@ -0,0 +1,374 @@
+use crate::elimination::write_pattern_char;
+use crate::error::CompileError;
+use crate::{AnchorKind, Label, QId, ANFA};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use core::fmt::Write;
+
+impl ANFA {
+    /// Returns the transitions of every state as CSV, one `from,symbol,to` row
+    /// per edge after a header row, for spreadsheets and data pipelines.
+    ///
+    /// A union is two rows, and a state without targets has no row. The symbol
+    /// is empty for epsilon, and any other label is written as an atom of
+    /// `ANFA::to_regex_string`, e.g. `a`, `\.`, `.`, `^`, `$`, or `[^a-z]`.
+    ///
+    /// A symbol holding a comma, quote, or line break is quoted, with quotes
+    /// doubled. Machines are not written, so `ANFA::from_transitions_csv` takes
+    /// `q0` and the finals separately.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.export_transitions_csv(), "from,symbol,to\n0,a,1\n");
+    /// ```
+    pub fn export_transitions_csv(&self) -> String {
+        let mut csv = String::from("from,symbol,to\n");
+        for (q, (label, targets)) in self.delta.iter().enumerate() {
+            let symbol = symbol_text(label);
+            for target in targets.iter().flatten() {
+                // writing into a String cannot fail
+                let _ = write!(csv, "{},", q);
+                if symbol.contains(&[',', '"', '\n', '\r'][..]) {
+                    let _ = write!(csv, "\"{}\"", symbol.replace('"', "\"\""));
+                } else {
+                    csv.push_str(&symbol);
+                }
+                let _ = writeln!(csv, ",{}", target);
+            }
+        }
+        csv
+    }
+
+    /// Reads the rows written by `ANFA::export_transitions_csv` into an ANFA with
+    /// one machine starting at `q0` and accepting at every state of `finals`.
+    ///
+    /// State ids must be contiguous from 0, counting every row, `q0`, and
+    /// `finals`. Each state keeps its id. A state whose rows have one symbol
+    /// transitions along it, and a state with several symbols fans out along
+    /// epsilon to one new state per symbol, so any table, e.g. a DFA's, can be
+    /// read. More than two targets are split with `ANFA::max_outgoing_targets_guard`.
+    /// A lone final without rows is `f`, otherwise every final moves to a new `f`
+    /// along epsilon. Fails when the header is missing, a row does not have three
+    /// fields, an id or symbol is invalid, or an id is not below the number of
+    /// distinct ids.
+    ///
+    /// ```rust
+    /// use regexxx::ANFA;
+    /// let csv = "from,symbol,to\n0,a,1\n0,b,2\n2,c,1\n";
+    /// let machine = ANFA::from_transitions_csv(csv, 0, &[1]).unwrap();
+    /// assert!(machine.is_match("a"));
+    /// assert!(machine.is_match("bc"));
+    /// assert!(!machine.is_match("b"));
+    /// ```
+    pub fn from_transitions_csv(csv: &str, q0: QId, finals: &[QId]) -> Result<ANFA, CompileError> {
+        let mut records = read_records(csv)?.into_iter();
+        match records.next() {
+            Some(header) if header == ["from", "symbol", "to"] => {}
+            _ => {
+                return Err(CompileError::Invalid(
+                    "CSV requires a from,symbol,to header.",
+                ));
+            }
+        }
+        let mut rows = vec::Vec::new();
+        for record in records {
+            let (from, symbol, to) = match &record[..] {
+                [from, symbol, to] => (from, symbol, to),
+                _ => {
+                    return Err(CompileError::Invalid(
+                        "CSV rows require from, symbol, and to.",
+                    ));
+                }
+            };
+            let id = |field: &str| {
+                field
+                    .parse::<QId>()
+                    .map_err(|_| CompileError::Invalid("Invalid CSV state id."))
+            };
+            rows.push((id(from)?, read_symbol(symbol)?, id(to)?));
+        }
+        // count the distinct ids before allocating a state per id
+        let ids: BTreeSet<QId> = rows
+            .iter()
+            .flat_map(|&(from, _, to)| [from, to])
+            .chain(finals.iter().copied())
+            .chain([q0])
+            .collect();
+        let len = ids.len();
+        match ids.last() {
+            Some(&max) if max >= len => return Err(CompileError::StateOutOfBounds(max)),
+            _ => {}
+        }
+        // the edges of each state grouped by label, in order of first appearance
+        let mut edges = vec![vec::Vec::new(); len];
+        for (from, label, to) in rows {
+            push_edge(&mut edges[from], label, to);
+        }
+        let mut anfa = ANFA::with_capacity(len + 1, 1);
+        for _ in 0..len {
+            anfa.push_state();
+        }
+        let f = match finals {
+            [f] if edges[*f].is_empty() => *f,
+            _ => {
+                let f = anfa.push_state();
+                for &q in finals {
+                    push_edge(&mut edges[q], Label::Epsilon, f);
+                }
+                f
+            }
+        };
+        for (q, groups) in edges.into_iter().enumerate() {
+            match &groups[..] {
+                [] => continue,
+                [(label, targets)] => {
+                    let targets = anfa.max_outgoing_targets_guard(targets);
+                    anfa.delta[q] = (label.clone(), targets);
+                    continue;
+                }
+                _ => {}
+            }
+            let mut branches = vec::Vec::with_capacity(groups.len());
+            for (label, targets) in groups {
+                let targets = anfa.max_outgoing_targets_guard(&targets);
+                // push one branch per symbol
+                anfa.delta.push((label, targets));
+                branches.push(anfa.delta.len() - 1);
+            }
+            let targets = anfa.max_outgoing_targets_guard(&branches);
+            anfa.delta[q] = (Label::Epsilon, targets);
+        }
+        anfa.mark_machine(q0, f)?;
+        Ok(anfa)
+    }
+}
+
+/// Adds an edge along `label` to `to`, grouped with the other edges along `label`.
+/// A repeated edge is added once.
+fn push_edge(groups: &mut vec::Vec<(Label, vec::Vec<QId>)>, label: Label, to: QId) {
+    match groups.iter_mut().find(|(l, _)| *l == label) {
+        Some((_, targets)) if targets.contains(&to) => {}
+        Some((_, targets)) => targets.push(to),
+        None => groups.push((label, vec![to])),
+    }
+}
+
+/// Splits CSV into records of fields. Quoted fields may hold commas, line
+/// breaks, and doubled quotes. Blank lines are skipped.
+fn read_records(csv: &str) -> Result<vec::Vec<vec::Vec<String>>, CompileError> {
+    let mut records = vec::Vec::new();
+    let mut record = vec::Vec::new();
+    let mut field = String::new();
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => loop {
+                match chars.next() {
+                    None => return Err(CompileError::Invalid("Unterminated CSV quote.")),
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                }
+            },
+            ',' => record.push(core::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(core::mem::take(&mut field));
+                if record != [""] {
+                    records.push(core::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Reads a symbol written by `symbol_text`.
+fn read_symbol(symbol: &str) -> Result<Label, CompileError> {
+    let invalid = CompileError::Invalid("Invalid CSV symbol.");
+    match symbol {
+        "" => return Ok(Label::Epsilon),
+        "." => return Ok(Label::Any),
+        "^" => return Ok(Label::Anchor(AnchorKind::Start)),
+        "$" => return Ok(Label::Anchor(AnchorKind::End)),
+        _ => {}
+    }
+    let chars: vec::Vec<char> = symbol.chars().collect();
+    match chars[..] {
+        [c] | ['\\', c] => return Ok(Label::Char(c)),
+        _ => {}
+    }
+    let inner = match chars[..] {
+        ['[', ref inner @ .., ']'] => inner,
+        _ => return Err(invalid),
+    };
+    let (negated, mut rest) = match inner {
+        ['^', rest @ ..] => (true, rest),
+        rest => (false, rest),
+    };
+    // every char may be escaped, so an unescaped - always joins a range
+    let next_char = |rest: &mut &[char]| {
+        let (c, tail) = match *rest {
+            ['\\'] | [] => return Err(invalid),
+            ['\\', c, tail @ ..] | [c, tail @ ..] => (*c, tail),
+        };
+        *rest = tail;
+        Ok(c)
+    };
+    let mut ranges = vec::Vec::new();
+    while !rest.is_empty() {
+        let lo = next_char(&mut rest)?;
+        let hi = match rest {
+            ['-', tail @ ..] => {
+                rest = tail;
+                next_char(&mut rest)?
+            }
+            _ => lo,
+        };
+        ranges.push((lo, hi));
+    }
+    Ok(Label::Class(ranges, negated))
+}
+
+/// Returns `label` as written in the symbol column. See `ANFA::export_transitions_csv`.
+fn symbol_text(label: &Label) -> String {
+    let mut text = String::new();
+    if *label != Label::Epsilon {
+        // writing into a String cannot fail
+        let _ = label.write_text(&mut text, write_pattern_char);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::error::CompileError;
+    use crate::parser::parse;
+    use crate::{Label, ANFA};
+    use alloc::vec;
+
+    #[test]
+    fn test_export_transitions_csv() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(
+            machine.export_transitions_csv(),
+            "from,symbol,to\n0,a,1\n",
+            "'a' is one edge, and f has no row"
+        );
+        let machine = parse("a|b").unwrap();
+        let csv = machine.export_transitions_csv();
+        assert!(csv.contains("4,,0\n4,,2\n"), "A union is two epsilon rows");
+        assert_eq!(csv.lines().count(), 7, "a|b has six edges");
+        let mut machine = parse("^.$").unwrap();
+        let class = Label::Class(vec![('a', 'z'), (',', ',')], true);
+        machine.delta.push((class, [Some(0), None]));
+        machine.delta.push((Label::Char('"'), [Some(0), None]));
+        machine.delta.push((Label::Char('.'), [Some(0), None]));
+        let csv = machine.export_transitions_csv();
+        for row in [",^,", ",.,", ",$,", ",\"[^a-z,]\",", ",\"\"\"\",", ",\\.,"] {
+            assert!(csv.contains(row), "CSV has the row {}", row);
+        }
+    }
+
+    #[test]
+    fn test_from_transitions_csv_round_trip() {
+        let machine = parse("a(b|c)*d").unwrap();
+        let [q0, f] = machine.automata_refs[0];
+        let copy = ANFA::from_transitions_csv(&machine.export_transitions_csv(), q0, &[f]).unwrap();
+        assert_eq!(copy.delta, machine.delta, "Every transition is restored");
+        for input in ["ad", "abcbd", "ab", "acdd", "", "d"] {
+            assert_eq!(
+                copy.is_match(input),
+                machine.is_match(input),
+                "The copy agrees on {:?}",
+                input
+            );
+        }
+        let mut machine = parse("^.$").unwrap();
+        let class = Label::Class(vec![('^', 'a'), (',', '"')], false);
+        let [q0, f] = machine.automata_refs[0];
+        let labeled = machine.delta[q0].1[0].unwrap();
+        machine.delta.push((class.clone(), [Some(labeled), None]));
+        machine
+            .delta
+            .push((Label::Class(vec![], true), [Some(q0), None]));
+        for c in ['.', '\\', '-', ']'] {
+            machine.delta.push((Label::Char(c), [Some(q0), None]));
+        }
+        let class = Label::Class(vec![('\\', ']'), ('-', '-')], false);
+        machine.delta.push((class, [Some(q0), None]));
+        let copy = ANFA::from_transitions_csv(&machine.export_transitions_csv(), q0, &[f]).unwrap();
+        assert_eq!(
+            copy.delta, machine.delta,
+            "Anchors, ., and classes are restored"
+        );
+    }
+
+    #[test]
+    fn test_from_transitions_csv_fan_out() {
+        // a DFA of a|b(c|d), accepting in two states
+        let csv = "from,symbol,to\n0,a,1\n0,b,2\n2,c,3\n2,d,3\n0,e,3\n0,e,1\n0,e,2\n";
+        let machine = ANFA::from_transitions_csv(csv, 0, &[1, 3]).unwrap();
+        for input in ["a", "bc", "bd", "e", "ec"] {
+            assert!(machine.is_match(input), "The table accepts {}", input);
+        }
+        for input in ["", "b", "ab", "bcc"] {
+            assert!(!machine.is_match(input), "The table rejects {}", input);
+        }
+        assert!(
+            machine.check_invariants().is_ok(),
+            "Fan-out keeps two targets"
+        );
+        let quoted = "from,symbol,to\r\n\r\n0,\",\",1\r\n0,\"\"\"\",1\r\n";
+        let machine = ANFA::from_transitions_csv(quoted, 0, &[1]).unwrap();
+        assert!(machine.is_match(","), "Quoted commas are symbols");
+        assert!(machine.is_match("\""), "Doubled quotes are symbols");
+    }
+
+    #[test]
+    fn test_from_transitions_csv_errors() {
+        let cases = [
+            ("0,a,1\n", "The header is required"),
+            ("from,symbol,to\n0,a\n", "Rows have three fields"),
+            ("from,symbol,to\nx,a,1\n", "Ids are numbers"),
+            ("from,symbol,to\n0,ab,1\n", "Symbols are known"),
+            ("from,symbol,to\n0,[a-],1\n", "Classes are ranges"),
+            ("from,symbol,to\n0,a,3\n", "Ids are contiguous"),
+            ("from,symbol,to\n0,\"a,1\n", "Quotes are closed"),
+            (
+                "from,symbol,to\n0,a,999999999999\n",
+                "Huge ids are rejected",
+            ),
+        ];
+        for (csv, message) in cases {
+            assert!(
+                ANFA::from_transitions_csv(csv, 0, &[1]).is_err(),
+                "{}",
+                message
+            );
+        }
+        let max = alloc::format!("from,symbol,to\n0,a,{}\n", usize::MAX);
+        assert_eq!(
+            ANFA::from_transitions_csv(&max, 0, &[1]).err(),
+            Some(CompileError::StateOutOfBounds(usize::MAX)),
+            "The largest id does not overflow"
+        );
+        assert_eq!(
+            ANFA::from_transitions_csv("from,symbol,to\n0,a,3\n", 0, &[3]).err(),
+            Some(CompileError::StateOutOfBounds(3)),
+            "Sparse ids are out of bounds"
+        );
+    }
+}
@@ -0,0 +1,1393 @@
+use crate::compilers::forward_compiler::ForwardCompiler;
+use crate::compilers::CompilerExt;
+use crate::error::CompileError;
+use crate::matcher::Matcher;
+use crate::{transition, AutomataRef, Label, QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+
+/// A deterministic automaton built from the top machine of an ANFA by subset construction.
+///
+/// Chars that appear in a `Label::Char` or `Label::Class` transition have explicit transitions,
+/// one per block of chars that behave alike, see `ANFA::alphabet_partition`.
+/// Every other char follows `otherwise`. The empty set of states is kept as a
+/// dead state, so every state has a transition for every char, i.e. the DFA is
+/// complete, which complementing needs. See `DFA::trim` for a DFA without it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DFA {
+    /// Initial state
+    pub start: usize,
+    /// Transitions along explicit blocks of chars `(lo, hi)`, which never overlap
+    pub transitions: vec::Vec<BTreeMap<(char, char), usize>>,
+    /// Transition along every char without an explicit transition
+    pub otherwise: vec::Vec<Option<usize>>,
+    /// Final states
+    pub accepting: vec::Vec<bool>,
+}
+
+impl DFA {
+    /// Returns true when the DFA accepts the whole input.
+    pub fn is_match(&self, input: &str) -> bool {
+        let mut state = self.start;
+        for c in input.chars() {
+            state = match self.next(state, c) {
+                None => {
+                    return false;
+                }
+                Some(next) => next,
+            };
+        }
+        self.accepting[state]
+    }
+
+    /// Returns the state reached from `state` along `c`.
+    pub fn next(&self, state: usize, c: char) -> Option<usize> {
+        // the block starting at or before c, if it also ends at or after c
+        match self.transitions[state].range(..=(c, char::MAX)).next_back() {
+            Some((&(_lo, hi), &next)) if c <= hi => Some(next),
+            _ => self.otherwise[state],
+        }
+    }
+
+    /// Returns the blocks of chars with explicit transitions, sorted, split so
+    /// every char of a block behaves alike in every state.
+    pub fn alphabet(&self) -> vec::Vec<(char, char)> {
+        partition(
+            self.transitions
+                .iter()
+                .flat_map(|transitions| transitions.keys().copied()),
+        )
+    }
+
+    /// Returns true when no final state is reachable, i.e. the language is empty.
+    pub fn is_empty(&self) -> bool {
+        let mut visited = vec![false; self.accepting.len()];
+        let mut stack = vec![self.start];
+        while let Some(state) = stack.pop() {
+            if visited[state] {
+                continue;
+            }
+            if self.accepting[state] {
+                return false;
+            }
+            visited[state] = true;
+            stack.extend(self.transitions[state].values());
+            stack.extend(self.otherwise[state]);
+        }
+        true
+    }
+
+    /// Returns the minimal DFA accepting the same language, by Moore's partition refinement.
+    ///
+    /// Unreachable states are dropped, then states are split by acceptance and
+    /// refined until every state in a block moves to the same blocks on every
+    /// char. Blocks are numbered in breadth-first order from `start`, so DFAs of
+    /// the same language minimize to equal DFAs.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let dfa = parse("a|a").unwrap().to_dfa().minimize();
+    /// assert_eq!(dfa, parse("a").unwrap().to_dfa().minimize());
+    /// ```
+    pub fn minimize(&self) -> DFA {
+        let order = self.breadth_first();
+        let (blocks, count) = self.refine(&order);
+        // renumber blocks in breadth-first order and keep one state of each
+        let mut ids = vec![None; count];
+        let mut representatives = vec::Vec::with_capacity(count);
+        for &state in order.iter() {
+            if ids[blocks[state]].is_none() {
+                ids[blocks[state]] = Some(representatives.len());
+                representatives.push(state);
+            }
+        }
+        let id = |state: usize| ids[blocks[state]].unwrap_or(0);
+        DFA {
+            start: 0,
+            transitions: representatives
+                .iter()
+                .map(|&state| {
+                    self.transitions[state]
+                        .iter()
+                        .map(|(&block, &next)| (block, id(next)))
+                        .collect()
+                })
+                .collect(),
+            otherwise: representatives
+                .iter()
+                .map(|&state| self.otherwise[state].map(id))
+                .collect(),
+            accepting: representatives
+                .iter()
+                .map(|&state| self.accepting[state])
+                .collect(),
+        }
+    }
+
+    /// Returns the block of each state in `order` after Moore's partition
+    /// refinement, and the number of blocks. See `DFA::minimize`.
+    fn refine(&self, order: &[usize]) -> (vec::Vec<usize>, usize) {
+        let alphabet = self.alphabet();
+        let mut blocks = vec![0; self.accepting.len()];
+        for &state in order.iter() {
+            blocks[state] = usize::from(self.accepting[state]);
+        }
+        let mut count = 0;
+        loop {
+            // a state's signature is its block and the blocks it moves to
+            let mut ids = BTreeMap::new();
+            let mut next_blocks = vec![0; self.accepting.len()];
+            for &state in order.iter() {
+                let mut signature = vec![Some(blocks[state])];
+                for &(lo, _hi) in alphabet.iter() {
+                    signature.push(self.next(state, lo).map(|next| blocks[next]));
+                }
+                signature.push(self.otherwise[state].map(|next| blocks[next]));
+                let id = ids.len();
+                next_blocks[state] = *ids.entry(signature).or_insert(id);
+            }
+            blocks = next_blocks;
+            if ids.len() == count {
+                break;
+            }
+            count = ids.len();
+        }
+        (blocks, count)
+    }
+
+    /// Returns the DFA without its dead states, the states that cannot reach an
+    /// accepting state, e.g. the empty set of subset construction.
+    ///
+    /// Transitions into dead states become absent entries, so matching fails as
+    /// soon as no entry exists. The result accepts the same strings, but it is not
+    /// complete, so flipping `accepting` no longer complements it. The start is
+    /// kept even when it is dead. States keep their order.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let dfa = parse("ab").unwrap().to_dfa();
+    /// assert_eq!(dfa.trim().accepting.len(), dfa.accepting.len() - 1);
+    /// ```
+    pub fn trim(&self) -> DFA {
+        let len = self.accepting.len();
+        let mut predecessors = vec![vec::Vec::new(); len];
+        for state in 0..len {
+            let nexts = self.transitions[state]
+                .values()
+                .chain(self.otherwise[state].iter());
+            for &next in nexts {
+                predecessors[next].push(state);
+            }
+        }
+        let mut live = self.accepting.clone();
+        let mut stack: vec::Vec<usize> = (0..len).filter(|&state| live[state]).collect();
+        while let Some(state) = stack.pop() {
+            for &previous in predecessors[state].iter() {
+                if !live[previous] {
+                    live[previous] = true;
+                    stack.push(previous);
+                }
+            }
+        }
+        let mut ids = vec![None; len];
+        let mut kept = vec::Vec::new();
+        for state in (0..len).filter(|&state| live[state] || state == self.start) {
+            ids[state] = Some(kept.len());
+            kept.push(state);
+        }
+        // a dead start is kept, but nothing leads to it
+        let id = |next: usize| if live[next] { ids[next] } else { None };
+        DFA {
+            start: ids[self.start].unwrap_or(0),
+            transitions: kept
+                .iter()
+                .map(|&state| {
+                    self.transitions[state]
+                        .iter()
+                        .filter_map(|(&block, &next)| Some((block, id(next)?)))
+                        .collect()
+                })
+                .collect(),
+            otherwise: kept
+                .iter()
+                .map(|&state| self.otherwise[state].and_then(id))
+                .collect(),
+            accepting: kept.iter().map(|&state| self.accepting[state]).collect(),
+        }
+    }
+
+    /// Returns every state reachable from `start`, in breadth-first order
+    /// along sorted chars and then `otherwise`.
+    fn breadth_first(&self) -> vec::Vec<usize> {
+        let mut visited = vec![false; self.accepting.len()];
+        let mut order = vec![self.start];
+        visited[self.start] = true;
+        let mut i = 0;
+        while i < order.len() {
+            let state = order[i];
+            let nexts = self.transitions[state]
+                .values()
+                .chain(self.otherwise[state].iter());
+            for &next in nexts {
+                if !visited[next] {
+                    visited[next] = true;
+                    order.push(next);
+                }
+            }
+            i += 1;
+        }
+        order
+    }
+
+    /// Returns the product automaton of `self` and `other`, which accepts when
+    /// `accept(accepted by self, accepted by other)` is true.
+    /// A missing transition in either operand leads to a non-accepting dead state.
+    pub(crate) fn product(&self, other: &DFA, accept: impl Fn(bool, bool) -> bool) -> DFA {
+        let alphabet = partition(self.alphabet().into_iter().chain(other.alphabet()));
+        let accepting = |dfa: &DFA, state: Option<usize>| state.is_some_and(|q| dfa.accepting[q]);
+        let mut product = DFA {
+            start: 0,
+            transitions: vec::Vec::new(),
+            otherwise: vec::Vec::new(),
+            accepting: vec::Vec::new(),
+        };
+        let mut ids = BTreeMap::new();
+        let mut pairs = vec![(Some(self.start), Some(other.start))];
+        ids.insert(pairs[0], 0);
+        let mut i = 0;
+        while i < pairs.len() {
+            let (a, b) = pairs[i];
+            let mut intern = |pair: (Option<usize>, Option<usize>)| match ids.get(&pair) {
+                Some(&id) => id,
+                None => {
+                    let id = pairs.len();
+                    ids.insert(pair, id);
+                    pairs.push(pair);
+                    id
+                }
+            };
+            let mut transitions = BTreeMap::new();
+            for &(lo, hi) in alphabet.iter() {
+                let next = (
+                    a.and_then(|a| self.next(a, lo)),
+                    b.and_then(|b| other.next(b, lo)),
+                );
+                transitions.insert((lo, hi), intern(next));
+            }
+            let next = (
+                a.and_then(|a| self.otherwise[a]),
+                b.and_then(|b| other.otherwise[b]),
+            );
+            let otherwise = intern(next);
+            product.transitions.push(transitions);
+            product.otherwise.push(Some(otherwise));
+            product
+                .accepting
+                .push(accept(accepting(self, a), accepting(other, b)));
+            i += 1;
+        }
+        product
+    }
+}
+
+/// Subset construction over the top machine of an ANFA.
+/// Every set of states is sorted so it can be used as a key.
+struct Subsets<'a> {
+    anfa: &'a ANFA,
+    matcher: Matcher,
+    machine: Option<AutomataRef>,
+    /// The alphabet in blocks, see `ANFA::alphabet_partition`
+    partition: vec::Vec<(char, char)>,
+}
+
+impl<'a> Subsets<'a> {
+    fn new(anfa: &'a ANFA, machine: Option<AutomataRef>) -> Subsets<'a> {
+        Subsets {
+            anfa,
+            matcher: Matcher::default(),
+            machine,
+            partition: anfa.alphabet_partition(),
+        }
+    }
+
+    fn start(&self) -> vec::Vec<QId> {
+        match self.machine {
+            None => vec::Vec::new(),
+            Some([q0, _f]) => {
+                self.sorted(self.matcher.anchored_closure(self.anfa, &[q0], true, false))
+            }
+        }
+    }
+
+    fn is_accepting(&self, states: &[QId]) -> bool {
+        match self.machine {
+            None => false,
+            // `$` is followed when the input ends in this state. A `$` leading to
+            // `^` is never followed, even for empty input.
+            Some([_q0, f]) => {
+                states.binary_search(&f).is_ok()
+                    || self
+                        .matcher
+                        .anchored_closure(self.anfa, states, false, true)
+                        .contains(&f)
+            }
+        }
+    }
+
+    /// Returns the states reached along `c`, or along a char outside the alphabet when `c` is `None`.
+    fn next(&self, states: &[QId], c: Option<char>) -> vec::Vec<QId> {
+        match c {
+            Some(c) => self.sorted(self.matcher.step(self.anfa, states, c)),
+            None => {
+                let mut next = vec::Vec::new();
+                for &q in states {
+                    // chars outside the alphabet only match `.` and negated classes
+                    if let (Label::Any | Label::Class(_, true), targets) = &self.anfa.delta[q] {
+                        next.extend(targets.iter().flatten());
+                    }
+                }
+                self.sorted(self.matcher.epsilon_closure(self.anfa, &next))
+            }
+        }
+    }
+
+    /// Returns the symbol `c` behaves as, i.e. the first char of its block, or
+    /// `None` when `c` is outside the alphabet.
+    fn symbol(&self, c: char) -> Option<char> {
+        let after = self.partition.partition_point(|&(lo, _hi)| lo <= c);
+        match after.checked_sub(1).map(|i| self.partition[i]) {
+            Some((lo, hi)) if c <= hi => Some(lo),
+            _ => None,
+        }
+    }
+
+    fn sorted(&self, mut states: vec::Vec<QId>) -> vec::Vec<QId> {
+        states.sort_unstable();
+        states
+    }
+}
+
+/// A DFA that runs subset construction on demand while matching.
+///
+/// Only the DFA states an input actually reaches are built. At most `capacity`
+/// states are cached; when the cache is full it is flushed and states are
+/// recomputed as they are reached again.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::dfa::LazyDfa;
+/// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+/// ForwardCompiler::star(&mut machine).unwrap();
+/// let mut dfa = LazyDfa::from_anfa(&machine);
+/// assert!(dfa.is_match("aaa"));
+/// ```
+pub struct LazyDfa<'a> {
+    subsets: Subsets<'a>,
+    capacity: usize,
+    states: vec::Vec<vec::Vec<QId>>,
+    ids: BTreeMap<vec::Vec<QId>, usize>,
+    transitions: vec::Vec<BTreeMap<char, usize>>,
+}
+
+impl<'a> LazyDfa<'a> {
+    /// Returns a lazy DFA caching up to 1024 states.
+    pub fn from_anfa(anfa: &'a ANFA) -> LazyDfa<'a> {
+        LazyDfa::with_capacity(anfa, 1024)
+    }
+
+    /// Returns a lazy DFA caching up to `capacity` states. At least two states are always cached.
+    pub fn with_capacity(anfa: &'a ANFA, capacity: usize) -> LazyDfa<'a> {
+        LazyDfa {
+            subsets: Subsets::new(anfa, anfa.top_machine()),
+            capacity: capacity.max(2),
+            states: vec::Vec::new(),
+            ids: BTreeMap::new(),
+            transitions: vec::Vec::new(),
+        }
+    }
+
+    /// Returns true when the top machine accepts the whole input.
+    pub fn is_match(&mut self, input: &str) -> bool {
+        let start = self.subsets.start();
+        if self.states.len() >= self.capacity && !self.ids.contains_key(&start) {
+            self.flush();
+        }
+        let mut state = self.intern(start);
+        for c in input.chars() {
+            if self.states[state].is_empty() {
+                return false;
+            }
+            state = self.next(state, c);
+        }
+        self.subsets.is_accepting(&self.states[state])
+    }
+
+    /// Returns the number of cached states.
+    pub fn cached_states(&self) -> usize {
+        self.states.len()
+    }
+
+    fn next(&mut self, state: usize, c: char) -> usize {
+        if let Some(&next) = self.transitions[state].get(&c) {
+            return next;
+        }
+        let next_states = self
+            .subsets
+            .next(&self.states[state], self.subsets.symbol(c));
+        let mut state = state;
+        if self.states.len() >= self.capacity && !self.ids.contains_key(&next_states) {
+            // keep only the state being left
+            let states = self.states.swap_remove(state);
+            self.flush();
+            state = self.intern(states);
+        }
+        let next = self.intern(next_states);
+        self.transitions[state].insert(c, next);
+        next
+    }
+
+    fn flush(&mut self) {
+        self.states.clear();
+        self.ids.clear();
+        self.transitions.clear();
+    }
+
+    fn intern(&mut self, states: vec::Vec<QId>) -> usize {
+        if let Some(&id) = self.ids.get(&states) {
+            return id;
+        }
+        let id = self.states.len();
+        self.ids.insert(states.clone(), id);
+        self.states.push(states);
+        self.transitions.push(BTreeMap::new());
+        id
+    }
+}
+
+impl ANFA {
+    /// Returns the DFA of the top machine by subset construction.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::union(&mut machine).unwrap();
+    /// let dfa = machine.to_dfa();
+    /// assert!(dfa.is_match("b"));
+    /// assert!(!dfa.is_match("ab"));
+    /// ```
+    pub fn to_dfa(&self) -> DFA {
+        self.machine_to_dfa(self.top_machine())
+    }
+
+    /// Returns the DFA of the top machine without dead states. See `DFA::trim`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let dfa = parse("a*b").unwrap().to_dfa_trimmed();
+    /// assert!(dfa.is_match("aab"));
+    /// assert_eq!(dfa.next(dfa.start, 'c'), None);
+    /// ```
+    pub fn to_dfa_trimmed(&self) -> DFA {
+        self.to_dfa().trim()
+    }
+
+    /// Returns the chars labels distinguish, in blocks `(lo, hi)` where every
+    /// char is matched by the same labels of `delta`, sorted.
+    ///
+    /// Each `Label::Char` and each range of a class bounds a block, and `.`
+    /// contributes `'\n'`, the only char it treats differently. Chars outside
+    /// every block behave alike, like `DFA::otherwise`. Subset construction steps
+    /// once per block rather than once per char.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('m').unwrap();
+    /// ForwardCompiler::expr_range(&mut machine, 'a', 'z').unwrap();
+    /// ForwardCompiler::union(&mut machine).unwrap();
+    /// assert_eq!(
+    ///     machine.alphabet_partition(),
+    ///     [('a', 'l'), ('m', 'm'), ('n', 'z')]
+    /// );
+    /// ```
+    pub fn alphabet_partition(&self) -> vec::Vec<(char, char)> {
+        let mut ranges = vec::Vec::new();
+        for (label, _) in self.delta.iter() {
+            match label {
+                Label::Char(c) => ranges.push((*c, *c)),
+                Label::Any => ranges.push(('\n', '\n')),
+                Label::Class(class, _) => ranges.extend(class.iter().copied()),
+                Label::Epsilon | Label::Anchor(_) => {}
+            }
+        }
+        partition(ranges)
+    }
+
+    /// Returns the DFA of `machine` by subset construction. No machine accepts nothing.
+    pub(crate) fn machine_to_dfa(&self, machine: Option<AutomataRef>) -> DFA {
+        let subsets = Subsets::new(self, machine);
+        let mut dfa = DFA {
+            start: 0,
+            transitions: vec::Vec::new(),
+            otherwise: vec::Vec::new(),
+            accepting: vec::Vec::new(),
+        };
+        let mut ids = BTreeMap::new();
+        let mut states = vec::Vec::new();
+        let start = subsets.start();
+        ids.insert(start.clone(), 0);
+        states.push(start);
+        let mut i = 0;
+        while i < states.len() {
+            let mut transitions = BTreeMap::new();
+            let mut otherwise = None;
+            // every char of a block behaves the same, so one step covers the block
+            let symbols = subsets.partition.iter().map(|&block| Some(block));
+            for symbol in symbols.chain(core::iter::once(None)) {
+                let next = subsets.next(&states[i], symbol.map(|(lo, _hi)| lo));
+                let id = match ids.get(&next) {
+                    Some(&id) => id,
+                    None => {
+                        let id = states.len();
+                        ids.insert(next.clone(), id);
+                        states.push(next);
+                        id
+                    }
+                };
+                match symbol {
+                    Some(block) => {
+                        transitions.insert(block, id);
+                    }
+                    None => otherwise = Some(id),
+                }
+            }
+            dfa.transitions.push(transitions);
+            dfa.otherwise.push(otherwise);
+            dfa.accepting.push(subsets.is_accepting(&states[i]));
+            i += 1;
+        }
+        dfa
+    }
+
+    /// Returns an ANFA with one machine accepting the language of a DFA transition table.
+    ///
+    /// `transitions[state]` maps chars to next states, `accepting[state]` marks final
+    /// states, and a char without a transition rejects. Each DFA state becomes an
+    /// epsilon state that fans out to one `Label::Char` state per outgoing edge, and
+    /// to `f` when the DFA state is accepting.
+    ///
+    /// ```rust
+    /// use regexxx::ANFA;
+    /// use std::collections::BTreeMap;
+    /// // RE a*b
+    /// let transitions = [BTreeMap::from([('a', 0), ('b', 1)]), BTreeMap::new()];
+    /// let machine = ANFA::from_dfa_table(&transitions, &[false, true], 0).unwrap();
+    /// assert!(machine.is_match("aab"));
+    /// assert!(!machine.is_match("ba"));
+    /// ```
+    pub fn from_dfa_table(
+        transitions: &[BTreeMap<char, usize>],
+        accepting: &[bool],
+        start: usize,
+    ) -> Result<ANFA, CompileError> {
+        let len = transitions.len();
+        if accepting.len() != len {
+            return Err(CompileError::Invalid(
+                "Accepting mask must cover every state.",
+            ));
+        }
+        if start >= len
+            || transitions
+                .iter()
+                .flat_map(|t| t.values())
+                .any(|&q| q >= len)
+        {
+            return Err(CompileError::Invalid(
+                "Transition to a state outside the table.",
+            ));
+        }
+        let mut anfa = ANFA::new();
+        anfa.push_dfa(&DFA {
+            start,
+            transitions: transitions
+                .iter()
+                .map(|edges| edges.iter().map(|(&c, &next)| ((c, c), next)).collect())
+                .collect(),
+            otherwise: vec![None; len],
+            accepting: accepting.to_vec(),
+        });
+        Ok(anfa)
+    }
+
+    /// Pushes a machine accepting the language of `dfa`.
+    ///
+    /// Each DFA state becomes an epsilon state that fans out to one `Label::Char`
+    /// or `Label::Class` state per explicit block, to a negated `Label::Class` of
+    /// the alphabet for `otherwise`, and to `f` when the DFA state is accepting.
+    pub(crate) fn push_dfa(&mut self, dfa: &DFA) {
+        let offset = self.delta.len();
+        let len = dfa.transitions.len();
+        for _ in 0..len {
+            self.delta.push((
+                // DFA state, transitions are set below
+                Label::Epsilon,
+                [None, None],
+            ));
+        }
+        let machine_f = offset + len;
+        self.delta.push((
+            // push f
+            Label::Epsilon,
+            [None, None],
+        ));
+        let mut outside = vec::Vec::new();
+        for (lo, hi) in dfa.alphabet() {
+            match outside.last_mut() {
+                Some((_, end)) if char::from_u32(*end as u32 + 1) == Some(lo) => *end = hi,
+                _ => outside.push((lo, hi)),
+            }
+        }
+        for (state, edges) in dfa.transitions.iter().enumerate() {
+            let mut targets = vec::Vec::with_capacity(edges.len() + 2);
+            for (&(lo, hi), &next) in edges.iter() {
+                let label = if lo == hi {
+                    Label::Char(lo)
+                } else {
+                    Label::Class(vec![(lo, hi)], false)
+                };
+                self.delta.push((
+                    // push edge along the block to the next DFA state
+                    label,
+                    [Some(offset + next), None],
+                ));
+                targets.push(self.delta.len() - 1);
+            }
+            if let Some(next) = dfa.otherwise[state] {
+                self.delta.push((
+                    // push edge along every char outside the alphabet
+                    Label::Class(outside.clone(), true),
+                    [Some(offset + next), None],
+                ));
+                targets.push(self.delta.len() - 1);
+            }
+            if dfa.accepting[state] {
+                targets.push(machine_f);
+            }
+            self.delta[offset + state].1 = self.max_outgoing_targets_guard(&targets);
+        }
+        self.automata_refs.push([offset + dfa.start, machine_f]);
+    }
+
+    /// Returns true when the top machine accepts no string at all.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert!(parse("a[]").unwrap().is_empty_language());
+    /// assert!(!parse("()").unwrap().is_empty_language());
+    /// ```
+    pub fn is_empty_language(&self) -> bool {
+        self.to_dfa().is_empty()
+    }
+
+    /// Returns true when the top machine accepts every string over `alphabet`,
+    /// i.e. its complement over `alphabet` is empty.
+    ///
+    /// The machine is complemented with `ANFA::complement_universal`, intersected
+    /// with `alphabet*`, and checked with `ANFA::is_empty_language`. Chars outside
+    /// `alphabet` are ignored, so `(a|b)*` is universal over `a` and `b`, but not
+    /// over every char.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert!(parse("(a|b)*").unwrap().accepts_universe(&['a', 'b']));
+    /// assert!(!parse("a*").unwrap().accepts_universe(&['a', 'b']));
+    /// ```
+    pub fn accepts_universe(&self, alphabet: &[char]) -> bool {
+        let mut anfa = ANFA::new();
+        anfa.push_dfa(&self.to_dfa());
+        // alphabet* loops on one state that is also f
+        let q = anfa.push_state();
+        let sigma = Label::Class(alphabet.iter().map(|&c| (c, c)).collect(), false);
+        let complement = anfa
+            .complement_universal()
+            .and_then(|()| anfa.set_targets(q, sigma, &[q]))
+            .and_then(|()| anfa.mark_machine(q, q))
+            .and_then(|()| anfa.intersection());
+        // every operator has its operands, so none fails
+        complement.is_ok() && anfa.is_empty_language()
+    }
+
+    /// Returns true when `delta` has no epsilon transitions and every labeled
+    /// state has at most one target, so at most one state is ever active.
+    ///
+    /// A state whose transition has two targets moves to both along the same
+    /// char, so it is nondeterministic. Final states, which never transition,
+    /// are not epsilon transitions.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert!(machine.is_deterministic());
+    /// ForwardCompiler::star(&mut machine).unwrap();
+    /// assert!(!machine.is_deterministic());
+    /// ```
+    pub fn is_deterministic(&self) -> bool {
+        self.delta
+            .iter()
+            .all(|t| !transition::is_epsilon(t) && !transition::is_union(t))
+    }
+
+    /// Returns true when, from every state reachable from `q0` of the top machine,
+    /// each char of `alphabet` leads to exactly one state, i.e. the machine is a
+    /// complete DFA over `alphabet`, which complementing requires.
+    ///
+    /// Unlike `ANFA::is_deterministic`, epsilon transitions are followed, so the
+    /// fan-out states of an imported DFA are allowed. Anchors are not followed.
+    /// Without a machine nothing is total.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("ab").unwrap();
+    /// assert!(!machine.is_total(&['a', 'b']));
+    /// assert!(machine.minimize().is_total(&['a', 'b']));
+    /// ```
+    pub fn is_total(&self, alphabet: &[char]) -> bool {
+        let [q0, _f] = match self.top_machine() {
+            None => return false,
+            Some(machine) => machine,
+        };
+        let matcher = Matcher::default();
+        let mut seen = vec![false; self.delta.len()];
+        seen[q0] = true;
+        let mut stack = vec![q0];
+        while let Some(q) = stack.pop() {
+            let closure = matcher.epsilon_closure(self, &[q]);
+            for &c in alphabet {
+                match matcher.step_set(self, &closure, c)[..] {
+                    [next] if !seen[next] => {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                    [_] => {}
+                    _ => return false,
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns an ANFA with one machine accepting the language of the top machine,
+    /// built from its minimal DFA. See `DFA::minimize`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("(a|b)*abb").unwrap().minimize();
+    /// assert!(machine.is_match("babb"));
+    /// assert!(!machine.is_match("abab"));
+    /// ```
+    pub fn minimize(&self) -> ANFA {
+        let mut anfa = ANFA::new();
+        anfa.push_dfa(&self.to_dfa().minimize());
+        anfa
+    }
+
+    /// Returns the number of states of the minimal DFA of the top machine, a
+    /// gauge of the pattern's complexity. See `ANFA::minimize`.
+    ///
+    /// The DFA is complete, so the count includes the dead state when one is
+    /// reachable. This is no cheaper than `ANFA::minimize`: the full DFA is built
+    /// by subset construction and refined, and only the minimal DFA itself is not
+    /// built, since its states are only counted.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert_eq!(parse("(a|b)*abb").unwrap().min_dfa_state_count(), 5);
+    /// ```
+    pub fn min_dfa_state_count(&self) -> usize {
+        let dfa = self.to_dfa();
+        dfa.refine(&dfa.breadth_first()).1
+    }
+
+    /// Replaces the top machine with its minimal DFA, like `ANFA::minimize` but in
+    /// place, so machines below it are kept. See `DFA::minimize`.
+    ///
+    /// The states of the old machine stay in `delta`, unreachable from the new one,
+    /// until `ANFA::trim_unreachable_from_q0`. Fails when there is no machine.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("(a|b)*abb").unwrap();
+    /// machine.minimize_in_place().unwrap();
+    /// assert!(machine.is_match("babb"));
+    /// assert!(!machine.is_match("abab"));
+    /// ```
+    pub fn minimize_in_place(&mut self) -> Result<(), CompileError> {
+        let dfa = self.to_dfa().minimize();
+        if self.automata_refs.pop().is_none() {
+            return Err(CompileError::Invalid("Minimizing requires one operand."));
+        }
+        self.push_dfa(&dfa);
+        Ok(())
+    }
+
+    /// Returns a DFA of the top machine that is built while matching. See `LazyDfa`.
+    pub fn determinize_incremental(&self) -> LazyDfa<'_> {
+        LazyDfa::from_anfa(self)
+    }
+
+    /// Returns true when the top machines of `self` and `other` accept the same language.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine_a = parse("(a|b)*").unwrap();
+    /// let machine_b = parse("(a*b*)*").unwrap();
+    /// assert!(machine_a.equivalent(&machine_b));
+    /// ```
+    pub fn equivalent(&self, other: &ANFA) -> bool {
+        self.to_dfa()
+            .product(&other.to_dfa(), |a, b| a != b)
+            .is_empty()
+    }
+
+    /// Returns true when the top machine accepts `s` and no other string.
+    ///
+    /// The language is compared with a machine built by `CompilerExt::literal_str`,
+    /// so the check covers strings of every length.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert!(parse("a(b|[])").unwrap().accepts_exactly("ab"));
+    /// assert!(!parse("ab*").unwrap().accepts_exactly("ab"));
+    /// ```
+    pub fn accepts_exactly(&self, s: &str) -> bool {
+        let mut literal = ANFA::with_capacity(2 * s.chars().count(), 2);
+        // pushing literals cannot fail
+        let _ = ForwardCompiler::literal_str(&mut literal, s);
+        self.equivalent(&literal)
+    }
+
+    /// Returns true when every string accepted by the top machine of `self` is also
+    /// accepted by the top machine of `other`, i.e. `L(self) ∩ ¬L(other)` is empty.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine_a = parse("ab").unwrap();
+    /// let machine_b = parse("a(b|c)").unwrap();
+    /// assert!(machine_a.is_subset_of(&machine_b));
+    /// assert!(!machine_b.is_subset_of(&machine_a));
+    /// ```
+    pub fn is_subset_of(&self, other: &ANFA) -> bool {
+        self.to_dfa()
+            .product(&other.to_dfa(), |a, b| a && !b)
+            .is_empty()
+    }
+}
+
+/// Returns the blocks `(lo, hi)` that split `ranges` at every bound, sorted, so
+/// every char of a block is inside the same ranges. Chars outside every range
+/// are in no block, and empty ranges are ignored.
+fn partition(ranges: impl IntoIterator<Item = (char, char)>) -> vec::Vec<(char, char)> {
+    // +1 where a range starts and -1 after it ends, as scalar values
+    let mut bounds = BTreeMap::new();
+    for (lo, hi) in ranges.into_iter().filter(|(lo, hi)| lo <= hi) {
+        *bounds.entry(lo as u32).or_insert(0) += 1;
+        *bounds.entry(hi as u32 + 1).or_insert(0) -= 1;
+    }
+    let mut partition = vec::Vec::new();
+    let mut covering = 0;
+    let mut bounds = bounds.into_iter().peekable();
+    while let Some((start, change)) = bounds.next() {
+        covering += change;
+        let end = match bounds.peek() {
+            Some(&(next, _)) if covering > 0 => next - 1,
+            _ => continue,
+        };
+        // blocks skip the surrogates, which are not chars
+        let lo = char::from_u32(start).unwrap_or('\u{e000}');
+        let hi = char::from_u32(end).unwrap_or('\u{d7ff}');
+        if lo <= hi {
+            partition.push((lo, hi));
+        }
+    }
+    partition
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::compilers::CompilerExt;
+    use crate::dfa::LazyDfa;
+    use crate::parser::parse;
+    use crate::{Label, ANFA};
+    use alloc::collections::BTreeMap;
+    use alloc::string::String;
+
+    /// Returns `count` pseudo-random strings over `alphabet` from a fixed seed.
+    fn random_strings(alphabet: &[char], count: usize) -> alloc::vec::Vec<String> {
+        let mut seed: u32 = 1337;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) as usize
+        };
+        (0..count)
+            .map(|_| {
+                let len = next() % 8;
+                (0..len)
+                    .map(|_| alphabet[next() % alphabet.len()])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_to_dfa() {
+        // RE a(b|c)*d
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'd').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dfa = machine.to_dfa();
+        assert_eq!(
+            dfa.transitions.len(),
+            6,
+            "Subset construction finds 5 sets and the dead state"
+        );
+        for input in random_strings(&['a', 'b', 'c', 'd'], 200) {
+            assert_eq!(
+                dfa.is_match(&input),
+                machine.is_match(&input),
+                "DFA and ANFA agree on {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_dfa_any() {
+        // RE a.
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_any(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dfa = machine.to_dfa();
+        assert!(dfa.is_match("aa"), "'.' matches chars in the alphabet");
+        assert!(
+            dfa.is_match("a😀"),
+            "'.' matches chars outside the alphabet"
+        );
+        assert!(!dfa.is_match("a\n"), "'.' does not match a newline");
+        assert!(!dfa.is_match("b😀"), "Explicit chars must match");
+    }
+
+    #[test]
+    fn test_lazy_dfa() {
+        // RE (a|b)*abb
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        for c in ['a', 'b', 'b'] {
+            ForwardCompiler::expr_a(&mut machine, c).unwrap();
+            ForwardCompiler::concatenate(&mut machine).unwrap();
+        }
+        let dfa = machine.to_dfa();
+        let mut lazy = LazyDfa::from_anfa(&machine);
+        let mut evicting = LazyDfa::with_capacity(&machine, 2);
+        for input in random_strings(&['a', 'b', 'c'], 500) {
+            let expected = dfa.is_match(&input);
+            assert_eq!(
+                lazy.is_match(&input),
+                expected,
+                "Lazy and eager DFA agree on {:?}",
+                input
+            );
+            assert_eq!(
+                evicting.is_match(&input),
+                expected,
+                "Evicting lazy DFA agrees on {:?}",
+                input
+            );
+            assert!(evicting.cached_states() <= 2, "Cache is bounded");
+        }
+        assert!(
+            lazy.cached_states() <= dfa.transitions.len(),
+            "Lazy DFA only builds sets the inputs reach"
+        );
+    }
+
+    #[test]
+    fn test_from_dfa_table() {
+        let machine = parse("a(b|c)*d|c*").unwrap();
+        let dfa = machine.to_dfa();
+        let table: alloc::vec::Vec<BTreeMap<char, usize>> = dfa
+            .transitions
+            .iter()
+            .map(|edges| edges.iter().map(|(&(lo, _hi), &next)| (lo, next)).collect())
+            .collect();
+        let imported = ANFA::from_dfa_table(&table, &dfa.accepting, dfa.start).unwrap();
+        assert_eq!(
+            imported.automata_refs.len(),
+            1,
+            "Importing a table builds one machine"
+        );
+        assert!(
+            imported.equivalent(&machine),
+            "to_dfa then from_dfa_table preserves the language"
+        );
+        for input in random_strings(&['a', 'b', 'c', 'd'], 200) {
+            assert_eq!(
+                imported.is_match(&input),
+                machine.is_match(&input),
+                "Imported table and ANFA agree on {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_dfa_table_invalid() {
+        let transitions = [BTreeMap::from([('a', 1)])];
+        assert!(
+            ANFA::from_dfa_table(&transitions, &[true], 0).is_err(),
+            "Transitions must stay inside the table"
+        );
+        assert!(
+            ANFA::from_dfa_table(&[BTreeMap::new()], &[], 0).is_err(),
+            "Accepting mask must match the table"
+        );
+        assert!(
+            ANFA::from_dfa_table(&[BTreeMap::new()], &[true], 1).is_err(),
+            "Start must be a state of the table"
+        );
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let machine = parse("(a|b)*").unwrap();
+        assert!(
+            machine.equivalent(&parse("(a*b*)*").unwrap()),
+            "(a|b)* = (a*b*)*"
+        );
+        assert!(
+            machine.equivalent(&parse("(b|a)*").unwrap()),
+            "Union is commutative"
+        );
+        assert!(
+            !machine.equivalent(&parse("(a|b)(a|b)*").unwrap()),
+            "(a|b)* accepts '', (a|b)(a|b)* does not"
+        );
+        assert!(
+            !machine.equivalent(&parse("(a|c)*").unwrap()),
+            "Alphabets differ"
+        );
+        assert!(
+            !parse(".").unwrap().equivalent(&parse("a").unwrap()),
+            "'.' accepts chars outside 'a'"
+        );
+        assert!(
+            parse("[]").unwrap().equivalent(&parse("[]a").unwrap()),
+            "Both languages are empty"
+        );
+    }
+
+    #[test]
+    fn test_is_subset_of() {
+        let machine_aa = parse("aa").unwrap();
+        let machine_a_star = parse("a*").unwrap();
+        assert!(machine_aa.is_subset_of(&machine_a_star), "aa ⊆ a*");
+        assert!(!machine_a_star.is_subset_of(&machine_aa), "a* ⊄ aa");
+        assert!(
+            machine_a_star.is_subset_of(&machine_a_star),
+            "Containment is reflexive"
+        );
+        assert!(
+            parse("[]").unwrap().is_subset_of(&machine_aa),
+            "The empty language is contained in every language"
+        );
+        assert!(
+            !parse(".").unwrap().is_subset_of(&parse("a|b").unwrap()),
+            "'.' accepts chars outside both alphabets"
+        );
+    }
+
+    #[test]
+    fn test_is_deterministic() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert!(machine.is_deterministic(), "A literal is deterministic");
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert!(!machine.is_deterministic(), "Star has epsilon transitions");
+        machine.remove_epsilons().unwrap();
+        assert!(
+            !machine.is_deterministic(),
+            "Epsilon-free 'a'* still moves to two states along 'a'"
+        );
+        let mut machine = ANFA::with_capacity(1, 1);
+        let q0 = machine.push_state();
+        machine.set_labeled(q0, 'a', q0).unwrap();
+        machine.mark_machine(q0, q0).unwrap();
+        assert!(
+            machine.is_deterministic(),
+            "A loop with one target is deterministic"
+        );
+    }
+
+    #[test]
+    fn test_is_total() {
+        let machine = parse("ab").unwrap();
+        let mut trimmed = ANFA::new();
+        trimmed.push_dfa(&machine.to_dfa_trimmed());
+        assert!(
+            !trimmed.is_total(&['a', 'b']),
+            "Without a sink, b has no transition from the start"
+        );
+        let mut complete = ANFA::new();
+        complete.push_dfa(&machine.to_dfa());
+        assert!(
+            complete.is_total(&['a', 'b', 'c']),
+            "The dead state is a sink for every char"
+        );
+        assert!(complete.equivalent(&machine), "The sink accepts nothing");
+        let mut machine = ANFA::with_capacity(2, 1);
+        let q0 = machine.push_state();
+        let q1 = machine.push_state();
+        machine.delta[q0] = (Label::Char('a'), [Some(q0), Some(q1)]);
+        machine.set_labeled(q1, 'a', q1).unwrap();
+        machine.mark_machine(q0, q1).unwrap();
+        assert!(
+            !machine.is_total(&['a']),
+            "Moving to two states along a is nondeterministic"
+        );
+        machine.delta[q0].1 = [Some(q0), None];
+        assert!(machine.is_total(&['a']), "A loop along a is total over a");
+        assert!(!machine.is_total(&['a', 'b']), "The loop has no b");
+        assert!(!ANFA::new().is_total(&['a']), "No machine is not total");
+    }
+
+    #[test]
+    fn test_accepts_universe() {
+        let machine = parse("(a|b)*").unwrap();
+        assert!(
+            machine.accepts_universe(&['a', 'b']),
+            "(a|b)* accepts every string over a and b"
+        );
+        assert!(
+            !machine.accepts_universe(&['a', 'b', 'c']),
+            "(a|b)* rejects c"
+        );
+        assert!(
+            !parse("a*").unwrap().accepts_universe(&['a', 'b']),
+            "a* rejects b"
+        );
+        assert!(
+            !parse(".*").unwrap().accepts_universe(&['a', '\n']),
+            ". does not match a newline"
+        );
+        assert!(
+            parse("()").unwrap().accepts_universe(&[]),
+            "Over no chars, the universe is ''"
+        );
+        assert!(
+            !ANFA::new().accepts_universe(&[]),
+            "No machine accepts nothing"
+        );
+    }
+
+    #[test]
+    fn test_min_dfa_state_count() {
+        assert_eq!(
+            parse("a*").unwrap().min_dfa_state_count(),
+            2,
+            "a* loops in one state, and every other char is dead"
+        );
+        let machine = parse("(a|a)*a*").unwrap();
+        assert_eq!(machine.min_dfa_state_count(), 2, "(a|a)*a* is a*");
+        assert!(
+            machine.min_dfa_state_count() < machine.count_states(),
+            "The minimal DFA is smaller than the ANFA"
+        );
+        assert_eq!(
+            machine.min_dfa_state_count(),
+            machine.to_dfa().minimize().accepting.len(),
+            "The count is the size of the minimal DFA"
+        );
+    }
+
+    #[test]
+    fn test_to_dfa_anchors() {
+        let machine = parse("^a$|b").unwrap();
+        let dfa = machine.to_dfa();
+        assert!(dfa.is_match("a"), "^a$ accepts 'a'");
+        assert!(dfa.is_match("b"), "b accepts 'b'");
+        assert!(!dfa.is_match("ab"), "^a$ rejects 'ab'");
+        assert!(
+            machine.equivalent(&parse("a|b").unwrap()),
+            "Anchors at the ends of a whole match are redundant"
+        );
+        assert!(
+            parse("a^b").unwrap().is_empty_language(),
+            "^ after input accepts nothing"
+        );
+    }
+
+    #[test]
+    fn test_trim() {
+        let machine = parse("a*b").unwrap();
+        let complete = machine.to_dfa();
+        let trimmed = machine.to_dfa_trimmed();
+        assert_eq!(
+            trimmed.accepting.len(),
+            complete.accepting.len() - 1,
+            "Trimming drops the dead state of a*b"
+        );
+        assert!(
+            trimmed
+                .otherwise
+                .iter()
+                .all(|otherwise| otherwise.is_none()),
+            "Chars outside a*b have no transition"
+        );
+        for input in random_strings(&['a', 'b', 'c'], 200) {
+            assert_eq!(
+                trimmed.is_match(&input),
+                complete.is_match(&input),
+                "Trimmed and complete DFAs agree on {:?}",
+                input
+            );
+        }
+        let trimmed = parse("a[]").unwrap().to_dfa_trimmed();
+        assert_eq!(trimmed.accepting, [false], "A dead start is kept alone");
+        assert!(!trimmed.is_match(""), "The empty language rejects ''");
+    }
+
+    #[test]
+    fn test_alphabet_partition() {
+        let mut machine = ANFA::new();
+        ForwardCompiler::expr_range(&mut machine, 'a', 'z').unwrap();
+        ForwardCompiler::expr_range(&mut machine, '0', '9').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(
+            machine.alphabet_partition(),
+            [('0', '9'), ('a', 'z')],
+            "[a-z] and [0-9] are two blocks, not 36 chars"
+        );
+        ForwardCompiler::expr_range(&mut machine, 'x', 'é').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(
+            machine.alphabet_partition(),
+            [('0', '9'), ('a', 'w'), ('x', 'z'), ('{', 'é')],
+            "Overlapping ranges split into blocks"
+        );
+        let dfa = machine.to_dfa();
+        for input in ["az0x", "bé", "9{", "x"] {
+            assert_eq!(
+                dfa.is_match(input),
+                machine.is_match(input),
+                "DFA and ANFA agree on {:?}",
+                input
+            );
+        }
+        let machine = parse("a.").unwrap();
+        assert_eq!(
+            machine.alphabet_partition(),
+            [('\n', '\n'), ('a', 'a')],
+            ". only distinguishes a newline"
+        );
+        // RE [\0-\u{10ffff}]*, one transition per block rather than per char
+        let mut machine = ANFA::new();
+        ForwardCompiler::expr_range(&mut machine, '\0', char::MAX).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let dfa = machine.to_dfa();
+        assert!(
+            dfa.transitions.iter().all(|edges| edges.len() <= 2),
+            "Every char is in the blocks around the surrogates"
+        );
+        assert!(
+            dfa.is_match("a😀\u{10ffff}"),
+            "Blocks are looked up by range"
+        );
+        let minimal = machine.minimize();
+        assert!(minimal.is_match("z\0"), "Blocks are pushed as classes");
+        assert!(minimal.equivalent(&machine), "Minimizing keeps the blocks");
+    }
+
+    #[test]
+    fn test_minimize() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let literal = ForwardCompiler::from_expr_a('a').unwrap();
+        let dfa = machine.to_dfa().minimize();
+        assert_eq!(
+            dfa,
+            literal.to_dfa().minimize(),
+            "a|a collapses to the minimal DFA of a"
+        );
+        assert_eq!(
+            dfa.accepting.len(),
+            3,
+            "The minimal DFA of a has a start, final, and dead state"
+        );
+        let machine = parse("(a|b)*abb").unwrap();
+        let dfa = machine.to_dfa().minimize();
+        assert_eq!(
+            dfa.accepting.len(),
+            5,
+            "(a|b)*abb has four live states and a dead state"
+        );
+        assert!(
+            machine.minimize().equivalent(&machine),
+            "Minimizing preserves the language"
+        );
+    }
+
+    #[test]
+    fn test_minimize_in_place() {
+        let mut machine = parse("(a|a)*").unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        machine.minimize_in_place().unwrap();
+        assert_eq!(machine.automata_refs.len(), 2, "The machine below is kept");
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("aab"), "(a|a)*b accepts aab");
+        let mut machine = parse("(a|a)*").unwrap();
+        machine.minimize_in_place().unwrap();
+        assert!(machine.is_match("aa"), "(a|a)* accepts aa");
+        assert!(!machine.is_match("ab"), "(a|a)* rejects ab");
+        let states = machine.machine_states(machine.top_machine().unwrap());
+        let minimal = parse("a*").unwrap().minimize();
+        assert_eq!(
+            states.len(),
+            minimal.machine_states(minimal.top_machine().unwrap()).len(),
+            "(a|a)* minimizes to the states of a*"
+        );
+        assert!(
+            ANFA::new().minimize_in_place().is_err(),
+            "Minimizing requires a machine"
+        );
+    }
+
+    #[test]
+    fn test_accepts_exactly() {
+        let mut machine = ANFA::with_capacity(4, 2);
+        ForwardCompiler::literal_str(&mut machine, "ab").unwrap();
+        assert!(machine.accepts_exactly("ab"), "ab accepts exactly 'ab'");
+        assert!(!machine.accepts_exactly("a"), "ab does not accept 'a'");
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert!(
+            !machine.accepts_exactly("ab"),
+            "(ab)* accepts more than 'ab'"
+        );
+        assert!(
+            parse("()").unwrap().accepts_exactly(""),
+            "Epsilon accepts exactly ''"
+        );
+        assert!(
+            !parse("[]").unwrap().accepts_exactly(""),
+            "Nothing does not accept ''"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{AutomataRef, Label, ANFA};
+use alloc::string::String;
+use alloc::vec;
+use core::fmt::{self, Write};
+
+/// The direction Graphviz lays out ranks of states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RankDir {
+    /// Left to right, `rankdir=LR`
+    LeftRight,
+    /// Top to bottom, `rankdir=TB`
+    TopBottom,
+}
+
+/// Configures `ANFA::dot_with_options`.
+///
+/// The default lays out left to right, labels states with their `QId`, highlights
+/// nothing, and draws epsilon edges like any other edge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DotOptions {
+    /// Direction of the layout
+    pub rankdir: RankDir,
+    /// Label each state with its `QId`. Otherwise states are unlabeled.
+    pub state_ids: bool,
+    /// Draw the states and edges of this machine in bold. See `ANFA::machine_states`.
+    pub highlight: Option<AutomataRef>,
+    /// Color of epsilon edges, e.g. `"gray"`. `None` uses the default color.
+    pub epsilon_color: Option<&'static str>,
+}
+
+impl Default for DotOptions {
+    fn default() -> DotOptions {
+        DotOptions {
+            rankdir: RankDir::LeftRight,
+            state_ids: true,
+            highlight: None,
+            epsilon_color: None,
+        }
+    }
+}
+
+impl ANFA {
+    /// Returns a Graphviz DOT graph of every state. The top machine's `q0` is
+    /// marked by an arrow and its `f` is drawn as a double circle.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert!(machine.dot().contains("0 -> 1 [label=\"a\"];"));
+    /// ```
+    pub fn dot(&self) -> String {
+        self.dot_with_options(DotOptions::default())
+    }
+
+    /// Writes the graph of `ANFA::dot` into `w` without allocating a `String`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let mut dot = String::new();
+    /// machine.write_dot(&mut dot).unwrap();
+    /// assert_eq!(dot, machine.dot());
+    /// ```
+    pub fn write_dot<W: Write>(&self, w: &mut W) -> fmt::Result {
+        self.write_dot_with_options(w, DotOptions::default())
+    }
+
+    /// Returns a Graphviz DOT graph of every state, configured by `opts`.
+    ///
+    /// ```rust
+    /// use regexxx::dot::{DotOptions, RankDir};
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a|b").unwrap();
+    /// let dot = machine.dot_with_options(DotOptions {
+    ///     rankdir: RankDir::TopBottom,
+    ///     epsilon_color: Some("gray"),
+    ///     ..DotOptions::default()
+    /// });
+    /// assert!(dot.contains("rankdir=TB;"));
+    /// ```
+    pub fn dot_with_options(&self, opts: DotOptions) -> String {
+        let mut dot = String::new();
+        // writing into a String cannot fail
+        let _ = self.write_dot_with_options(&mut dot, opts);
+        dot
+    }
+
+    /// Writes the graph of `ANFA::dot_with_options` into `w`.
+    pub fn write_dot_with_options<W: Write>(&self, w: &mut W, opts: DotOptions) -> fmt::Result {
+        let mut highlighted = vec![false; self.delta.len()];
+        if let Some(machine) = opts.highlight {
+            for q in self.machine_states(machine) {
+                highlighted[q] = true;
+            }
+        }
+        let rankdir = match opts.rankdir {
+            RankDir::LeftRight => "LR",
+            RankDir::TopBottom => "TB",
+        };
+        writeln!(w, "digraph ANFA {{")?;
+        writeln!(w, "  rankdir={};", rankdir)?;
+        writeln!(w, "  node [shape=circle];")?;
+        let top = self.automata_refs.last();
+        if let Some([q0, _f]) = top {
+            writeln!(w, "  start [shape=point];")?;
+            writeln!(w, "  start -> {};", q0)?;
+        }
+        for (q, &bold) in highlighted.iter().enumerate() {
+            write!(w, "  {} [", q)?;
+            if opts.state_ids {
+                write!(w, "label=\"{}\"", q)?;
+            } else {
+                write!(w, "label=\"\"")?;
+            }
+            if top.is_some_and(|[_q0, f]| *f == q) {
+                write!(w, ", shape=doublecircle")?;
+            }
+            if bold {
+                write!(w, ", style=bold")?;
+            }
+            writeln!(w, "];")?;
+        }
+        for (q, (label, targets)) in self.delta.iter().enumerate() {
+            for &target in targets.iter().flatten() {
+                write!(w, "  {} -> {} [label=\"", q, target)?;
+                write_label(w, label)?;
+                write!(w, "\"")?;
+                if *label == Label::Epsilon {
+                    if let Some(color) = opts.epsilon_color {
+                        write!(w, ", color=\"{}\"", color)?;
+                    }
+                }
+                if highlighted[q] && highlighted[target] {
+                    write!(w, ", style=bold")?;
+                }
+                writeln!(w, "];")?;
+            }
+        }
+        writeln!(w, "}}")
+    }
+}
+
+/// Writes `label` as the text of a DOT edge label, escaping quotes and backslashes.
+fn write_label(w: &mut impl Write, label: &Label) -> fmt::Result {
+    label.write_text(w, |w, c, _in_class| match c {
+        '"' | '\\' => write!(w, "\\{}", c),
+        c => write!(w, "{}", c),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::dot::{DotOptions, RankDir};
+    use crate::parser::parse;
+    use alloc::string::String;
+    use core::fmt::{self, Write};
+
+    #[test]
+    fn test_dot() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, '"').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let dot = machine.dot();
+        assert!(dot.starts_with("digraph ANFA {"), "Output is a digraph");
+        assert!(
+            dot.contains("rankdir=LR;"),
+            "Layout is left to right by default"
+        );
+        assert!(dot.contains("start -> 0;"), "q0 is marked");
+        assert!(
+            dot.contains("3 [label=\"3\", shape=doublecircle];"),
+            "f is a double circle"
+        );
+        assert!(
+            dot.contains("1 -> 2 [label=\"ε\"];"),
+            "Epsilon edges are labeled"
+        );
+        assert!(
+            dot.contains("2 -> 3 [label=\"\\\"\"];"),
+            "Quotes are escaped"
+        );
+    }
+
+    #[test]
+    fn test_dot_with_options() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        let machine_b = machine.automata_refs[1];
+        ForwardCompiler::union(&mut machine).unwrap();
+        let dot = machine.dot_with_options(DotOptions {
+            rankdir: RankDir::LeftRight,
+            state_ids: false,
+            highlight: Some(machine_b),
+            epsilon_color: Some("gray"),
+        });
+        assert!(dot.contains("rankdir=LR;"), "Layout is left to right");
+        assert!(dot.contains("0 [label=\"\"];"), "States are unlabeled");
+        assert!(
+            dot.contains("2 [label=\"\", style=bold];"),
+            "States of the highlighted machine are bold"
+        );
+        assert!(
+            dot.contains("2 -> 3 [label=\"b\", style=bold];"),
+            "Edges of the highlighted machine are bold"
+        );
+        assert!(
+            dot.contains("0 -> 1 [label=\"a\"];"),
+            "Other machines are not bold"
+        );
+        assert!(
+            dot.contains("4 -> 0 [label=\"ε\", color=\"gray\"];"),
+            "Epsilon edges are colored"
+        );
+    }
+
+    #[test]
+    fn test_write_dot() {
+        let machine = parse("a(b|c)*").unwrap();
+        let mut dot = String::new();
+        machine.write_dot(&mut dot).unwrap();
+        assert_eq!(dot, machine.dot(), "Writing matches the allocating version");
+        let opts = DotOptions {
+            rankdir: RankDir::TopBottom,
+            ..DotOptions::default()
+        };
+        let mut dot = String::new();
+        machine.write_dot_with_options(&mut dot, opts).unwrap();
+        assert_eq!(
+            dot,
+            machine.dot_with_options(opts),
+            "Writing with options matches the allocating version"
+        );
+    }
+
+    #[test]
+    fn test_write_dot_fixed_buffer() {
+        // a sink with a fixed capacity, like an embedded buffer
+        struct Buffer {
+            bytes: [u8; 16],
+            len: usize,
+        }
+        impl Write for Buffer {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let end = self.len + s.len();
+                if end > self.bytes.len() {
+                    return Err(fmt::Error);
+                }
+                self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+                self.len = end;
+                Ok(())
+            }
+        }
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let mut buffer = Buffer {
+            bytes: [0; 16],
+            len: 0,
+        };
+        assert!(
+            machine.write_dot(&mut buffer).is_err(),
+            "A full sink stops the output"
+        );
+        assert_eq!(
+            &buffer.bytes[..buffer.len],
+            b"digraph ANFA {\n",
+            "Output is written until the sink is full"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::parser::is_meta;
+use crate::{Label, ANFA};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use core::fmt;
+
+/// A regular expression accumulated on the edges of a generalized NFA.
+/// The constructors simplify `∅` and `ε` operands away as edges are combined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Expr {
+    Empty,
+    Epsilon,
+    /// A label other than epsilon
+    Label(Label),
+    Concat(Box<Expr>, Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Star(Box<Expr>),
+}
+
+impl Expr {
+    fn concat(a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Empty, _) | (_, Expr::Empty) => Expr::Empty,
+            (Expr::Epsilon, b) => b,
+            (a, Expr::Epsilon) => a,
+            (a, b) => Expr::Concat(Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn union(a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Empty, b) => b,
+            (a, Expr::Empty) => a,
+            (a, b) if a == b => a,
+            (a, b) => Expr::Union(Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn star(a: Expr) -> Expr {
+        match a {
+            Expr::Empty | Expr::Epsilon => Expr::Epsilon,
+            Expr::Star(a) => Expr::Star(a),
+            a => Expr::Star(Box::new(a)),
+        }
+    }
+
+    /// Writes `self`, wrapping it in parentheses when its precedence is below `min`.
+    /// Union is 0, concatenation is 1, and star and atoms are 2.
+    fn write(&self, f: &mut fmt::Formatter<'_>, min: u8) -> fmt::Result {
+        let precedence = match self {
+            Expr::Union(_, _) => 0,
+            Expr::Concat(_, _) => 1,
+            _ => 2,
+        };
+        if precedence < min {
+            f.write_str("(")?;
+            self.write(f, 0)?;
+            return f.write_str(")");
+        }
+        match self {
+            Expr::Empty => f.write_str("[]"),
+            Expr::Epsilon => f.write_str("()"),
+            Expr::Label(label) => label.write_text(f, write_pattern_char),
+            Expr::Concat(a, b) => {
+                a.write(f, 1)?;
+                b.write(f, 1)
+            }
+            Expr::Union(a, b) => {
+                a.write(f, 0)?;
+                f.write_str("|")?;
+                b.write(f, 0)
+            }
+            Expr::Star(a) => {
+                a.write(f, 2)?;
+                f.write_str("*")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl ANFA {
+    /// Returns a regular expression for the language of the top machine.
+    ///
+    /// Implements state elimination: the machine is wrapped in a generalized NFA
+    /// whose edges are labeled with expressions, then each state is removed by
+    /// rerouting every path through it, `p -> q -> r`, into the edge
+    /// `p -> r = (p -> r) | (p -> q)(q -> q)*(q -> r)`. The edge left between
+    /// the new start and final states is the expression.
+    ///
+    /// The result is not minimal. It uses the syntax of `parser::parse`, where
+    /// `()` is epsilon and `[]` accepts nothing. Classes are written as `[a-z]`
+    /// or `[^a-z]`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a(b|c)*").unwrap();
+    /// assert_eq!(machine.to_regex_string(), "a(b|c)*");
+    /// ```
+    pub fn to_regex_string(&self) -> String {
+        let mut expression = String::new();
+        // writing into a String cannot fail
+        let _ = fmt::write(&mut expression, format_args!("{}", self.eliminate()));
+        expression
+    }
+
+    /// Returns the expression of the top machine. See `ANFA::to_regex_string`.
+    pub(crate) fn eliminate(&self) -> Expr {
+        let [machine_q0, machine_f] = match self.automata_refs.last() {
+            None => {
+                return Expr::Empty;
+            }
+            Some(machine) => *machine,
+        };
+        let reachable = self.reachable_from(machine_q0);
+        let start = self.delta.len();
+        let end = start + 1;
+        // edges[p] maps each successor of p to the expression labeling p -> r
+        let mut edges: vec::Vec<BTreeMap<usize, Expr>> = vec![BTreeMap::new(); end + 1];
+        add(&mut edges, start, machine_q0, Expr::Epsilon);
+        add(&mut edges, machine_f, end, Expr::Epsilon);
+        for (q, (label, targets)) in self.delta.iter().enumerate() {
+            if !reachable[q] {
+                continue;
+            }
+            let expr = match label {
+                Label::Epsilon => Expr::Epsilon,
+                label => Expr::Label(label.clone()),
+            };
+            for &r in targets.iter().flatten() {
+                add(&mut edges, q, r, expr.clone());
+            }
+        }
+        for q in 0..start {
+            if !reachable[q] {
+                continue;
+            }
+            let outgoing = core::mem::take(&mut edges[q]);
+            let mut outgoing: vec::Vec<(usize, Expr)> = outgoing.into_iter().collect();
+            let loop_expr = match outgoing.iter().position(|(r, _)| *r == q) {
+                None => Expr::Epsilon,
+                Some(i) => Expr::star(outgoing.swap_remove(i).1),
+            };
+            for p in 0..edges.len() {
+                let incoming = match edges[p].remove(&q) {
+                    None => continue,
+                    Some(incoming) => incoming,
+                };
+                let through = Expr::concat(incoming, loop_expr.clone());
+                for (r, out) in outgoing.iter() {
+                    add(
+                        &mut edges,
+                        p,
+                        *r,
+                        Expr::concat(through.clone(), out.clone()),
+                    );
+                }
+            }
+        }
+        edges[start].remove(&end).unwrap_or(Expr::Empty)
+    }
+}
+
+/// Writes `c` as in a pattern, escaping metacharacters outside a class and the
+/// chars that delimit ranges inside one. See `Label::write_text`.
+pub(crate) fn write_pattern_char<W: fmt::Write>(w: &mut W, c: char, in_class: bool) -> fmt::Result {
+    let escaped = if in_class {
+        matches!(c, '\\' | ']' | '-' | '^')
+    } else {
+        is_meta(c)
+    };
+    if escaped {
+        write!(w, "\\{}", c)
+    } else {
+        write!(w, "{}", c)
+    }
+}
+
+/// Unions `e` into the edge `p -> r`.
+fn add(edges: &mut [BTreeMap<usize, Expr>], p: usize, r: usize, e: Expr) {
+    let union = match edges[p].remove(&r) {
+        None => e,
+        Some(existing) => Expr::union(existing, e),
+    };
+    edges[p].insert(r, union);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::parser::parse;
+    use crate::Label;
+    use alloc::vec;
+
+    #[test]
+    fn test_to_regex_string() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let expression = machine.to_regex_string();
+        assert_eq!(expression, "a|b", "(a|b) is recovered");
+        assert!(
+            parse(&expression).unwrap().equivalent(&machine),
+            "Recovered expression parses back to an equivalent automaton"
+        );
+    }
+
+    #[test]
+    fn test_to_regex_string_round_trip() {
+        for pattern in [
+            "a(b|c)*d",
+            "(a|b)*abb",
+            "a**",
+            "(ab|a)(ba|b)*",
+            ".\\*",
+            "a|",
+            "(^|b)a$",
+        ] {
+            let machine = parse(pattern).unwrap();
+            let expression = machine.to_regex_string();
+            assert!(
+                parse(&expression).unwrap().equivalent(&machine),
+                "{} round trips through {}",
+                pattern,
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_regex_string_trivial() {
+        assert_eq!(
+            ForwardCompiler::from_expr_0().unwrap().to_regex_string(),
+            "[]",
+            "Nothing is the empty class"
+        );
+        assert_eq!(
+            ForwardCompiler::from_expr_1().unwrap().to_regex_string(),
+            "()",
+            "Epsilon is the empty group"
+        );
+    }
+
+    #[test]
+    fn test_to_regex_string_class() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        machine.delta[0].0 = Label::Class(vec![('a', 'z'), ('-', '-')], true);
+        assert_eq!(
+            machine.to_regex_string(),
+            "[^a-z\\-]",
+            "Classes are written in brackets"
+        );
+    }
+}
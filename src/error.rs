@@ -0,0 +1,103 @@
+use crate::QId;
+use core::fmt;
+
+/// An automaton could not be built or parsed.
+///
+/// Every fallible operation of the compilers, the stack operators, and the
+/// parser returns this error. With the `std` feature it implements
+/// `std::error::Error`, so it can be propagated into a `Box<dyn Error>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompileError {
+    /// The state is not in `delta`
+    StateOutOfBounds(QId),
+    /// An operation was applied to invalid operands or input, e.g. a union of one
+    /// machine or an unbalanced parenthesis. The message describes the problem.
+    Invalid(&'static str),
+    /// A repetition would push more copies of its operand than the limit allows
+    ExpansionTooLarge {
+        /// Copies the repetition needs
+        requested: usize,
+        /// Most copies allowed
+        limit: usize,
+    },
+    /// Building finished with other than one machine on the stack, e.g. after a
+    /// forgotten concatenation. See `ANFA::assert_single_machine`.
+    UnbalancedStack {
+        /// Machines left on the stack
+        remaining: usize,
+    },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::StateOutOfBounds(q) => write!(f, "State {} is out of bounds.", q),
+            CompileError::Invalid(message) => f.write_str(message),
+            CompileError::ExpansionTooLarge { requested, limit } => write!(
+                f,
+                "Repetition of {} copies exceeds the limit of {}.",
+                requested, limit
+            ),
+            CompileError::UnbalancedStack { remaining } => {
+                write!(f, "Expected one machine, {} remain.", remaining)
+            }
+        }
+    }
+}
+
+impl From<&'static str> for CompileError {
+    fn from(message: &'static str) -> CompileError {
+        CompileError::Invalid(message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompileError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::error::CompileError;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_display() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let error = ForwardCompiler::concatenate(&mut machine).unwrap_err();
+        assert_eq!(
+            error,
+            CompileError::Invalid("Concatenation requires two operands."),
+            "Operand errors keep their message"
+        );
+        assert_eq!(
+            error.to_string(),
+            "Concatenation requires two operands.",
+            "Display writes the message"
+        );
+        assert_eq!(
+            CompileError::from("Unbalanced parenthesis."),
+            CompileError::Invalid("Unbalanced parenthesis."),
+            "Messages convert into errors"
+        );
+        assert_eq!(
+            CompileError::UnbalancedStack { remaining: 2 }.to_string(),
+            "Expected one machine, 2 remain.",
+            "Display counts the machines left"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_std_error() {
+        fn concatenate_one() -> Result<(), std::boxed::Box<dyn std::error::Error>> {
+            let mut machine = ForwardCompiler::from_expr_a('a')?;
+            ForwardCompiler::concatenate(&mut machine)?;
+            Ok(())
+        }
+        assert_eq!(
+            concatenate_one().unwrap_err().to_string(),
+            "Concatenation requires two operands.",
+            "Errors propagate into a boxed std error"
+        );
+    }
+}
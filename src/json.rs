@@ -0,0 +1,639 @@
+use crate::error::CompileError;
+use crate::{transition, AnchorKind, Label, QId, Transition, ANFA};
+use alloc::string::String;
+use alloc::vec;
+use core::fmt::Write;
+use core::iter::Peekable;
+use core::str::Chars;
+
+impl ANFA {
+    /// Returns the ANFA as JSON, without any dependency on serde.
+    ///
+    /// ```text
+    /// {"delta": [[label, [t0, t1]], ...], "automata_refs": [[q0, f], ...]}
+    /// ```
+    ///
+    /// Absent targets are `null`. A label is one of:
+    ///
+    /// ```text
+    /// null                                  epsilon
+    /// "a"                                   the char a
+    /// {"any": true}                         any char, i.e. .
+    /// {"anchor": "start"}                   ^, or "end" for $
+    /// {"class": [["a", "z"]], "negated": false}
+    /// ```
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(
+    ///     machine.to_json(),
+    ///     r#"{"delta": [["a", [1, null]], [null, [null, null]]], "automata_refs": [[0, 1]]}"#
+    /// );
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"delta\": [");
+        for (q, (label, targets)) in self.delta.iter().enumerate() {
+            if q > 0 {
+                json.push_str(", ");
+            }
+            json.push('[');
+            write_label(&mut json, label);
+            json.push_str(", [");
+            for (i, target) in targets.iter().enumerate() {
+                if i > 0 {
+                    json.push_str(", ");
+                }
+                // writing into a String cannot fail
+                let _ = match target {
+                    Some(target) => write!(json, "{}", target),
+                    None => write!(json, "null"),
+                };
+            }
+            json.push_str("]]");
+        }
+        json.push_str("], \"automata_refs\": [");
+        for (i, [q0, f]) in self.automata_refs.iter().enumerate() {
+            if i > 0 {
+                json.push_str(", ");
+            }
+            let _ = write!(json, "[{}, {}]", q0, f);
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// Reads an ANFA written by `ANFA::to_json`.
+    ///
+    /// Keys may appear in any order and whitespace is ignored. Fails when the
+    /// JSON is malformed, a label or target is not in the format of
+    /// `ANFA::to_json`, or a target or machine refers to a missing state.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// use regexxx::ANFA;
+    /// let machine = parse("a|b").unwrap();
+    /// let copy = ANFA::from_json(&machine.to_json()).unwrap();
+    /// assert_eq!(copy.delta, machine.delta);
+    /// ```
+    pub fn from_json(json: &str) -> Result<ANFA, CompileError> {
+        let mut reader = Reader {
+            chars: json.chars().peekable(),
+        };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        if reader.chars.next().is_some() {
+            return Err(CompileError::Invalid("Trailing characters after JSON."));
+        }
+        let mut delta = None;
+        let mut automata_refs = None;
+        for (key, value) in value.into_object()? {
+            match key.as_str() {
+                "delta" => delta = Some(value),
+                "automata_refs" => automata_refs = Some(value),
+                _ => return Err(CompileError::Invalid("Unknown JSON key.")),
+            }
+        }
+        let (delta, automata_refs) = match (delta, automata_refs) {
+            (Some(delta), Some(automata_refs)) => (delta, automata_refs),
+            _ => {
+                return Err(CompileError::Invalid(
+                    "JSON requires delta and automata_refs.",
+                ));
+            }
+        };
+        let mut anfa = ANFA::new();
+        for transition in delta.into_array()? {
+            anfa.delta.push(read_transition(transition)?);
+        }
+        for machine in automata_refs.into_array()? {
+            let [q0, f] = read_pair(machine)?;
+            match (q0, f) {
+                (Some(q0), Some(f)) => anfa.mark_machine(q0, f)?,
+                _ => return Err(CompileError::Invalid("Machines require q0 and f.")),
+            }
+        }
+        for (_, targets) in anfa.delta.iter() {
+            let targets: vec::Vec<QId> = targets.iter().flatten().copied().collect();
+            anfa.check_states(&targets)?;
+        }
+        Ok(anfa)
+    }
+}
+
+/// The top machine as the formal 5-tuple `FA = (Q, Σ, δ, q0, F)`. See
+/// `ANFA::to_formal_tuple`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormalTuple {
+    /// Q, every state of the ANFA
+    pub states: vec::Vec<QId>,
+    /// Σ, every char of a transition, sorted
+    pub sigma: vec::Vec<char>,
+    /// δ as `(from, symbol, to)` triples, where `None` is ε
+    pub delta: vec::Vec<(QId, Option<char>, QId)>,
+    /// q0, the start state
+    pub q0: QId,
+    /// F, the final states
+    pub finals: vec::Vec<QId>,
+}
+
+impl ANFA {
+    /// Returns the top machine as the formal 5-tuple `FA = (Q, Σ, δ, q0, F)`,
+    /// for importing into academic tools.
+    ///
+    /// Q is `0..delta.len()`, and a union contributes one triple per target. Fails
+    /// when there is no machine, or when a label is not a char or ε, because `.`,
+    /// classes, and anchors have no symbol.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let tuple = ForwardCompiler::from_expr_a('a').unwrap().to_formal_tuple().unwrap();
+    /// assert_eq!(tuple.delta, [(0, Some('a'), 1)]);
+    /// ```
+    pub fn to_formal_tuple(&self) -> Result<FormalTuple, CompileError> {
+        let [q0, f] = self
+            .top_machine()
+            .ok_or(CompileError::Invalid("Exporting requires one machine."))?;
+        let mut sigma = vec::Vec::new();
+        let mut delta = vec::Vec::new();
+        for (q, transition) in self.delta.iter().enumerate() {
+            let symbol = match transition.0 {
+                Label::Epsilon => None,
+                Label::Char(c) => Some(c),
+                _ => {
+                    return Err(CompileError::Invalid(
+                        "Only char and epsilon labels have a symbol.",
+                    ))
+                }
+            };
+            sigma.extend(symbol);
+            delta.extend(transition::targets(transition).map(|target| (q, symbol, target)));
+        }
+        sigma.sort_unstable();
+        sigma.dedup();
+        Ok(FormalTuple {
+            states: (0..self.delta.len()).collect(),
+            sigma,
+            delta,
+            q0,
+            finals: vec![f],
+        })
+    }
+}
+
+impl FormalTuple {
+    /// Returns the tuple as JSON in the shape JFLAP-like tools import, where ε is
+    /// the empty string.
+    ///
+    /// ```text
+    /// {"states": [0, 1], "sigma": ["a"], "delta": [[0, "a", 1]], "q0": 0, "F": [1]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let list = |json: &mut String, items: &[QId]| {
+            json.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    json.push_str(", ");
+                }
+                // writing into a String cannot fail
+                let _ = write!(json, "{}", item);
+            }
+            json.push(']');
+        };
+        let mut json = String::from("{\"states\": ");
+        list(&mut json, &self.states);
+        json.push_str(", \"sigma\": [");
+        for (i, &c) in self.sigma.iter().enumerate() {
+            if i > 0 {
+                json.push_str(", ");
+            }
+            write_char(&mut json, c);
+        }
+        json.push_str("], \"delta\": [");
+        for (i, &(from, symbol, to)) in self.delta.iter().enumerate() {
+            if i > 0 {
+                json.push_str(", ");
+            }
+            let _ = write!(json, "[{}, ", from);
+            match symbol {
+                Some(c) => write_char(&mut json, c),
+                None => json.push_str("\"\""),
+            }
+            let _ = write!(json, ", {}]", to);
+        }
+        let _ = write!(json, "], \"q0\": {}, \"F\": ", self.q0);
+        list(&mut json, &self.finals);
+        json.push('}');
+        json
+    }
+}
+
+/// Writes `label` in the format of `ANFA::to_json`.
+fn write_label(json: &mut String, label: &Label) {
+    match label {
+        Label::Epsilon => json.push_str("null"),
+        Label::Char(c) => write_char(json, *c),
+        Label::Any => json.push_str("{\"any\": true}"),
+        Label::Anchor(AnchorKind::Start) => json.push_str("{\"anchor\": \"start\"}"),
+        Label::Anchor(AnchorKind::End) => json.push_str("{\"anchor\": \"end\"}"),
+        Label::Class(ranges, negated) => {
+            json.push_str("{\"class\": [");
+            for (i, &(lo, hi)) in ranges.iter().enumerate() {
+                if i > 0 {
+                    json.push_str(", ");
+                }
+                json.push('[');
+                write_char(json, lo);
+                json.push_str(", ");
+                write_char(json, hi);
+                json.push(']');
+            }
+            let _ = write!(json, "], \"negated\": {}}}", negated);
+        }
+    }
+}
+
+/// Writes `c` as a JSON string, escaping quotes, backslashes, and control chars.
+fn write_char(json: &mut String, c: char) {
+    json.push('"');
+    match c {
+        '"' => json.push_str("\\\""),
+        '\\' => json.push_str("\\\\"),
+        c if c.is_control() && (c as u32) < 0x10000 => {
+            let _ = write!(json, "\\u{:04x}", c as u32);
+        }
+        c => json.push(c),
+    }
+    json.push('"');
+}
+
+fn read_transition(value: Value) -> Result<Transition, CompileError> {
+    let mut parts = value.into_array()?.into_iter();
+    let (label, targets) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(label), Some(targets), None) => (label, targets),
+        _ => {
+            return Err(CompileError::Invalid(
+                "Transitions are a label and targets.",
+            ));
+        }
+    };
+    let targets = read_pair(targets)?;
+    if targets[0].is_none() && targets[1].is_some() {
+        return Err(CompileError::Invalid(
+            "A second target requires a first target.",
+        ));
+    }
+    Ok((read_label(label)?, targets))
+}
+
+fn read_label(value: Value) -> Result<Label, CompileError> {
+    match value {
+        Value::Null => Ok(Label::Epsilon),
+        Value::String(s) => read_char(s).map(Label::Char),
+        Value::Object(fields) => {
+            let mut class = None;
+            let mut negated = false;
+            let mut label = None;
+            for (key, value) in fields {
+                match (key.as_str(), value) {
+                    ("any", Value::Bool(true)) => label = Some(Label::Any),
+                    ("anchor", Value::String(kind)) if kind == "start" => {
+                        label = Some(Label::Anchor(AnchorKind::Start))
+                    }
+                    ("anchor", Value::String(kind)) if kind == "end" => {
+                        label = Some(Label::Anchor(AnchorKind::End))
+                    }
+                    ("class", ranges) => class = Some(ranges),
+                    ("negated", Value::Bool(b)) => negated = b,
+                    _ => return Err(CompileError::Invalid("Unknown JSON label.")),
+                }
+            }
+            match (label, class) {
+                (Some(label), None) => Ok(label),
+                (None, Some(class)) => {
+                    let mut ranges = vec::Vec::new();
+                    for range in class.into_array()? {
+                        let mut bounds = range.into_array()?.into_iter();
+                        match (bounds.next(), bounds.next(), bounds.next()) {
+                            (Some(Value::String(lo)), Some(Value::String(hi)), None) => {
+                                ranges.push((read_char(lo)?, read_char(hi)?));
+                            }
+                            _ => return Err(CompileError::Invalid("Ranges are two chars.")),
+                        }
+                    }
+                    Ok(Label::Class(ranges, negated))
+                }
+                _ => Err(CompileError::Invalid("Unknown JSON label.")),
+            }
+        }
+        _ => Err(CompileError::Invalid("Unknown JSON label.")),
+    }
+}
+
+fn read_char(s: String) -> Result<char, CompileError> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(CompileError::Invalid("Char labels are one char.")),
+    }
+}
+
+/// Reads `[a, b]` where each is a state or `null`.
+fn read_pair(value: Value) -> Result<[Option<QId>; 2], CompileError> {
+    let mut pair = [None, None];
+    let mut items = value.into_array()?.into_iter();
+    for slot in pair.iter_mut() {
+        *slot = match items.next() {
+            Some(Value::Null) => None,
+            Some(Value::Number(q)) => Some(q),
+            _ => return Err(CompileError::Invalid("Expected a state or null.")),
+        };
+    }
+    match items.next() {
+        None => Ok(pair),
+        Some(_) => Err(CompileError::Invalid("Expected a state or null.")),
+    }
+}
+
+/// The subset of JSON `ANFA::to_json` writes. Numbers are non-negative integers.
+enum Value {
+    Null,
+    Bool(bool),
+    Number(usize),
+    String(String),
+    Array(vec::Vec<Value>),
+    Object(vec::Vec<(String, Value)>),
+}
+
+impl Value {
+    fn into_array(self) -> Result<vec::Vec<Value>, CompileError> {
+        match self {
+            Value::Array(items) => Ok(items),
+            _ => Err(CompileError::Invalid("Expected a JSON array.")),
+        }
+    }
+
+    fn into_object(self) -> Result<vec::Vec<(String, Value)>, CompileError> {
+        match self {
+            Value::Object(fields) => Ok(fields),
+            _ => Err(CompileError::Invalid("Expected a JSON object.")),
+        }
+    }
+}
+
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Reader<'a> {
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.chars.next();
+        }
+    }
+
+    /// Consumes `c` after any whitespace
+    fn expect(&mut self, c: char) -> Result<(), CompileError> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some(next) if next == c => Ok(()),
+            _ => Err(CompileError::Invalid("Malformed JSON.")),
+        }
+    }
+
+    /// Consumes `word` after its first char
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, CompileError> {
+        for c in word.chars().skip(1) {
+            if self.chars.next() != Some(c) {
+                return Err(CompileError::Invalid("Malformed JSON."));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, CompileError> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some('n') => self.keyword("null", Value::Null),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('"') => self.string().map(Value::String),
+            Some('[') => {
+                let mut items = vec::Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&']') {
+                    self.chars.next();
+                    return Ok(Value::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some(']') => return Ok(Value::Array(items)),
+                        _ => return Err(CompileError::Invalid("Malformed JSON.")),
+                    }
+                }
+            }
+            Some('{') => {
+                let mut fields = vec::Vec::new();
+                self.skip_whitespace();
+                if self.chars.peek() == Some(&'}') {
+                    self.chars.next();
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    self.expect('"')?;
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    fields.push((key, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some(',') => continue,
+                        Some('}') => return Ok(Value::Object(fields)),
+                        _ => return Err(CompileError::Invalid("Malformed JSON.")),
+                    }
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut n = c as usize - '0' as usize;
+                while let Some(d) = self.chars.peek().and_then(|c| c.to_digit(10)) {
+                    self.chars.next();
+                    n = n
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(d as usize))
+                        .ok_or(CompileError::Invalid("JSON number is too large."))?;
+                }
+                Ok(Value::Number(n))
+            }
+            _ => Err(CompileError::Invalid("Malformed JSON.")),
+        }
+    }
+
+    /// Reads the rest of a string after its opening quote
+    fn string(&mut self) -> Result<String, CompileError> {
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(CompileError::Invalid("Unterminated JSON string.")),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let high = self.hex()?;
+                            let code = if (0xd800..0xdc00).contains(&high) {
+                                self.expect('\\')?;
+                                self.expect('u')?;
+                                let low = self.hex()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(CompileError::Invalid("Invalid JSON escape."));
+                                }
+                                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                            } else {
+                                high
+                            };
+                            char::from_u32(code)
+                                .ok_or(CompileError::Invalid("Invalid JSON escape."))?
+                        }
+                        _ => return Err(CompileError::Invalid("Invalid JSON escape.")),
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Reads the four hex digits of a `\u` escape
+    fn hex(&mut self) -> Result<u32, CompileError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or(CompileError::Invalid("Invalid JSON escape."))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::parser::parse;
+    use crate::{AnchorKind, Label, ANFA};
+    use alloc::vec;
+
+    #[test]
+    fn test_json_round_trip() {
+        let machine = parse("a(b|c)*d").unwrap();
+        let copy = ANFA::from_json(&machine.to_json()).unwrap();
+        assert_eq!(copy.delta, machine.delta, "Every transition is restored");
+        assert_eq!(
+            copy.automata_refs, machine.automata_refs,
+            "Every machine is restored"
+        );
+        assert!(copy.is_match("abcbd"), "The copy accepts abcbd");
+    }
+
+    #[test]
+    fn test_json_labels() {
+        let mut machine = parse("^.$").unwrap();
+        machine.delta.push((Label::Char('\n'), [None, None]));
+        let class = Label::Class(vec![('a', 'z'), ('"', '"')], true);
+        machine.delta.push((class, [Some(0), None]));
+        let json = machine.to_json();
+        assert!(json.contains(r#""\u000a""#), "Control chars are escaped");
+        assert!(
+            json.contains(r#"{"class": [["a", "z"], ["\"", "\""]], "negated": true}"#),
+            "Classes list their ranges"
+        );
+        let copy = ANFA::from_json(&json).unwrap();
+        assert_eq!(copy.delta, machine.delta, "Every label is restored");
+        assert!(
+            copy.delta
+                .iter()
+                .any(|(label, _)| *label == Label::Anchor(AnchorKind::End)),
+            "Anchors are restored"
+        );
+    }
+
+    #[test]
+    fn test_json_errors() {
+        for (json, reason) in [
+            ("", "Empty input"),
+            (r#"{"delta": []}"#, "Missing automata_refs"),
+            (
+                r#"{"delta": [[null, [1, null]]], "automata_refs": [[0, 0]]}"#,
+                "Target out of bounds",
+            ),
+            (
+                r#"{"delta": [[null, [null, null]]], "automata_refs": [[0, 1]]}"#,
+                "Machine out of bounds",
+            ),
+            (
+                r#"{"delta": [["ab", [null, null]]], "automata_refs": []}"#,
+                "Char label longer than one char",
+            ),
+            (
+                r#"{"delta": [[null, [null, 0]]], "automata_refs": []}"#,
+                "Second target without a first",
+            ),
+            (r#"{"delta": [], "automata_refs": []} x"#, "Trailing input"),
+            (
+                r#"{"delta": [["\udbff\u0000", [null, null]]], "automata_refs": []}"#,
+                "High surrogate without a low surrogate",
+            ),
+        ] {
+            assert!(ANFA::from_json(json).is_err(), "{}", reason);
+        }
+    }
+
+    #[test]
+    fn test_to_formal_tuple() {
+        let tuple = ForwardCompiler::from_expr_a('a')
+            .unwrap()
+            .to_formal_tuple()
+            .unwrap();
+        assert_eq!(tuple.states, [0, 1], "Q is every state");
+        assert_eq!(tuple.sigma, ['a'], "Σ is the literal");
+        assert_eq!(tuple.delta, [(0, Some('a'), 1)], "δ steps along a");
+        assert_eq!(tuple.q0, 0, "q0 is the start of the literal");
+        assert_eq!(tuple.finals, [1], "F is the end of the literal");
+        assert_eq!(
+            tuple.to_json(),
+            r#"{"states": [0, 1], "sigma": ["a"], "delta": [[0, "a", 1]], "q0": 0, "F": [1]}"#,
+            "The JSON lists each component"
+        );
+        let tuple = parse("a|b").unwrap().to_formal_tuple().unwrap();
+        assert_eq!(tuple.sigma, ['a', 'b'], "Σ is sorted");
+        assert!(
+            tuple
+                .delta
+                .iter()
+                .filter(|(_, symbol, _)| symbol.is_none())
+                .count()
+                >= 2,
+            "A union has an ε triple per target"
+        );
+        assert!(
+            tuple.to_json().contains(r#", "", "#),
+            "ε is the empty string"
+        );
+        assert!(
+            parse("a.").unwrap().to_formal_tuple().is_err(),
+            ". has no symbol"
+        );
+        assert!(ANFA::new().to_formal_tuple().is_err(), "No machine fails");
+    }
+}
@@ -1,6 +1,16 @@
+//! Augmented non-deterministic finite automata.
+//!
+//! The crate is `#![no_std]` and only depends on `core` and `alloc`. Building,
+//! simulating, and determinizing automata never uses `std`: sets of states are
+//! `alloc::vec::Vec`s deduplicated with a visited bitset, and keyed lookups use
+//! `alloc::collections::BTreeMap`.
+//!
+//! Enable the `std` feature to implement `std::error::Error` for the error types.
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 // for ease of copy+paste:
 // ε
@@ -8,19 +18,112 @@ extern crate alloc;
 
 // size of QId
 // size of label
+use crate::error::CompileError;
 use alloc::vec;
+use core::fmt;
 
 /// Unique state id
 /// TODO: Compile a regex large enough to overflow
 pub type QId = usize;
 
-/// A transition along an optional label to zero, one, or two States.
-/// When a label is `None`, transition is an epsilon transition
+/// The condition for taking a transition.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Label {
+    /// Always advances without consuming input
+    Epsilon,
+    /// Consumes exactly this char
+    Char(char),
+    /// Consumes any one char, i.e. `.`
+    Any,
+    /// Consumes one char inside any of the inclusive ranges, e.g. `[a-z0-9]`.
+    /// When negated, consumes one char outside every range, e.g. `[^a-z0-9]`.
+    Class(vec::Vec<(char, char)>, bool),
+    /// Advances without consuming input, but only at a position of the input
+    Anchor(AnchorKind),
+}
+
+/// A zero-width assertion about the position of the input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnchorKind {
+    /// The start of the input, i.e. `^`
+    Start,
+    /// The end of the input, i.e. `$`
+    End,
+}
+
+impl Label {
+    /// Returns true when a transition labeled `self` may be taken on input `c`.
+    /// Epsilon and anchors never consume input, so they never match.
+    ///
+    /// ```rust
+    /// use regexxx::Label;
+    /// let digits = Label::Class(vec![('0', '9')], false);
+    /// assert!(digits.matches('7'));
+    /// assert!(!digits.matches('a'));
+    /// assert!(!Label::Any.matches('\n'));
+    /// ```
+    pub fn matches(&self, c: char) -> bool {
+        match self {
+            Label::Epsilon | Label::Anchor(_) => false,
+            Label::Char(l) => *l == c,
+            Label::Any => c != '\n',
+            Label::Class(ranges, negated) => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+            }
+        }
+    }
+
+    /// Writes `self` as written in patterns: `ε`, the char, `.`, `^`, `$`, or a
+    /// class like `[^a-z]`. Every char is written by `escape`, which is told
+    /// whether the char is inside a class, so each format escapes its own
+    /// delimiters. See `ANFA::to_table_string`, `ANFA::dot`, and
+    /// `ANFA::export_transitions_csv`.
+    pub(crate) fn write_text<W: fmt::Write>(
+        &self,
+        w: &mut W,
+        escape: impl Fn(&mut W, char, bool) -> fmt::Result,
+    ) -> fmt::Result {
+        match self {
+            Label::Epsilon => w.write_str("ε"),
+            Label::Char(c) => escape(w, *c, false),
+            Label::Any => w.write_str("."),
+            Label::Anchor(AnchorKind::Start) => w.write_str("^"),
+            Label::Anchor(AnchorKind::End) => w.write_str("$"),
+            Label::Class(ranges, negated) => {
+                w.write_str(if *negated { "[^" } else { "[" })?;
+                for &(lo, hi) in ranges.iter() {
+                    escape(w, lo, true)?;
+                    if lo != hi {
+                        w.write_str("-")?;
+                        escape(w, hi, true)?;
+                    }
+                }
+                w.write_str("]")
+            }
+        }
+    }
+}
+
+/// Writes a label as in state tables, without escaping.
+///
+/// ```rust
+/// use regexxx::Label;
+/// assert_eq!(Label::Class(vec![('a', 'z'), ('_', '_')], true).to_string(), "[^a-z_]");
+/// assert_eq!(Label::Epsilon.to_string(), "ε");
+/// ```
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_text(f, |f, c, _in_class| write!(f, "{}", c))
+    }
+}
+
+/// A transition along a label to zero, one, or two States.
+/// When a label is `Label::Epsilon`, transition is an epsilon transition
 /// and it always advances to its final states. When QId is `None`,
 /// there is no transition. Transition is ordered. If `Transition.1[1]`
 /// is `Some(QId)`, then `Transition.1[0]` must also be `Some(QId)`.
 /// i.e. a union operation is when both `Option<QId>` are `Some(QId)`.
-pub type Transition = (Option<char>, [Option<QId>; 2]);
+pub type Transition = (Label, [Option<QId>; 2]);
 
 /// DeltaFunction is a vector of ordered transitions that satisfy
 /// the function `δ ⊆ State × T × State`. An index of `DeltaFunction`
@@ -30,9 +133,20 @@ pub type DeltaFunction = vec::Vec<Transition>;
 /// The initial and final states of an expression: [q0, f]
 pub type AutomataRef = [QId; 2];
 
+/// An edge from a state to one of its targets, labeled with the char it
+/// consumes if any: (from, to, label). See `ANFA::as_graph_edges`.
+pub type GraphEdge = (QId, QId, Option<char>);
+
+/// A stack of machines sharing one delta function.
+///
+/// This is the only ANFA type. Compilers in `compilers` push and combine machines,
+/// and the inherent methods in `operators`, `transforms`, `matcher`, and `dfa`
+/// act on the same public fields.
 #[derive(Debug)]
 pub struct ANFA {
+    /// Every machine as `[q0, f]`, the last is the top machine
     pub automata_refs: vec::Vec<AutomataRef>,
+    /// Transitions of every state of every machine
     pub delta: DeltaFunction,
 }
 
@@ -41,10 +155,566 @@ impl ANFA {
     /// ANFA must be constructed by a static compiler factory
     fn new() -> ANFA {
         ANFA {
-            automata_refs: vec::Vec::with_capacity(u32::MAX as usize),
-            delta: vec::Vec::with_capacity(u32::MAX as usize),
+            automata_refs: vec::Vec::new(),
+            delta: vec::Vec::new(),
+        }
+    }
+
+    /// Returns the top machine, i.e. the machine operators and simulation act on.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.top_machine(), Some([0, 1]));
+    /// ```
+    pub fn top_machine(&self) -> Option<AutomataRef> {
+        self.automata_refs.last().copied()
+    }
+
+    /// Returns the only machine, or `CompileError::UnbalancedStack` when the stack
+    /// holds any other number of machines, e.g. after a forgotten concatenation.
+    ///
+    /// Call it when building is finished, so an incomplete program fails instead
+    /// of matching only its top sub-expression.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// use regexxx::error::CompileError;
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.assert_single_machine(), Ok([0, 1]));
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// assert_eq!(
+    ///     machine.assert_single_machine(),
+    ///     Err(CompileError::UnbalancedStack { remaining: 2 })
+    /// );
+    /// ```
+    pub fn assert_single_machine(&self) -> Result<AutomataRef, CompileError> {
+        match self.automata_refs.as_slice() {
+            [machine] => Ok(*machine),
+            machines => Err(CompileError::UnbalancedStack {
+                remaining: machines.len(),
+            }),
+        }
+    }
+
+    /// Returns the initial state of the top machine.
+    pub fn q0(&self) -> Option<QId> {
+        self.top_machine().map(|[q0, _f]| q0)
+    }
+
+    /// Returns every machine on the stack as `[q0, f]`, bottom first.
+    pub fn finals(&self) -> &[AutomataRef] {
+        &self.automata_refs
+    }
+
+    /// Returns the number of states in `delta`, shared by every machine.
+    pub fn count_states(&self) -> usize {
+        self.delta.len()
+    }
+
+    /// Returns the number of distinct final states across every machine on the stack.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// assert_eq!(machine.count_states(), 4);
+    /// assert_eq!(machine.count_final_states(), 2);
+    /// ```
+    pub fn count_final_states(&self) -> usize {
+        let mut finals: vec::Vec<QId> = self.automata_refs.iter().map(|[_q0, f]| *f).collect();
+        finals.sort_unstable();
+        finals.dedup();
+        finals.len()
+    }
+
+    /// Returns each distinct accepting state of the top machine, in order: every
+    /// state reachable from `q0` that reaches `f` without consuming input.
+    ///
+    /// The input may end at any of them and be accepted. An ANFA funnels into
+    /// one `f`, which is always yielded, so machines with many accepting states,
+    /// e.g. from `ANFA::from_dfa_table`, also yield the states that fan out to `f`.
+    /// Anchors are assumed to pass.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.iter_finals().collect::<Vec<_>>(), [1]);
+    /// ```
+    pub fn iter_finals(&self) -> impl Iterator<Item = QId> + '_ {
+        let (reachable, accepting) = match self.top_machine() {
+            None => (vec::Vec::new(), vec::Vec::new()),
+            Some([q0, f]) => {
+                let mut predecessors = vec![vec::Vec::new(); self.delta.len()];
+                for (q, (label, targets)) in self.delta.iter().enumerate() {
+                    if matches!(label, Label::Epsilon | Label::Anchor(_)) {
+                        for &target in targets.iter().flatten() {
+                            predecessors[target].push(q);
+                        }
+                    }
+                }
+                let mut accepting = vec![false; self.delta.len()];
+                let mut stack = vec![f];
+                while let Some(q) = stack.pop() {
+                    if !accepting[q] {
+                        accepting[q] = true;
+                        stack.extend(predecessors[q].iter());
+                    }
+                }
+                let mut reachable = self.reachable_from(q0);
+                reachable[f] = true;
+                (reachable, accepting)
+            }
+        };
+        (0..reachable.len()).filter(move |&q| reachable[q] && accepting[q])
+    }
+
+    /// Returns the char `state` consumes, or `None` when its label is not a `Char`
+    /// or `state` is out of bounds.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.label_at(0), Some('a'));
+    /// assert_eq!(machine.label_at(1), None);
+    /// ```
+    pub fn label_at(&self, state: QId) -> Option<char> {
+        match self.delta.get(state) {
+            Some((Label::Char(c), _)) => Some(*c),
+            _ => None,
+        }
+    }
+
+    /// Returns the targets of `state`, or no targets when `state` is out of bounds.
+    pub fn targets_at(&self, state: QId) -> [Option<QId>; 2] {
+        self.delta
+            .get(state)
+            .map_or([None, None], |(_, targets)| *targets)
+    }
+
+    /// Returns how many targets `state` has: 0 for a final state, 1 for a step,
+    /// or 2 for a union. A state out of bounds has no targets.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a|b").unwrap();
+    /// let [q0, f] = machine.top_machine().unwrap();
+    /// assert_eq!(machine.transition_count_for(q0), 2);
+    /// assert_eq!(machine.transition_count_for(f), 0);
+    /// ```
+    pub fn transition_count_for(&self, state: QId) -> usize {
+        self.targets_at(state).iter().flatten().count()
+    }
+
+    /// Returns every state and every edge as `(from, to, label)`, a neutral form
+    /// for graph libraries such as `petgraph`.
+    ///
+    /// A union expands into one edge per target, in order. The label is the char
+    /// of a `Label::Char` and `None` otherwise, so epsilon, `.`, class, and anchor
+    /// edges are unlabeled.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.as_graph_edges(), (vec![0, 1], vec![(0, 1, Some('a'))]));
+    /// ```
+    pub fn as_graph_edges(&self) -> (vec::Vec<QId>, vec::Vec<GraphEdge>) {
+        let nodes = (0..self.delta.len()).collect();
+        let edges = self
+            .delta
+            .iter()
+            .enumerate()
+            .flat_map(|(q, transition)| {
+                let label = match transition.0 {
+                    Label::Char(c) => Some(c),
+                    _ => None,
+                };
+                transition::targets(transition).map(move |target| (q, target, label))
+            })
+            .collect();
+        (nodes, edges)
+    }
+
+    /// Returns an empty ANFA with room for `states` states and `machines` machines.
+    ///
+    /// Like `ANFA::new`, the result is not a valid automaton until an expression
+    /// is pushed, e.g. with `Compiler::expr_a`. Capacity is only a hint; building
+    /// past it reallocates as usual.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// use regexxx::ANFA;
+    /// let mut machine = ANFA::with_capacity(4, 2);
+    /// ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert!(machine.is_match("ab"));
+    /// ```
+    pub fn with_capacity(states: usize, machines: usize) -> ANFA {
+        ANFA {
+            automata_refs: vec::Vec::with_capacity(machines),
+            delta: vec::Vec::with_capacity(states),
         }
     }
 }
 
+pub mod ast;
+pub mod binary;
+pub mod builder;
 pub mod compilers;
+pub mod csv;
+pub mod dfa;
+pub mod dot;
+pub mod elimination;
+pub mod error;
+pub mod json;
+pub mod matcher;
+pub mod operators;
+pub mod parser;
+pub mod paths;
+pub mod postfix;
+pub mod regex;
+pub mod stats;
+pub mod table;
+pub mod transforms;
+pub mod transition;
+
+pub use crate::regex::{compile, Captures, CompiledRegex};
+
+#[cfg(test)]
+mod tests {
+    // Only `core` and `alloc` are in scope, so this module fails to compile
+    // if the matching APIs ever require `std`.
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::dfa::LazyDfa;
+    use crate::error::CompileError;
+    use crate::matcher::Matcher;
+    use crate::parser::parse;
+    use crate::{Label, QId, ANFA};
+    use alloc::collections::BTreeMap;
+    use alloc::vec;
+
+    #[test]
+    fn test_no_std_matching() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert!(machine.is_match("aaa"), "is_match without std");
+        assert_eq!(machine.find("baa"), Some((0, 0)), "find without std");
+        assert_eq!(
+            machine.debug_trace("b"),
+            vec![vec![0, 2, 3, 4], vec![]],
+            "debug_trace without std"
+        );
+        assert!(
+            Matcher::default().is_match(&machine, ""),
+            "Matcher without std"
+        );
+        assert!(machine.to_dfa().is_match("aa"), "DFA without std");
+        assert!(
+            LazyDfa::from_anfa(&machine).is_match("a"),
+            "LazyDfa without std"
+        );
+    }
+
+    #[test]
+    fn test_label_matches() {
+        assert!(!Label::Epsilon.matches('a'), "Epsilon consumes nothing");
+        assert!(Label::Char('a').matches('a'), "Char matches itself");
+        assert!(!Label::Char('a').matches('b'), "Char matches only itself");
+        assert!(Label::Any.matches('😀'), "Any matches any char");
+        assert!(!Label::Any.matches('\n'), "Any excludes a newline");
+        let class = Label::Class(vec![('a', 'c'), ('x', 'x')], false);
+        for c in ['a', 'b', 'c', 'x'] {
+            assert!(class.matches(c), "Class matches chars in its ranges");
+        }
+        assert!(
+            !class.matches('d'),
+            "Class rejects chars outside its ranges"
+        );
+        let negated = Label::Class(vec![('a', 'c')], true);
+        assert!(!negated.matches('b'), "Negated class rejects its ranges");
+        assert!(
+            negated.matches('\n'),
+            "Negated class matches everything else"
+        );
+        assert!(
+            !Label::Class(vec![], false).matches('a'),
+            "Empty class matches nothing"
+        );
+    }
+
+    #[test]
+    fn test_compiler_outputs_simulate() {
+        // every compiler operation still emits `Label::Epsilon` and `Label::Char`
+        for (pattern, accepted, rejected) in [
+            ("[]", vec![], vec!["", "a"]),
+            ("()", vec![""], vec!["a"]),
+            ("a", vec!["a"], vec!["", "aa"]),
+            ("ab", vec!["ab"], vec!["a", "ba"]),
+            ("a*", vec!["", "a", "aaa"], vec!["b"]),
+            ("a|b", vec!["a", "b"], vec!["", "ab"]),
+            ("a(b|c)*d", vec!["ad", "abcd"], vec!["abc"]),
+        ] {
+            let machine = parse(pattern).unwrap();
+            for (q, (label, _)) in machine.delta.iter().enumerate() {
+                assert!(
+                    matches!(label, Label::Epsilon | Label::Char(_)),
+                    "{} only labels state {} with epsilon or a char",
+                    pattern,
+                    q
+                );
+            }
+            for input in accepted {
+                assert!(machine.is_match(input), "{} accepts {:?}", pattern, input);
+            }
+            for input in rejected {
+                assert!(!machine.is_match(input), "{} rejects {:?}", pattern, input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_class_simulates() {
+        // RE x[0-9][^0-9]
+        let mut machine = ANFA::new();
+        machine.delta = vec![
+            (Label::Char('x'), [Some(1), None]),
+            (Label::Class(vec![('0', '9')], false), [Some(2), None]),
+            (Label::Class(vec![('0', '9')], true), [Some(3), None]),
+            (Label::Epsilon, [None, None]),
+        ];
+        machine.automata_refs.push([0, 3]);
+        let dfa = machine.to_dfa();
+        for (input, expected) in [
+            ("x1a", true),
+            ("x9😀", true),
+            ("x12", false),
+            ("xa1", false),
+        ] {
+            assert_eq!(machine.is_match(input), expected, "ANFA on {:?}", input);
+            assert_eq!(dfa.is_match(input), expected, "DFA on {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut machine = ANFA::with_capacity(2000, 2);
+        let capacity = (machine.delta.capacity(), machine.automata_refs.capacity());
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        for _ in 1..1000 {
+            ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+            ForwardCompiler::concatenate(&mut machine).unwrap();
+        }
+        assert_eq!(machine.delta.len(), 2000, "1000 literals push 2000 states");
+        assert_eq!(
+            (machine.delta.capacity(), machine.automata_refs.capacity()),
+            capacity,
+            "Building within the capacity hint does not reallocate"
+        );
+        assert!(
+            machine.is_match(&"a".repeat(1000)),
+            "Concatenation accepts 1000 'a's"
+        );
+    }
+
+    /// Returns true when concatenating the top two machines of `anfa` starts in
+    /// the first machine's `q0` and ends in the second machine's `f`.
+    fn concatenation_preserves_q0(mut anfa: ANFA) -> bool {
+        let [machine_a, machine_b] = match anfa.finals() {
+            [.., machine_a, machine_b] => [*machine_a, *machine_b],
+            _ => return false,
+        };
+        ForwardCompiler::concatenate(&mut anfa).is_ok()
+            && anfa.q0() == Some(machine_a[0])
+            && anfa.top_machine() == Some([machine_a[0], machine_b[1]])
+    }
+
+    #[test]
+    fn test_top_machine() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        assert_eq!(
+            machine.finals(),
+            [[2, 4], [5, 6]],
+            "Two machines are stacked"
+        );
+        assert_eq!(machine.q0(), Some(5), "q0 is the start of the top machine");
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(
+            machine.top_machine(),
+            Some([2, 6]),
+            "Concatenation runs from the first q0 to the second f"
+        );
+        assert_eq!(
+            machine.finals().len(),
+            1,
+            "Concatenation leaves one machine"
+        );
+        for operands in ["ab", "a*b", "(a|b)c*", "()a", "a()"] {
+            let mut machine = parse(operands).unwrap();
+            machine.duplicate_top().unwrap();
+            assert!(
+                concatenation_preserves_q0(machine),
+                "Concatenating {} with itself preserves q0",
+                operands
+            );
+        }
+        let mut machine = ANFA::new();
+        assert_eq!(machine.top_machine(), None, "An empty ANFA has no machine");
+        assert_eq!(machine.q0(), None, "An empty ANFA has no q0");
+        ForwardCompiler::expr_1(&mut machine).unwrap();
+        assert!(
+            !concatenation_preserves_q0(machine),
+            "Concatenation requires two machines"
+        );
+    }
+
+    #[test]
+    fn test_canonical_anfa() {
+        // compiler-driven and inherent APIs build the same crate::ANFA
+        let mut machine: ANFA = ANFA::with_capacity(16, 4);
+        for c in ['a', 'b', 'c'] {
+            ForwardCompiler::expr_a(&mut machine, c).unwrap();
+        }
+        machine.union_all_balanced(3).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'd').unwrap();
+        machine.concat_into_existing().unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(machine.finals().len(), 1, "One machine is left");
+        assert!(
+            machine.equivalent(&parse("((a|b|c)d)*").unwrap()),
+            "Both APIs compose"
+        );
+        machine.remove_epsilons().unwrap();
+        assert!(machine.is_match("adcd"), "Transforms act on the same ANFA");
+    }
+
+    #[test]
+    fn test_label_and_targets_at() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(machine.label_at(0), Some('a'), "q0 consumes 'a'");
+        assert_eq!(machine.targets_at(0), [Some(1), None], "q0 advances to f");
+        assert_eq!(machine.label_at(1), None, "f is not labeled");
+        assert_eq!(machine.targets_at(1), [None, None], "f does not advance");
+        assert_eq!(machine.label_at(2), None, "Out of bounds is not labeled");
+        assert_eq!(
+            machine.targets_at(2),
+            [None, None],
+            "Out of bounds does not advance"
+        );
+    }
+
+    #[test]
+    fn test_transition_count_for() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(machine.transition_count_for(0), 1, "q0 of a literal steps");
+        assert_eq!(
+            machine.transition_count_for(1),
+            0,
+            "f of a literal is final"
+        );
+        assert_eq!(
+            machine.transition_count_for(9),
+            0,
+            "Out of bounds has no targets"
+        );
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let [q0, _f] = machine.top_machine().unwrap();
+        assert_eq!(
+            machine.transition_count_for(q0),
+            2,
+            "q0 of a union has two targets"
+        );
+    }
+
+    #[test]
+    fn test_iter_finals() {
+        // RE a|ab, with states 0, 1, and 2 accepting 'a' and 'ab'
+        let transitions = [
+            BTreeMap::from([('a', 1)]),
+            BTreeMap::from([('b', 2)]),
+            BTreeMap::new(),
+        ];
+        let machine = ANFA::from_dfa_table(&transitions, &[false, true, true], 0).unwrap();
+        let finals: vec::Vec<QId> = machine.iter_finals().collect();
+        let [_q0, f] = machine.top_machine().unwrap();
+        assert_eq!(finals, [1, 2, f], "Each accepting DFA state and f");
+        assert_eq!(
+            ANFA::new().iter_finals().count(),
+            0,
+            "No machine has no finals"
+        );
+    }
+
+    #[test]
+    fn test_assert_single_machine() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(
+            machine.assert_single_machine(),
+            Ok([0, 1]),
+            "One machine returns its ref"
+        );
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        assert_eq!(
+            machine.assert_single_machine(),
+            Err(CompileError::UnbalancedStack { remaining: 2 }),
+            "Two machines are unbalanced"
+        );
+        assert_eq!(
+            ANFA::new().assert_single_machine(),
+            Err(CompileError::UnbalancedStack { remaining: 0 }),
+            "No machine is unbalanced"
+        );
+    }
+
+    #[test]
+    fn test_count_states() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(machine.count_states(), 2, "A literal has two states");
+        assert_eq!(
+            machine.count_final_states(),
+            1,
+            "A literal has one final state"
+        );
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert_eq!(machine.count_states(), 6, "Union pushes two states");
+        assert_eq!(
+            machine.count_final_states(),
+            1,
+            "Union leaves one final state"
+        );
+        machine.automata_refs.push(machine.automata_refs[0]);
+        assert_eq!(
+            machine.count_final_states(),
+            1,
+            "Shared final states are counted once"
+        );
+    }
+
+    #[test]
+    fn test_as_graph_edges() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let (nodes, edges) = machine.as_graph_edges();
+        assert_eq!(nodes, [0, 1], "Every state is a node");
+        assert_eq!(
+            edges
+                .iter()
+                .filter(|(_, _, label)| *label == Some('a'))
+                .count(),
+            1,
+            "A literal has exactly one edge labeled a"
+        );
+        let machine = parse("a|b").unwrap();
+        let (_, edges) = machine.as_graph_edges();
+        let [q0, _f] = machine.automata_refs[0];
+        let unions: vec::Vec<_> = edges.iter().filter(|(from, _, _)| *from == q0).collect();
+        assert_eq!(unions.len(), 2, "A union expands into two edges");
+        assert!(
+            unions.iter().all(|(_, _, label)| label.is_none()),
+            "Epsilon edges are unlabeled"
+        );
+    }
+}
@@ -0,0 +1,1525 @@
+use crate::{transition, AnchorKind, Label, QId, ANFA};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use core::fmt::{self, Write};
+
+/// Configures how an ANFA is simulated against input.
+///
+/// The default matcher compares labels exactly.
+///
+/// ```rust
+/// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+/// use regexxx::matcher::Matcher;
+/// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+/// let matcher = Matcher {
+///     case_insensitive: true,
+///     ..Matcher::default()
+/// };
+/// assert!(matcher.is_match(&machine, "A"));
+/// assert!(!Matcher::default().is_match(&machine, "A"));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Matcher {
+    /// Compare labels with `char::eq_ignore_ascii_case`. Non-ASCII labels are still compared exactly.
+    /// A class contains the input when it contains either ASCII case of the input.
+    pub case_insensitive: bool,
+    /// Stop following epsilon transitions after this many consecutive hops.
+    /// States further away are treated as unreachable, so a limit can reject
+    /// input the automaton otherwise accepts. `None` follows every chain.
+    pub max_epsilon_depth: Option<usize>,
+    /// Let `Label::Any` match `'\n'`. By default `.` matches any char except a newline.
+    pub dot_matches_newline: bool,
+}
+
+impl Matcher {
+    /// Returns true when the top machine accepts the whole input.
+    pub fn is_match(&self, anfa: &ANFA, input: &str) -> bool {
+        self.is_match_chars(anfa, input.chars())
+    }
+
+    /// Returns true when the top machine accepts every char of `chars`, consumed
+    /// one at a time, so the input never needs to be in memory at once.
+    ///
+    /// Iteration stops early once no state is active.
+    pub fn is_match_chars<I: IntoIterator<Item = char>>(&self, anfa: &ANFA, chars: I) -> bool {
+        let [q0, f] = match anfa.automata_refs.last() {
+            None => {
+                return false;
+            }
+            Some(machine) => *machine,
+        };
+        let mut active = self.anchored_closure(anfa, &[q0], true, false);
+        let mut empty = true;
+        for c in chars {
+            if active.is_empty() {
+                return false;
+            }
+            active = self.step(anfa, &active, c);
+            empty = false;
+        }
+        self.end_closure(anfa, active, empty).contains(&f)
+    }
+
+    /// Returns whether the top machine accepts each whole input, in the order of
+    /// `inputs`. See `Matcher::is_match`.
+    ///
+    /// The visited bitset and the active state buffers are allocated once and
+    /// reused for every input, so checking many inputs against one machine does
+    /// not allocate per input.
+    pub fn match_all_anchored(&self, anfa: &ANFA, inputs: &[&str]) -> vec::Vec<bool> {
+        let [q0, f] = match anfa.automata_refs.last() {
+            None => {
+                return vec![false; inputs.len()];
+            }
+            Some(machine) => *machine,
+        };
+        let mut visited = vec![false; anfa.delta.len()];
+        let mut active = vec::Vec::new();
+        let mut next = vec::Vec::new();
+        inputs
+            .iter()
+            .map(|input| {
+                active.clear();
+                active.push(q0);
+                visited[q0] = true;
+                self.close_in_place(anfa, &mut visited, &mut active, true, false);
+                let mut empty = true;
+                for c in input.chars() {
+                    if active.is_empty() {
+                        break;
+                    }
+                    for &q in active.iter() {
+                        visited[q] = false;
+                    }
+                    next.clear();
+                    for &q in active.iter() {
+                        let (label, targets) = &anfa.delta[q];
+                        if self.label_matches(label, c) {
+                            for &q in targets.iter().flatten() {
+                                if !visited[q] {
+                                    visited[q] = true;
+                                    next.push(q);
+                                }
+                            }
+                        }
+                    }
+                    self.close_in_place(anfa, &mut visited, &mut next, false, false);
+                    core::mem::swap(&mut active, &mut next);
+                    empty = false;
+                }
+                // only close again at the end for anchors, see `Matcher::end_closure`
+                if active
+                    .iter()
+                    .any(|&q| matches!(anfa.delta[q].0, Label::Anchor(_)))
+                {
+                    self.close_in_place(anfa, &mut visited, &mut active, empty, true);
+                }
+                let accepted = active.contains(&f);
+                for &q in active.iter() {
+                    visited[q] = false;
+                }
+                accepted
+            })
+            .collect()
+    }
+
+    /// Returns whether the top machine accepts the whole input, could accept it
+    /// once more chars are appended, or can never accept it.
+    ///
+    /// Active states that cannot reach `f` are dropped after each step, so
+    /// `MatchState::CouldMatch` means some continuation of the input is accepted.
+    pub fn partial_match_prefix(&self, anfa: &ANFA, input: &str) -> MatchState {
+        let [q0, f] = match anfa.automata_refs.last() {
+            None => {
+                return MatchState::Rejected;
+            }
+            Some(machine) => *machine,
+        };
+        let live = anfa.machine_states([q0, f]);
+        let is_live = |q: &QId| live.binary_search(q).is_ok();
+        let mut active = self.anchored_closure(anfa, &[q0], true, false);
+        let mut empty = true;
+        for c in input.chars() {
+            active.retain(is_live);
+            if active.is_empty() {
+                return MatchState::Rejected;
+            }
+            active = self.step(anfa, &active, c);
+            empty = false;
+        }
+        if self.end_closure(anfa, active.clone(), empty).contains(&f) {
+            MatchState::Matched
+        } else if active.iter().any(is_live) {
+            MatchState::CouldMatch
+        } else {
+            MatchState::Rejected
+        }
+    }
+
+    /// Returns whether the top machine accepts the whole input, or `Err(Timeout)`
+    /// once more than `step_budget` states have been visited.
+    ///
+    /// Every state in an epsilon closure, and every active state stepped along a
+    /// char, counts as one visit. The work of one match attempt is therefore
+    /// bounded by the budget, plus at most one step.
+    pub fn is_match_with_limit(
+        &self,
+        anfa: &ANFA,
+        input: &str,
+        step_budget: usize,
+    ) -> Result<bool, Timeout> {
+        let [q0, f] = match anfa.automata_refs.last() {
+            None => {
+                return Ok(false);
+            }
+            Some(machine) => *machine,
+        };
+        let mut visits: usize = 0;
+        let mut spend = |states: usize| {
+            visits = visits.saturating_add(states);
+            if visits > step_budget {
+                Err(Timeout)
+            } else {
+                Ok(())
+            }
+        };
+        let mut active = self.anchored_closure(anfa, &[q0], true, false);
+        spend(active.len())?;
+        for c in input.chars() {
+            if active.is_empty() {
+                return Ok(false);
+            }
+            spend(active.len())?;
+            active = self.step(anfa, &active, c);
+            spend(active.len())?;
+        }
+        active = self.end_closure(anfa, active, input.is_empty());
+        spend(active.len())?;
+        Ok(active.contains(&f))
+    }
+
+    /// Returns the byte range `(start, end)` of the leftmost-longest match of
+    /// the top machine within the input.
+    pub fn find(&self, anfa: &ANFA, input: &str) -> Option<(usize, usize)> {
+        anfa.automata_refs.last()?;
+        input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(core::iter::once(input.len()))
+            .find_map(|start| Some((start, self.longest_from(anfa, start, input)?)))
+    }
+
+    /// Returns the end of the longest match of the top machine starting at byte
+    /// `pos` of the input, or `None` when nothing matches there or `pos` is not a
+    /// char boundary.
+    ///
+    /// Simulation starts from the closure of `q0` at `pos` and stops once no state
+    /// is active, so a max-munch tokenizer can call this at each token start. `^`
+    /// only matches when `pos` is 0.
+    pub fn longest_from(&self, anfa: &ANFA, pos: usize, input: &str) -> Option<usize> {
+        let [q0, f] = *anfa.automata_refs.last()?;
+        let rest = input.get(pos..)?;
+        let at_end = |i: usize| i == input.len();
+        let mut active = self.anchored_closure(anfa, &[q0], pos == 0, at_end(pos));
+        let mut end = None;
+        if active.contains(&f) {
+            end = Some(pos);
+        }
+        for (i, c) in rest.char_indices() {
+            let i = pos + i + c.len_utf8();
+            active = self.step(anfa, &active, c);
+            if at_end(i) {
+                active = self.end_closure(anfa, active, false);
+            }
+            if active.is_empty() {
+                break;
+            }
+            if active.contains(&f) {
+                end = Some(i);
+            }
+        }
+        end
+    }
+
+    /// Returns the byte range `(start, end)` of the leftmost-first match of the
+    /// top machine within the input, i.e. the match a backtracking engine finds.
+    ///
+    /// Active states are kept in priority order, a Pike VM: epsilon transitions are
+    /// followed depth first, `targets[0]` before `targets[1]`, and once a state
+    /// reaches `f` every lower-priority state is dropped. Union branches are
+    /// therefore preferences, so `a*` prefers repeating and a lazy star, see
+    /// `CompilerExt::star_lazy`, prefers leaving. `max_epsilon_depth` is ignored.
+    pub fn find_first(&self, anfa: &ANFA, input: &str) -> Option<(usize, usize)> {
+        let [q0, f] = *anfa.automata_refs.last()?;
+        let starts = input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(core::iter::once(input.len()));
+        let mut visited = vec![false; anfa.delta.len()];
+        for start in starts {
+            let mut active = vec::Vec::new();
+            self.ordered_closure(anfa, q0, start, input, &mut visited, &mut active);
+            let mut end = None;
+            let mut at = start;
+            let mut chars = input[start..].chars();
+            loop {
+                if let Some(i) = active.iter().position(|&q| q == f) {
+                    end = Some(at);
+                    active.truncate(i);
+                }
+                let c = match chars.next() {
+                    Some(c) if !active.is_empty() => c,
+                    _ => break,
+                };
+                at += c.len_utf8();
+                visited.iter_mut().for_each(|v| *v = false);
+                let mut next = vec::Vec::new();
+                for &q in active.iter() {
+                    let (label, targets) = &anfa.delta[q];
+                    if self.label_matches(label, c) {
+                        for &q in targets.iter().flatten() {
+                            self.ordered_closure(anfa, q, at, input, &mut visited, &mut next);
+                        }
+                    }
+                }
+                active = next;
+            }
+            if end.is_some() {
+                return end.map(|end| (start, end));
+            }
+            visited.iter_mut().for_each(|v| *v = false);
+        }
+        None
+    }
+
+    /// Appends `q` and the states reachable from it along epsilon transitions to
+    /// `closure` in priority order, skipping visited states. `at` is the byte
+    /// position in `input`, which decides the anchors that are followed.
+    fn ordered_closure(
+        &self,
+        anfa: &ANFA,
+        q: QId,
+        at: usize,
+        input: &str,
+        visited: &mut [bool],
+        closure: &mut vec::Vec<QId>,
+    ) {
+        let mut stack = vec![q];
+        while let Some(q) = stack.pop() {
+            if visited[q] {
+                continue;
+            }
+            visited[q] = true;
+            closure.push(q);
+            let (label, targets) = &anfa.delta[q];
+            let advances = match label {
+                Label::Epsilon => true,
+                Label::Anchor(AnchorKind::Start) => at == 0,
+                Label::Anchor(AnchorKind::End) => at == input.len(),
+                _ => false,
+            };
+            if advances {
+                // push in reverse so targets[0] is expanded first
+                stack.extend(targets.iter().rev().flatten());
+            }
+        }
+    }
+
+    /// Returns the sorted active states of the top machine before any input is
+    /// consumed and after each char. Once the machine is stuck every remaining
+    /// set is empty.
+    pub fn debug_trace(&self, anfa: &ANFA, input: &str) -> vec::Vec<vec::Vec<QId>> {
+        let [q0, _f] = match anfa.automata_refs.last() {
+            None => {
+                return vec::Vec::new();
+            }
+            Some(machine) => *machine,
+        };
+        let mut active = self.anchored_closure(anfa, &[q0], true, false);
+        let mut trace = vec::Vec::with_capacity(input.len() + 1);
+        for c in input.chars() {
+            let next = self.step(anfa, &active, c);
+            active.sort_unstable();
+            trace.push(active);
+            active = next;
+        }
+        active = self.end_closure(anfa, active, input.is_empty());
+        active.sort_unstable();
+        trace.push(active);
+        trace
+    }
+
+    /// Returns true when a transition labeled `label` may be taken on input `c`.
+    pub(crate) fn label_matches(&self, label: &Label, c: char) -> bool {
+        match label {
+            Label::Char(l) => *l == c || (self.case_insensitive && l.eq_ignore_ascii_case(&c)),
+            Label::Any => c != '\n' || self.dot_matches_newline,
+            Label::Class(ranges, negated) if self.case_insensitive => {
+                let inside = |c: char| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+                (inside(c.to_ascii_lowercase()) || inside(c.to_ascii_uppercase())) != *negated
+            }
+            _ => label.matches(c),
+        }
+    }
+
+    /// Advances every active state along `c`, then follows epsilon transitions.
+    pub(crate) fn step(&self, anfa: &ANFA, active: &[QId], c: char) -> vec::Vec<QId> {
+        self.epsilon_closure(anfa, &self.step_set(anfa, active, c))
+    }
+
+    /// Advances every active state along `c` without following epsilon transitions.
+    /// Each state is returned once, in the order it is reached.
+    pub(crate) fn step_set(&self, anfa: &ANFA, active: &[QId], c: char) -> vec::Vec<QId> {
+        let mut visited = vec![false; anfa.delta.len()];
+        let mut next = vec::Vec::new();
+        for &q in active {
+            let (label, targets) = &anfa.delta[q];
+            if self.label_matches(label, c) {
+                for &q in targets.iter().flatten() {
+                    if !visited[q] {
+                        visited[q] = true;
+                        next.push(q);
+                    }
+                }
+            }
+        }
+        next
+    }
+
+    /// Returns every state reachable from `states` along epsilon transitions, including `states`.
+    ///
+    /// The closure is a breadth-first search over a visited bitset, so each state
+    /// is expanded at most once no matter how long or nested its epsilon chains are,
+    /// e.g. `((a*)*)*`. One simulation step is therefore O(states + edges).
+    pub(crate) fn epsilon_closure(&self, anfa: &ANFA, states: &[QId]) -> vec::Vec<QId> {
+        self.anchored_closure(anfa, states, false, false)
+    }
+
+    /// Returns `active` at the end of the input, after following any `$` anchors,
+    /// and `^` anchors too when `at_start`. Without anchors `active` is returned
+    /// as is, so `max_epsilon_depth` is not spent twice.
+    fn end_closure(&self, anfa: &ANFA, active: vec::Vec<QId>, at_start: bool) -> vec::Vec<QId> {
+        if active
+            .iter()
+            .any(|&q| matches!(anfa.delta[q].0, Label::Anchor(_)))
+        {
+            self.anchored_closure(anfa, &active, at_start, true)
+        } else {
+            active
+        }
+    }
+
+    /// Returns the epsilon closure of `states`, also following `Label::Anchor`
+    /// transitions whose position holds. `at_start` and `at_end` tell whether
+    /// the input position is the start or end of the input.
+    pub(crate) fn anchored_closure(
+        &self,
+        anfa: &ANFA,
+        states: &[QId],
+        at_start: bool,
+        at_end: bool,
+    ) -> vec::Vec<QId> {
+        let mut visited = vec![false; anfa.delta.len()];
+        let mut closure = vec::Vec::with_capacity(states.len());
+        for &q in states {
+            if !visited[q] {
+                visited[q] = true;
+                closure.push(q);
+            }
+        }
+        self.close_in_place(anfa, &mut visited, &mut closure, at_start, at_end);
+        closure
+    }
+
+    /// Extends `closure` to its anchored closure. See `Matcher::anchored_closure`.
+    /// Every state of `closure` must be marked in `visited`, and every state
+    /// pushed is marked, so callers can reuse both buffers between steps.
+    fn close_in_place(
+        &self,
+        anfa: &ANFA,
+        visited: &mut [bool],
+        closure: &mut vec::Vec<QId>,
+        at_start: bool,
+        at_end: bool,
+    ) {
+        let mut depth = 0;
+        let mut frontier = 0;
+        while frontier < closure.len() {
+            if self.max_epsilon_depth.is_some_and(|max| depth >= max) {
+                break;
+            }
+            let frontier_end = closure.len();
+            for i in frontier..frontier_end {
+                let (label, targets) = &anfa.delta[closure[i]];
+                let advances = match label {
+                    Label::Epsilon => true,
+                    Label::Anchor(AnchorKind::Start) => at_start,
+                    Label::Anchor(AnchorKind::End) => at_end,
+                    _ => false,
+                };
+                if advances {
+                    for &q in targets.iter().flatten() {
+                        if !visited[q] {
+                            visited[q] = true;
+                            closure.push(q);
+                        }
+                    }
+                }
+            }
+            frontier = frontier_end;
+            depth += 1;
+        }
+    }
+}
+
+/// How far an input is from being accepted. See `Matcher::partial_match_prefix`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchState {
+    /// The whole input is accepted
+    Matched,
+    /// The input is not accepted, but appending more chars can make it accepted
+    CouldMatch,
+    /// No input starting with the input is accepted
+    Rejected,
+}
+
+/// A simulation ran out of its step budget. See `Matcher::is_match_with_limit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeout;
+
+impl core::fmt::Display for Timeout {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Simulation exceeded its step budget.")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Timeout {}
+
+/// A matcher bound to one ANFA, which skips epsilon closure when it can.
+///
+/// When no labeled transition leads to an epsilon transition, e.g. after
+/// `ANFA::remove_epsilons`, the closure of every step is the step itself. Only
+/// the start states are closed over, then each char moves directly to the next
+/// states. `remove_epsilons` keeps epsilon fan-out states when a state has more
+/// than two successors, and those machines still take the closure path.
+///
+/// The ANFA is owned so `delta` cannot change after it is checked.
+///
+/// ```rust
+/// use regexxx::matcher::{CompiledMatcher, Matcher};
+/// use regexxx::parser::parse;
+/// let mut machine = parse("a(b|c)d").unwrap();
+/// machine.remove_epsilons().unwrap();
+/// let compiled = CompiledMatcher::new(machine, Matcher::default());
+/// assert!(compiled.is_epsilon_free());
+/// assert!(compiled.is_match("acd"));
+/// ```
+#[derive(Debug)]
+pub struct CompiledMatcher {
+    anfa: ANFA,
+    matcher: Matcher,
+    epsilon_free: bool,
+}
+
+impl CompiledMatcher {
+    /// Returns a matcher for the top machine of `anfa`.
+    pub fn new(anfa: ANFA, matcher: Matcher) -> CompiledMatcher {
+        // anchors are only followed at the ends of the input, so they take the closure path
+        let epsilon_free = anfa.delta.iter().all(|(label, targets)| match label {
+            Label::Anchor(_) => false,
+            Label::Epsilon => true,
+            _ => targets
+                .iter()
+                .flatten()
+                .all(|&q| !matches!(&anfa.delta[q], (Label::Epsilon, [Some(_), _]))),
+        });
+        CompiledMatcher {
+            anfa,
+            matcher,
+            epsilon_free,
+        }
+    }
+
+    /// Returns true when matching skips epsilon closure after the start states.
+    pub fn is_epsilon_free(&self) -> bool {
+        self.epsilon_free
+    }
+
+    /// Returns the matched ANFA.
+    pub fn anfa(&self) -> &ANFA {
+        &self.anfa
+    }
+
+    /// Returns the matched ANFA, e.g. to modify it and compile it again.
+    pub fn into_anfa(self) -> ANFA {
+        self.anfa
+    }
+
+    /// Returns true when the top machine accepts the whole input. See `Matcher::is_match`.
+    pub fn is_match(&self, input: &str) -> bool {
+        if !self.epsilon_free {
+            return self.matcher.is_match(&self.anfa, input);
+        }
+        let [q0, f] = match self.anfa.automata_refs.last() {
+            None => {
+                return false;
+            }
+            Some(machine) => *machine,
+        };
+        let mut visited = vec![false; self.anfa.delta.len()];
+        let mut active = self.matcher.epsilon_closure(&self.anfa, &[q0]);
+        let mut next = vec::Vec::with_capacity(active.len());
+        for c in input.chars() {
+            if active.is_empty() {
+                return false;
+            }
+            for &q in active.iter() {
+                let (label, targets) = &self.anfa.delta[q];
+                if self.matcher.label_matches(label, c) {
+                    for &q in targets.iter().flatten() {
+                        if !visited[q] {
+                            visited[q] = true;
+                            next.push(q);
+                        }
+                    }
+                }
+            }
+            for &q in next.iter() {
+                visited[q] = false;
+            }
+            core::mem::swap(&mut active, &mut next);
+            next.clear();
+        }
+        active.contains(&f)
+    }
+}
+
+impl ANFA {
+    /// Returns true when the top machine accepts the whole input. See `Matcher::is_match`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert!(machine.is_match("a"));
+    /// assert!(!machine.is_match("aa"));
+    /// ```
+    pub fn is_match(&self, input: &str) -> bool {
+        Matcher::default().is_match(self, input)
+    }
+
+    /// Returns true when the top machine accepts the chars of an iterator. See
+    /// `Matcher::is_match_chars`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a*b").unwrap();
+    /// assert!(machine.is_match_chars("aab".chars()));
+    /// assert!(machine.is_match_chars(vec!['a', 'a', 'b']));
+    /// ```
+    pub fn is_match_chars<I: IntoIterator<Item = char>>(&self, chars: I) -> bool {
+        Matcher::default().is_match_chars(self, chars)
+    }
+
+    /// Returns whether the top machine accepts each whole input, reusing buffers
+    /// across inputs. See `Matcher::match_all_anchored`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a|bc").unwrap();
+    /// assert_eq!(machine.match_all_anchored(&["a", "b", "bc"]), [true, false, true]);
+    /// ```
+    pub fn match_all_anchored(&self, inputs: &[&str]) -> vec::Vec<bool> {
+        Matcher::default().match_all_anchored(self, inputs)
+    }
+
+    /// Returns whether the top machine accepts the whole input, or could with
+    /// more chars, e.g. to validate a form field as it is typed. See
+    /// `Matcher::partial_match_prefix`.
+    ///
+    /// ```rust
+    /// use regexxx::matcher::MatchState;
+    /// use regexxx::parser::parse;
+    /// let machine = parse("abc").unwrap();
+    /// assert_eq!(machine.partial_match_prefix("ab"), MatchState::CouldMatch);
+    /// assert_eq!(machine.partial_match_prefix("abc"), MatchState::Matched);
+    /// assert_eq!(machine.partial_match_prefix("ax"), MatchState::Rejected);
+    /// ```
+    pub fn partial_match_prefix(&self, input: &str) -> MatchState {
+        Matcher::default().partial_match_prefix(self, input)
+    }
+
+    /// Returns whether the top machine accepts the whole input within a budget of
+    /// state visits. See `Matcher::is_match_with_limit`.
+    ///
+    /// ```rust
+    /// use regexxx::matcher::Timeout;
+    /// use regexxx::parser::parse;
+    /// let machine = parse("(a|b)*abb").unwrap();
+    /// assert_eq!(machine.simulate_with_limit("aabb", 1000), Ok(true));
+    /// assert_eq!(machine.simulate_with_limit("aabb", 10), Err(Timeout));
+    /// ```
+    pub fn simulate_with_limit(&self, input: &str, step_budget: usize) -> Result<bool, Timeout> {
+        Matcher::default().is_match_with_limit(self, input, step_budget)
+    }
+
+    /// Returns the states reached from `states` by one transition along `c`,
+    /// without following epsilon transitions afterwards.
+    ///
+    /// Together with `ANFA::epsilon_closure` this is one step of simulation:
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("ab").unwrap();
+    /// let [q0, f] = machine.automata_refs[0];
+    /// let mut active = machine.epsilon_closure(&[q0]);
+    /// for c in "ab".chars() {
+    ///     active = machine.epsilon_closure(&machine.step_set(&active, c));
+    /// }
+    /// assert!(active.contains(&f));
+    /// ```
+    pub fn step_set(&self, states: &[QId], c: char) -> vec::Vec<QId> {
+        Matcher::default().step_set(self, states, c)
+    }
+
+    /// Returns every state reachable from `states` along epsilon transitions, including `states`.
+    pub fn epsilon_closure(&self, states: &[QId]) -> vec::Vec<QId> {
+        Matcher::default().epsilon_closure(self, states)
+    }
+
+    /// Returns the byte range of the leftmost-longest match. See `Matcher::find`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.find("bab"), Some((1, 2)));
+    /// ```
+    pub fn find(&self, input: &str) -> Option<(usize, usize)> {
+        Matcher::default().find(self, input)
+    }
+
+    /// Returns the end of the longest match starting at byte `pos`. See
+    /// `Matcher::longest_from`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("ab*").unwrap();
+    /// assert_eq!(machine.simulate_longest_from(1, "xabbx"), Some(4));
+    /// assert_eq!(machine.simulate_longest_from(0, "xabbx"), None);
+    /// ```
+    pub fn simulate_longest_from(&self, pos: usize, input: &str) -> Option<usize> {
+        Matcher::default().longest_from(self, pos, input)
+    }
+
+    /// Returns the byte range of the longest match starting at each char boundary,
+    /// in order of their start, so matches may overlap.
+    ///
+    /// Only the longest match per start is reported, not every match length, so
+    /// `a*` over `"aa"` gives `(0, 2)` but not `(0, 1)` or `(0, 0)`. Unlike
+    /// `CompiledRegex::find_iter`, a match does not skip the starts inside it.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("aa").unwrap();
+    /// assert_eq!(
+    ///     machine.match_positions_overlapping("aaaa"),
+    ///     [(0, 2), (1, 3), (2, 4)]
+    /// );
+    /// ```
+    pub fn match_positions_overlapping(&self, input: &str) -> vec::Vec<(usize, usize)> {
+        let matcher = Matcher::default();
+        input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(core::iter::once(input.len()))
+            .filter_map(|start| Some((start, matcher.longest_from(self, start, input)?)))
+            .collect()
+    }
+
+    /// Returns true when the top machine accepts some suffix of the input,
+    /// including the empty suffix.
+    ///
+    /// The forward machine is run once, adding the closure of `q0` after every
+    /// char so a new suffix starts at each position, and accepts when `f` is
+    /// active at the end. Threads of different starts share one set of active
+    /// states, so this takes O(n · (states + edges)) for n chars rather than a
+    /// simulation per start. `^` only matches the whole input.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("b*c").unwrap();
+    /// assert!(machine.is_suffix_match("abbc"));
+    /// assert!(!machine.is_suffix_match("abbca"));
+    /// ```
+    pub fn is_suffix_match(&self, input: &str) -> bool {
+        let [q0, f] = match self.automata_refs.last() {
+            None => return false,
+            Some(&machine) => machine,
+        };
+        let matcher = Matcher::default();
+        let mut active = matcher.anchored_closure(self, &[q0], true, input.is_empty());
+        let mut at = 0;
+        for c in input.chars() {
+            at += c.len_utf8();
+            let mut next = matcher.step_set(self, &active, c);
+            next.push(q0);
+            active = matcher.anchored_closure(self, &next, false, at == input.len());
+        }
+        active.contains(&f)
+    }
+
+    /// Returns every edge `(from, to)` that simulating the top machine over each
+    /// of `inputs` never takes, to find gaps in a test suite.
+    ///
+    /// An edge is taken when its state is active and it advances: an epsilon edge,
+    /// an anchor edge whose position holds, or a labeled edge whose label matches
+    /// the next char. Edges taken by threads that later die still count. A union
+    /// has one edge per target. Edges outside the top machine are never taken.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a|b").unwrap();
+    /// assert!(!machine.uncovered_transitions(&["a"]).is_empty());
+    /// assert!(machine.uncovered_transitions(&["a", "b"]).is_empty());
+    /// ```
+    pub fn uncovered_transitions(&self, inputs: &[&str]) -> BTreeSet<(QId, QId)> {
+        let mut uncovered: BTreeSet<(QId, QId)> = self
+            .delta
+            .iter()
+            .enumerate()
+            .flat_map(|(q, transition)| transition::targets(transition).map(move |t| (q, t)))
+            .collect();
+        let q0 = match self.automata_refs.last() {
+            Some(&[q0, _f]) => q0,
+            None => return uncovered,
+        };
+        let matcher = Matcher::default();
+        for input in inputs.iter() {
+            let mut active = matcher.anchored_closure(self, &[q0], true, input.is_empty());
+            let mut at = 0;
+            for c in input.chars() {
+                cover_closure(self, &active, at == 0, false, &mut uncovered);
+                for &q in active.iter() {
+                    let (label, targets) = &self.delta[q];
+                    if matcher.label_matches(label, c) {
+                        for &t in targets.iter().flatten() {
+                            uncovered.remove(&(q, t));
+                        }
+                    }
+                }
+                at += c.len_utf8();
+                let next = matcher.step_set(self, &active, c);
+                active = matcher.anchored_closure(self, &next, false, at == input.len());
+            }
+            cover_closure(self, &active, at == 0, true, &mut uncovered);
+        }
+        uncovered
+    }
+
+    /// Returns the byte range of the leftmost-first match. See `Matcher::find_first`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a|ab").unwrap();
+    /// assert_eq!(machine.find_first("ab"), Some((0, 1)));
+    /// assert_eq!(machine.find("ab"), Some((0, 2)));
+    /// ```
+    pub fn find_first(&self, input: &str) -> Option<(usize, usize)> {
+        Matcher::default().find_first(self, input)
+    }
+
+    /// Returns the active states after each char. See `Matcher::debug_trace`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(machine.debug_trace("a"), vec![vec![0], vec![1]]);
+    /// ```
+    pub fn debug_trace(&self, input: &str) -> vec::Vec<vec::Vec<QId>> {
+        Matcher::default().debug_trace(self, input)
+    }
+
+    /// Writes how the active states evolve as each char of `input` is consumed,
+    /// for teaching and debugging. See `ANFA::debug_trace`.
+    ///
+    /// Each step is the char, the active states before and after it, and whether
+    /// `f` is active, followed by a snapshot of every state where active states
+    /// are bracketed. The first step is the closure of `q0` before any input.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let mut out = String::new();
+    /// machine.pretty_print(&mut out, "a").unwrap();
+    /// assert_eq!(
+    ///     out,
+    ///     "start: {0}, rejecting\n  [0]  1\n'a': {0} -> {1}, accepting\n   0  [1]\n"
+    /// );
+    /// ```
+    pub fn pretty_print<W: Write>(&self, w: &mut W, input: &str) -> fmt::Result {
+        let f = match self.automata_refs.last() {
+            None => return Ok(()),
+            Some(&[_q0, f]) => f,
+        };
+        let trace = self.debug_trace(input);
+        let accepting = |states: &[QId]| {
+            if states.contains(&f) {
+                "accepting"
+            } else {
+                "rejecting"
+            }
+        };
+        let snapshot = |w: &mut W, states: &[QId]| -> fmt::Result {
+            let mut line = String::from(" ");
+            for q in 0..self.delta.len() {
+                // writing into a String cannot fail
+                if states.contains(&q) {
+                    let _ = write!(line, " [{}]", q);
+                } else {
+                    let _ = write!(line, "  {} ", q);
+                }
+            }
+            writeln!(w, "{}", line.trim_end())
+        };
+        writeln!(
+            w,
+            "start: {}, {}",
+            StateSet(&trace[0]),
+            accepting(&trace[0])
+        )?;
+        snapshot(w, &trace[0])?;
+        for (c, states) in input.chars().zip(trace.windows(2)) {
+            writeln!(
+                w,
+                "{:?}: {} -> {}, {}",
+                c,
+                StateSet(&states[0]),
+                StateSet(&states[1]),
+                accepting(&states[1])
+            )?;
+            snapshot(w, &states[1])?;
+        }
+        Ok(())
+    }
+}
+
+/// Removes the epsilon and anchor edges that `closure` follows from `uncovered`.
+/// See `ANFA::uncovered_transitions`.
+fn cover_closure(
+    anfa: &ANFA,
+    closure: &[QId],
+    at_start: bool,
+    at_end: bool,
+    uncovered: &mut BTreeSet<(QId, QId)>,
+) {
+    for &q in closure {
+        let (label, targets) = &anfa.delta[q];
+        let advances = match label {
+            Label::Epsilon => true,
+            Label::Anchor(AnchorKind::Start) => at_start,
+            Label::Anchor(AnchorKind::End) => at_end,
+            _ => false,
+        };
+        if advances {
+            for &t in targets.iter().flatten() {
+                uncovered.remove(&(q, t));
+            }
+        }
+    }
+}
+
+/// Formats sorted states as `{0, 1}`.
+struct StateSet<'a>(&'a [QId]);
+
+impl<'a> fmt::Display for StateSet<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("{")?;
+        for (i, q) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", q)?;
+        }
+        f.write_str("}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::compilers::CompilerExt;
+    use crate::matcher::{CompiledMatcher, MatchState, Matcher, Timeout};
+    use crate::parser::parse;
+    use crate::{Label, ANFA};
+    use alloc::vec;
+
+    #[test]
+    fn test_is_match() {
+        // RE a(b|c)*d
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'd').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("ad"), "Star accepts zero repetitions");
+        assert!(machine.is_match("abccbd"), "Star accepts many repetitions");
+        assert!(!machine.is_match("abc"), "Input must reach the final state");
+        assert!(
+            !machine.is_match("abdd"),
+            "The whole input must be consumed"
+        );
+        assert!(!machine.is_match(""), "Empty input is not in the language");
+    }
+
+    #[test]
+    fn test_is_match_chars() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert!(machine.is_match_chars("aaa".chars()), "a* accepts aaa");
+        assert!(
+            machine.is_match_chars(['a', 'a', 'a'].iter().copied()),
+            "Any char iterator is accepted"
+        );
+        assert!(
+            machine.is_match_chars(core::iter::empty()),
+            "a* accepts no chars"
+        );
+        assert!(!machine.is_match_chars("aab".chars()), "a* rejects aab");
+        // stops at 'b', so the endless tail is never read
+        let endless = "ab".chars().chain(core::iter::repeat('a'));
+        assert!(!machine.is_match_chars(endless), "Iteration stops early");
+        let machine = parse("^a$").unwrap();
+        assert!(machine.is_match_chars("a".chars()), "Anchors hold");
+    }
+
+    #[test]
+    fn test_match_all_anchored() {
+        let machine = parse("(ab)*").unwrap();
+        // "ab" is one repetition, so (ab)* accepts it
+        assert_eq!(
+            machine.match_all_anchored(&["ab", "a", "abab"]),
+            [true, false, true],
+            "Each input is matched whole"
+        );
+        assert_eq!(
+            machine.match_all_anchored(&[""]),
+            [true],
+            "(ab)* accepts ''"
+        );
+        let inputs = ["", "ab", "aba", "b", "abab", "ba"];
+        let expected: vec::Vec<bool> = inputs.iter().map(|input| machine.is_match(input)).collect();
+        assert_eq!(
+            machine.match_all_anchored(&inputs),
+            expected,
+            "Reused buffers do not leak between inputs"
+        );
+        let machine = parse("^a$|b").unwrap();
+        assert_eq!(
+            machine.match_all_anchored(&["a", "b", "ab"]),
+            [true, true, false],
+            "Anchors hold"
+        );
+        assert_eq!(
+            ANFA::new().match_all_anchored(&["", "a"]),
+            [false, false],
+            "No machine accepts nothing"
+        );
+    }
+
+    #[test]
+    fn test_partial_match_prefix() {
+        let machine = parse("abc").unwrap();
+        assert_eq!(
+            machine.partial_match_prefix("ab"),
+            MatchState::CouldMatch,
+            "ab can become abc"
+        );
+        assert_eq!(
+            machine.partial_match_prefix("abc"),
+            MatchState::Matched,
+            "abc is accepted"
+        );
+        assert_eq!(
+            machine.partial_match_prefix("ax"),
+            MatchState::Rejected,
+            "Nothing starting with ax is accepted"
+        );
+        assert_eq!(
+            machine.partial_match_prefix("abcd"),
+            MatchState::Rejected,
+            "Nothing longer than abc is accepted"
+        );
+        assert_eq!(
+            machine.partial_match_prefix(""),
+            MatchState::CouldMatch,
+            "'' can become abc"
+        );
+        let machine = parse("a(b|c)*$").unwrap();
+        assert_eq!(
+            machine.partial_match_prefix("abc"),
+            MatchState::Matched,
+            "Anchors hold at the end"
+        );
+        let mut machine = parse("a|xy").unwrap();
+        // cut y, so the x branch never reaches f
+        let y = machine
+            .delta
+            .iter()
+            .position(|(label, _)| *label == Label::Char('y'))
+            .unwrap();
+        machine.delta[y].1 = [None, None];
+        assert_eq!(
+            machine.partial_match_prefix("x"),
+            MatchState::Rejected,
+            "Dead states cannot become a match"
+        );
+        assert_eq!(
+            machine.partial_match_prefix(""),
+            MatchState::CouldMatch,
+            "'' can become a"
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert_eq!(
+            machine.find("baab"),
+            Some((0, 0)),
+            "Leftmost match wins, even when empty"
+        );
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(
+            machine.find("baab"),
+            Some((1, 3)),
+            "Longest match from the leftmost start"
+        );
+        assert_eq!(machine.find("bbb"), None, "No match");
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let insensitive = Matcher {
+            case_insensitive: true,
+            ..Matcher::default()
+        };
+        assert!(
+            insensitive.is_match(&machine, "A"),
+            "Case insensitive literal 'a' matches 'A'"
+        );
+        assert!(
+            !Matcher::default().is_match(&machine, "A"),
+            "Case sensitive literal 'a' does not match 'A'"
+        );
+        let machine = ForwardCompiler::from_expr_a('é').unwrap();
+        assert!(
+            !insensitive.is_match(&machine, "É"),
+            "Non-ASCII labels are compared exactly"
+        );
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        machine.delta[0].0 = Label::Class(vec![('a', 'c')], true);
+        assert!(
+            !insensitive.is_match(&machine, "B"),
+            "Case insensitive [^a-c] rejects 'B'"
+        );
+        assert!(
+            Matcher::default().is_match(&machine, "B"),
+            "Case sensitive [^a-c] matches 'B'"
+        );
+    }
+
+    #[test]
+    fn test_nested_star() {
+        // RE ((a*)*)*
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert!(machine.is_match(""), "Nested star accepts empty input");
+        assert!(machine.is_match("aaaa"), "Nested star accepts repetitions");
+        assert!(!machine.is_match("aab"), "Nested star rejects other labels");
+        let long_input = "a".repeat(10_000);
+        assert!(
+            machine.is_match(&long_input),
+            "Each state is expanded once per step, so long input stays linear"
+        );
+    }
+
+    #[test]
+    fn test_max_epsilon_depth() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let shallow = Matcher {
+            max_epsilon_depth: Some(1),
+            ..Matcher::default()
+        };
+        assert!(
+            !shallow.is_match(&machine, ""),
+            "Star's final state is two epsilon hops from q0"
+        );
+        let deep = Matcher {
+            max_epsilon_depth: Some(2),
+            ..Matcher::default()
+        };
+        assert!(
+            deep.is_match(&machine, ""),
+            "Two hops reach the final state"
+        );
+        assert!(deep.is_match(&machine, "aa"), "Depth resets on every step");
+    }
+
+    #[test]
+    fn test_debug_trace() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(
+            machine.debug_trace("ab"),
+            vec![vec![0], vec![1, 2], vec![3]],
+            "'a' ⋅ 'b' moves through the epsilon bridge to f"
+        );
+        assert_eq!(
+            machine.debug_trace("acb"),
+            vec![vec![0], vec![1, 2], vec![], vec![]],
+            "Stuck machines trace empty sets"
+        );
+        machine.remove_epsilons().unwrap();
+        assert_eq!(
+            machine.debug_trace("ab"),
+            vec![vec![0], vec![2], vec![3]],
+            "Epsilon-free 'a' ⋅ 'b' is in a single state at each position"
+        );
+    }
+
+    #[test]
+    fn test_dot_matches_newline() {
+        let mut machine = ANFA::new();
+        ForwardCompiler::expr_any(&mut machine).unwrap();
+        assert!(machine.is_match("a"), "'.' matches an ASCII char");
+        assert!(
+            machine.is_match("😀"),
+            "'.' matches any Unicode scalar value"
+        );
+        assert!(!machine.is_match(""), "'.' consumes exactly one char");
+        assert!(
+            !machine.is_match("\n"),
+            "'.' does not match a newline by default"
+        );
+        let dot_all = Matcher {
+            dot_matches_newline: true,
+            ..Matcher::default()
+        };
+        assert!(
+            dot_all.is_match(&machine, "\n"),
+            "'.' matches a newline when enabled"
+        );
+    }
+
+    #[test]
+    fn test_compiled_matcher() {
+        for pattern in ["a*", "ab*", "a(b|c)d", "(a|b)c", "(a|b)*abb"] {
+            let machine = parse(pattern).unwrap();
+            let mut epsilon_free = parse(pattern).unwrap();
+            epsilon_free.remove_epsilons().unwrap();
+            let slow = CompiledMatcher::new(machine, Matcher::default());
+            let fast = CompiledMatcher::new(epsilon_free, Matcher::default());
+            assert!(
+                !slow.is_epsilon_free(),
+                "{} has epsilon transitions",
+                pattern
+            );
+            for input in ["", "a", "ab", "abb", "aabb", "abd", "ac", "bc", "abbb"] {
+                assert_eq!(
+                    fast.is_match(input),
+                    slow.is_match(input),
+                    "Fast and closure paths agree on {} for {:?}",
+                    pattern,
+                    input
+                );
+            }
+        }
+        let mut machine = parse("a(b|c)d").unwrap();
+        machine.remove_epsilons().unwrap();
+        assert!(
+            CompiledMatcher::new(machine, Matcher::default()).is_epsilon_free(),
+            "Removing epsilons from a(b|c)d enables the fast path"
+        );
+        let mut machine = parse("(a|b)*abb").unwrap();
+        machine.remove_epsilons().unwrap();
+        assert!(
+            !CompiledMatcher::new(machine, Matcher::default()).is_epsilon_free(),
+            "Fan-out states of (a|b)*abb keep the closure path"
+        );
+    }
+
+    #[test]
+    fn test_simulate_with_limit() {
+        let machine = parse("(a|b)*abb").unwrap();
+        let input = "ab".repeat(50) + "abb";
+        assert_eq!(
+            machine.simulate_with_limit(&input, 100_000),
+            Ok(true),
+            "A generous budget completes the match"
+        );
+        assert_eq!(
+            machine.simulate_with_limit("abab", 100_000),
+            Ok(false),
+            "A generous budget completes the rejection"
+        );
+        assert_eq!(
+            machine.simulate_with_limit(&input, 20),
+            Err(Timeout),
+            "A tiny budget times out"
+        );
+        assert_eq!(
+            machine.simulate_with_limit("", 0),
+            Err(Timeout),
+            "Closing over q0 counts against the budget"
+        );
+    }
+
+    #[test]
+    fn test_step_set() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        assert_eq!(
+            machine.step_set(&[machine_a_q0], 'a'),
+            [machine_a_f],
+            "q0 of 'a' moves to f along 'a'"
+        );
+        assert!(
+            machine.step_set(&[machine_a_q0], 'b').is_empty(),
+            "q0 of 'a' does not move along 'b'"
+        );
+        // RE ab, where f of 'a' is an epsilon state
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert_eq!(
+            machine.step_set(&[0], 'a'),
+            [1],
+            "Epsilon transitions are not followed"
+        );
+        assert_eq!(
+            machine.epsilon_closure(&machine.step_set(&[0], 'a')),
+            [1, 2],
+            "Closure follows them"
+        );
+        assert_eq!(
+            machine.step_set(&[0, 0, 2], 'a'),
+            [1],
+            "Each state is returned once"
+        );
+    }
+
+    #[test]
+    fn test_anchors() {
+        let machine = parse("^a$").unwrap();
+        assert!(machine.is_match("a"), "^a$ matches 'a'");
+        assert!(!machine.is_match("ba"), "^a$ rejects 'ba'");
+        assert!(!machine.is_match("ab"), "^a$ rejects 'ab'");
+        let machine = parse("a$").unwrap();
+        assert_eq!(
+            machine.find("aba"),
+            Some((2, 3)),
+            "$ only matches at the end"
+        );
+        let machine = parse("^a").unwrap();
+        assert_eq!(machine.find("ba"), None, "^ only matches at the start");
+        let machine = parse("(^|b)a").unwrap();
+        assert!(machine.is_match("a"), "^ is zero-width");
+        assert!(machine.is_match("ba"), "Anchors can be alternatives");
+        let machine = parse("^$").unwrap();
+        assert!(machine.is_match(""), "^$ matches ''");
+        assert_eq!(
+            Matcher::default().is_match_with_limit(&machine, "", 10),
+            Ok(true),
+            "Anchors are followed with a limit"
+        );
+        let compiled = CompiledMatcher::new(parse("a$").unwrap(), Matcher::default());
+        assert!(!compiled.is_epsilon_free(), "Anchors take the closure path");
+        assert!(compiled.is_match("a"), "Compiled anchors are followed");
+    }
+
+    #[test]
+    fn test_find_first() {
+        let machine = parse("a|ab").unwrap();
+        assert_eq!(
+            machine.find_first("ab"),
+            Some((0, 1)),
+            "The first branch wins"
+        );
+        let machine = parse("ab|a").unwrap();
+        assert_eq!(
+            machine.find_first("ab"),
+            Some((0, 2)),
+            "The first branch wins"
+        );
+        let machine = parse("b*").unwrap();
+        assert_eq!(
+            machine.find_first("abb"),
+            Some((0, 0)),
+            "The leftmost match wins even when empty"
+        );
+        let machine = parse("a$").unwrap();
+        assert_eq!(
+            machine.find_first("aa"),
+            Some((1, 2)),
+            "Anchors are followed"
+        );
+        assert_eq!(parse("c").unwrap().find_first("ab"), None, "No match");
+    }
+
+    #[test]
+    fn test_simulate_longest_from() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::plus(&mut machine).unwrap();
+        assert_eq!(
+            machine.simulate_longest_from(0, "aaab"),
+            Some(3),
+            "a+ munches every a"
+        );
+        assert_eq!(
+            machine.simulate_longest_from(1, "aaab"),
+            Some(3),
+            "Positions are bytes of the whole input"
+        );
+        assert_eq!(
+            machine.simulate_longest_from(3, "aaab"),
+            None,
+            "a+ does not match at b"
+        );
+        assert_eq!(
+            machine.simulate_longest_from(9, "aaab"),
+            None,
+            "Positions past the input do not match"
+        );
+        let machine = parse("^a|b$").unwrap();
+        assert_eq!(
+            machine.simulate_longest_from(1, "aab"),
+            None,
+            "^ only matches at position 0"
+        );
+        assert_eq!(
+            machine.simulate_longest_from(2, "aab"),
+            Some(3),
+            "$ matches at the end of the whole input"
+        );
+    }
+
+    #[test]
+    fn test_match_positions_overlapping() {
+        let machine = parse("aa").unwrap();
+        assert_eq!(
+            machine.match_positions_overlapping("aaaa"),
+            [(0, 2), (1, 3), (2, 4)],
+            "aa matches at every start with two a's after it"
+        );
+        let machine = parse("a*").unwrap();
+        assert_eq!(
+            machine.match_positions_overlapping("aab"),
+            [(0, 2), (1, 2), (2, 2), (3, 3)],
+            "Only the longest match per start is reported"
+        );
+        assert!(
+            parse("c")
+                .unwrap()
+                .match_positions_overlapping("ab")
+                .is_empty(),
+            "No match"
+        );
+    }
+
+    #[test]
+    fn test_is_suffix_match() {
+        let machine = parse("bc").unwrap();
+        assert!(machine.is_suffix_match("abc"), "abc ends with bc");
+        assert!(!machine.is_suffix_match("bca"), "bca does not end with bc");
+        assert!(machine.is_suffix_match("bc"), "The whole input is a suffix");
+        assert!(
+            parse("a*").unwrap().is_suffix_match("ab"),
+            "a* accepts the empty suffix"
+        );
+        let machine = parse("^b").unwrap();
+        assert!(machine.is_suffix_match("b"), "^b accepts b");
+        assert!(
+            !machine.is_suffix_match("ab"),
+            "^ does not match inside the input"
+        );
+    }
+
+    #[test]
+    fn test_uncovered_transitions() {
+        let machine = parse("a(b|c)*d").unwrap();
+        let c = machine
+            .delta
+            .iter()
+            .position(|(label, _)| *label == Label::Char('c'))
+            .unwrap();
+        let uncovered = machine.uncovered_transitions(&["abd"]);
+        assert!(
+            uncovered.iter().any(|&(q, _)| q == c),
+            "abd never consumes c"
+        );
+        let after_c = machine.targets_at(c)[0].unwrap();
+        assert!(
+            uncovered.iter().all(|&(q, _)| q == c || q == after_c),
+            "abd takes every edge outside the c branch"
+        );
+        assert!(
+            machine.uncovered_transitions(&["abd", "acd"]).is_empty(),
+            "abd and acd take every edge"
+        );
+        assert_eq!(
+            machine.uncovered_transitions(&[]).len(),
+            machine.as_graph_edges().1.len(),
+            "No input takes no edge"
+        );
+        assert!(
+            parse("^a$")
+                .unwrap()
+                .uncovered_transitions(&["a"])
+                .is_empty(),
+            "Anchors are taken where they hold"
+        );
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        let mut out = alloc::string::String::new();
+        machine.pretty_print(&mut out, "abc").unwrap();
+        let lines: vec::Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines.len(),
+            8,
+            "A step and a snapshot per char, and the start"
+        );
+        assert_eq!(lines[0], "start: {0}, rejecting", "The closure of q0");
+        assert_eq!(lines[1], "  [0]  1   2   3", "Only q0 is bracketed");
+        assert_eq!(
+            lines[2], "'a': {0} -> {1, 2}, rejecting",
+            "Consuming a follows the epsilon to b"
+        );
+        assert_eq!(
+            lines[4], "'b': {1, 2} -> {3}, accepting",
+            "f is active after ab"
+        );
+        assert_eq!(
+            lines[6], "'c': {3} -> {}, rejecting",
+            "The machine is stuck"
+        );
+        assert_eq!(lines[7], "   0   1   2   3", "Nothing is bracketed");
+    }
+}
@@ -0,0 +1,828 @@
+use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use crate::error::CompileError;
+use crate::{transition, AutomataRef, Label, QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+
+impl ANFA {
+    /// Unions the top `count` machines into one, i.e. `a | b | c | ...`
+    ///
+    /// `Transition` only holds two targets, so the machines are joined by a
+    /// balanced tree of binary `ForwardCompiler::union`s. Earlier machines
+    /// stay on the left (`0`) branch of each union. See `CompilerExt::union_all`
+    /// for a chain of unions that leans right.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+    /// machine.union_all_balanced(3).unwrap();
+    /// assert!(machine.is_match("b"));
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `'a' ∪ 'b' ∪ 'c' ∪ 'd'`
+    ///
+    /// ('a' ∪ 'b') ∪ ('c' ∪ 'd')
+    /// ```
+    pub fn union_all_balanced(&mut self, count: usize) -> Result<(), CompileError> {
+        if count == 0 || count > self.automata_refs.len() {
+            return Err(CompileError::Invalid("Union requires count operands."));
+        }
+        let operands = self
+            .automata_refs
+            .split_off(self.automata_refs.len() - count);
+        self.union_balanced(&operands)
+    }
+
+    /// Returns one ANFA holding the machines of `self` followed by the machines of `other`.
+    ///
+    /// Every state of `other` is shifted past the states of `self`, so machines
+    /// built separately can be combined by the stack operators afterwards.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine_a = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let machine_b = ForwardCompiler::from_expr_a('b').unwrap();
+    /// let mut machine = machine_a.merge(machine_b);
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert!(machine.is_match("ab"));
+    /// ```
+    pub fn merge(mut self, other: ANFA) -> ANFA {
+        let offset = self.delta.len();
+        let shift = |q: QId| q + offset;
+        self.delta.extend(
+            other
+                .delta
+                .into_iter()
+                .map(|(label, targets)| (label, targets.map(|q| q.map(shift)))),
+        );
+        self.automata_refs
+            .extend(other.automata_refs.into_iter().map(|refs| refs.map(shift)));
+        self
+    }
+
+    /// Concatenates the top two machines without overwriting the final state of the first.
+    ///
+    /// `ForwardCompiler::concatenate` replaces the transition of `machine_a_f` with
+    /// an epsilon transition to `machine_b_q0`. That is only safe while `machine_a_f`
+    /// has no transition of its own, e.g. after `delta` was edited by hand. Here the
+    /// old transition is moved to a new state, and `machine_a_f` forks along epsilon
+    /// to it and to a new bridge state leading into `machine_b`.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// machine.concat_into_existing().unwrap();
+    /// assert!(machine.is_match("ab"));
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `'a' ⋅ 'b'` when f of 'a' transitions along 'x' to itself
+    ///
+    /// State table:
+    /// | Q | T | Q    |
+    /// |---|---|------|
+    /// | 0 | a | 1    | (q0)
+    /// | 1 | ε | 4, 5 |
+    /// | 2 | b | 3    |
+    /// | 3 |   |      | (f)
+    /// | 4 | ε | 2    | (bridge)
+    /// | 5 | x | 1    | (old transition of 1)
+    /// ```
+    pub fn concat_into_existing(&mut self) -> Result<(), CompileError> {
+        if self.automata_refs.len() < 2 {
+            return Err(CompileError::Invalid(
+                "Concatenation requires two operands.",
+            ));
+        }
+        let operands = self.automata_refs.split_off(self.automata_refs.len() - 2);
+        let [machine_a_q0, machine_a_f] = operands[0];
+        let [machine_b_q0, machine_b_f] = operands[1];
+        let bridge = self.delta.len();
+        self.delta.push((
+            // push epsilon bridge into 'b'
+            Label::Epsilon,
+            [Some(machine_b_q0), None],
+        ));
+        let residual = match self.delta[machine_a_f].1 {
+            [None, None] => None,
+            _ => {
+                self.delta.push(self.delta[machine_a_f].clone());
+                Some(self.delta.len() - 1)
+            }
+        };
+        self.delta[machine_a_f] = (
+            // fork 'a' into 'b' and its old transition
+            Label::Epsilon,
+            [Some(bridge), residual],
+        );
+        self.automata_refs.push([machine_a_q0, machine_b_f]);
+        self.debug_assert_touched(&[machine_a_f, bridge]);
+        Ok(())
+    }
+
+    /// Concatenates the top two machines like `ForwardCompiler::concatenate`, but
+    /// pads a stack of fewer than two machines with epsilon instead of failing.
+    ///
+    /// This is for interactive use, e.g. a REPL, where a partial build should
+    /// degrade gracefully. Epsilon is the identity of concatenation, so one machine
+    /// is left unchanged and an empty stack becomes epsilon. Programs should use
+    /// the strict `ForwardCompiler::concatenate`, which reports the missing operand.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// machine.concatenate_or_push_epsilon().unwrap();
+    /// assert!(machine.is_match("a"));
+    /// ```
+    pub fn concatenate_or_push_epsilon(&mut self) -> Result<(), CompileError> {
+        while self.automata_refs.len() < 2 {
+            ForwardCompiler::expr_1(self)?;
+        }
+        ForwardCompiler::concatenate(self)
+    }
+
+    /// Concatenates the top `count` machines into one, i.e. `a ⋅ b ⋅ c ⋅ ...`,
+    /// without the epsilon bridges `ForwardCompiler::concatenate` adds.
+    ///
+    /// When `f` of one machine has no transition and `q0` of the next has no
+    /// incoming edge and is no other machine's state, the transition of `q0` is
+    /// spliced into `f` and `q0` is removed, so a literal of n chars keeps n + 1
+    /// states instead of 2n. Other pairs fall back to `ForwardCompiler::concatenate`.
+    /// Removing states renumbers the states after them.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+    /// machine.concat_reuse(3).unwrap();
+    /// assert!(machine.is_match("abc"));
+    /// assert_eq!(machine.delta.len(), 4);
+    /// ```
+    ///
+    /// ```text
+    /// Definition of `'a' ⋅ 'b'`
+    ///
+    /// State table:
+    /// | Q | T | Q |
+    /// |---|---|---|
+    /// | 0 | a | 1 | (q0)
+    /// | 1 | b | 2 | (f of 'a', spliced with q0 of 'b')
+    /// | 2 |   |   | (f)
+    /// ```
+    pub fn concat_reuse(&mut self, count: usize) -> Result<(), CompileError> {
+        if count == 0 || count > self.automata_refs.len() {
+            return Err(CompileError::Invalid(
+                "Concatenation requires count operands.",
+            ));
+        }
+        let operands = self
+            .automata_refs
+            .split_off(self.automata_refs.len() - count);
+        let mut incoming = vec![0; self.delta.len()];
+        for (_, targets) in self.delta.iter() {
+            for &target in targets.iter().flatten() {
+                incoming[target] += 1;
+            }
+        }
+        let mut refs = vec![0; self.delta.len()];
+        for &[q0, f] in self.automata_refs.iter().chain(operands.iter()) {
+            refs[q0] += 1;
+            refs[f] += 1;
+        }
+        let mut keep = vec![true; self.delta.len()];
+        let mut machine_a = operands[0];
+        for &[machine_b_q0, machine_b_f] in operands[1..].iter() {
+            let [machine_a_q0, machine_a_f] = machine_a;
+            let spliceable = self.delta[machine_a_f].1 == [None, None]
+                && incoming[machine_b_q0] == 0
+                && refs[machine_b_q0] == if machine_b_q0 == machine_b_f { 2 } else { 1 };
+            if spliceable {
+                // 'a' ends where 'b' starts
+                self.delta[machine_a_f] = self.delta[machine_b_q0].clone();
+                keep[machine_b_q0] = false;
+                machine_a = if machine_b_q0 == machine_b_f {
+                    machine_a
+                } else {
+                    [machine_a_q0, machine_b_f]
+                };
+            } else {
+                self.automata_refs.push(machine_a);
+                self.automata_refs.push([machine_b_q0, machine_b_f]);
+                ForwardCompiler::concatenate(self)?;
+                // concatenating never fails with two operands
+                machine_a = self.automata_refs.pop().unwrap_or(machine_a);
+            }
+        }
+        self.automata_refs.push(machine_a);
+        if keep.contains(&false) {
+            keep.resize(self.delta.len(), true);
+            self.retain_states(&keep);
+        }
+        Ok(())
+    }
+
+    /// Pushes a copy of the top machine, e.g. so it can be repeated.
+    ///
+    /// Every state reachable from `q0` is copied after the existing states, so
+    /// the copy shares no state with the original.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// machine.duplicate_top().unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert!(machine.is_match("aa"));
+    /// ```
+    pub fn duplicate_top(&mut self) -> Result<(), CompileError> {
+        let machine_a = match self.automata_refs.last() {
+            None => {
+                return Err(CompileError::Invalid("Duplicating requires one operand."));
+            }
+            Some(machine_a) => *machine_a,
+        };
+        let copy = self.copy_machine(machine_a);
+        self.automata_refs.push(copy);
+        Ok(())
+    }
+
+    /// Returns a new machine for `machine_a ⋅ machine_b`, leaving the stack untouched.
+    ///
+    /// Both operands are copied first, so they may be anywhere on the stack, or
+    /// not on it at all, and stay usable afterwards. Like `ForwardCompiler::concatenate`,
+    /// the transition of the copy of `machine_a_f` is replaced.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// let machine_a = machine.automata_refs[0];
+    /// let machine_ab = machine.concatenate_refs(machine_a, machine_a).unwrap();
+    /// machine.automata_refs.push(machine_ab);
+    /// assert!(machine.is_match("aa"));
+    /// ```
+    pub fn concatenate_refs(
+        &mut self,
+        machine_a: AutomataRef,
+        machine_b: AutomataRef,
+    ) -> Result<AutomataRef, CompileError> {
+        self.check_states(&machine_a)?;
+        self.check_states(&machine_b)?;
+        let [machine_a_q0, machine_a_f] = self.copy_machine(machine_a);
+        let [machine_b_q0, machine_b_f] = self.copy_machine(machine_b);
+        // point machine_a at machine_b
+        self.delta[machine_a_f] = transition::epsilon(machine_b_q0);
+        Ok([machine_a_q0, machine_b_f])
+    }
+
+    /// Returns a new machine for `machine_a ∪ machine_b`, leaving the stack untouched.
+    ///
+    /// Both operands are copied first, see `ANFA::concatenate_refs`.
+    pub fn union_refs(
+        &mut self,
+        machine_a: AutomataRef,
+        machine_b: AutomataRef,
+    ) -> Result<AutomataRef, CompileError> {
+        self.check_states(&machine_a)?;
+        self.check_states(&machine_b)?;
+        let [machine_a_q0, machine_a_f] = self.copy_machine(machine_a);
+        let [machine_b_q0, machine_b_f] = self.copy_machine(machine_b);
+        let machine_c_q0 = self.delta.len();
+        let machine_c_f = machine_c_q0 + 1;
+        // push union transition
+        self.delta
+            .push(transition::union([Some(machine_a_q0), Some(machine_b_q0)])?);
+        // push final state
+        self.delta.push(transition::nothing());
+        // point machine_a and machine_b at machine_c
+        self.delta[machine_a_f] = transition::epsilon(machine_c_f);
+        self.delta[machine_b_f] = transition::epsilon(machine_c_f);
+        Ok([machine_c_q0, machine_c_f])
+    }
+
+    /// Copies the states reachable from `q0` of `machine`, and its `f`, after the
+    /// existing states. Returns the copy.
+    pub(crate) fn copy_machine(&mut self, machine: AutomataRef) -> AutomataRef {
+        let states = self.copied_states(machine);
+        self.copy_states(&states, machine)
+    }
+
+    /// Returns the sorted states `ANFA::copy_machine` copies.
+    pub(crate) fn copied_states(&self, machine: AutomataRef) -> vec::Vec<QId> {
+        let [machine_q0, machine_f] = machine;
+        let mut reachable = self.reachable_from(machine_q0);
+        reachable[machine_f] = true;
+        reachable
+            .iter()
+            .enumerate()
+            .filter(|(_, &r)| r)
+            .map(|(q, _)| q)
+            .collect()
+    }
+
+    /// Copies the sorted `states` of `machine` after the existing states, so one
+    /// set of states can be copied many times. Returns the copy.
+    pub(crate) fn copy_states(&mut self, states: &[QId], machine: AutomataRef) -> AutomataRef {
+        let offset = self.delta.len();
+        let copy = |q: QId| states.binary_search(&q).ok().map(|i| offset + i);
+        for &q in states {
+            let (label, targets) = self.delta[q].clone();
+            self.delta
+                .push((label, targets.map(|target| target.and_then(copy))));
+        }
+        machine.map(|q| copy(q).unwrap_or(q))
+    }
+
+    /// Pops the top two machines and pushes their intersection, i.e. `a ∩ b`
+    ///
+    /// Both machines are determinized and combined by the product construction
+    /// over the union of their alphabets. A char only one machine transitions on
+    /// leads the other machine to a dead state, so machines over disjoint
+    /// alphabets intersect to the empty language, or to epsilon when both accept
+    /// the empty string. The operands' states are left in `delta`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("(a|b)*").unwrap().merge(parse("a*b").unwrap());
+    /// machine.intersection().unwrap();
+    /// assert!(machine.is_match("aab"));
+    /// assert!(!machine.is_match("ba"));
+    /// ```
+    pub fn intersection(&mut self) -> Result<(), CompileError> {
+        if self.automata_refs.len() < 2 {
+            return Err(CompileError::Invalid("Intersection requires two operands."));
+        }
+        let machine_b = self.automata_refs.pop();
+        let machine_a = self.automata_refs.pop();
+        let product = self
+            .machine_to_dfa(machine_a)
+            .product(&self.machine_to_dfa(machine_b), |a, b| a && b);
+        self.push_dfa(&product);
+        Ok(())
+    }
+
+    /// Pops the top two machines and pushes their difference, i.e. `a \ b`
+    ///
+    /// The result accepts the strings `a` accepts and `b` rejects. Like
+    /// `ANFA::intersection`, both machines are determinized and combined by the
+    /// product construction. The DFAs are complete, so `b` is complemented over
+    /// every char, not only its own alphabet. The operands' states are left in `delta`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// // identifiers except the keyword "if"
+    /// let mut machine = parse("(i|f|x)*").unwrap().merge(parse("if").unwrap());
+    /// machine.difference().unwrap();
+    /// assert!(machine.is_match("fix"));
+    /// assert!(!machine.is_match("if"));
+    /// ```
+    pub fn difference(&mut self) -> Result<(), CompileError> {
+        if self.automata_refs.len() < 2 {
+            return Err(CompileError::Invalid("Difference requires two operands."));
+        }
+        let machine_b = self.automata_refs.pop();
+        let machine_a = self.automata_refs.pop();
+        let product = self
+            .machine_to_dfa(machine_a)
+            .product(&self.machine_to_dfa(machine_b), |a, b| a && !b);
+        self.push_dfa(&product);
+        Ok(())
+    }
+
+    /// Pops the top two machines and pushes their symmetric difference, i.e.
+    /// `(a \ b) ∪ (b \ a)`
+    ///
+    /// The result accepts exactly the strings that distinguish `a` from `b`, so it
+    /// is empty when `ANFA::equivalent` holds, and `ANFA::iter_accepting_paths`
+    /// lists the distinguishing strings when it does not. Like `ANFA::difference`,
+    /// both machines are combined by the product construction. The operands'
+    /// states are left in `delta`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("a|b").unwrap().merge(parse("b|c").unwrap());
+    /// machine.symmetric_difference().unwrap();
+    /// assert!(machine.is_match("a"));
+    /// assert!(machine.is_match("c"));
+    /// assert!(!machine.is_match("b"));
+    /// ```
+    pub fn symmetric_difference(&mut self) -> Result<(), CompileError> {
+        if self.automata_refs.len() < 2 {
+            return Err(CompileError::Invalid(
+                "Symmetric difference requires two operands.",
+            ));
+        }
+        let machine_b = self.automata_refs.pop();
+        let machine_a = self.automata_refs.pop();
+        let product = self
+            .machine_to_dfa(machine_a)
+            .product(&self.machine_to_dfa(machine_b), |a, b| a != b);
+        self.push_dfa(&product);
+        Ok(())
+    }
+
+    /// Replaces the top machine with its complement over every char, i.e. `¬a`
+    ///
+    /// The machine is determinized, every state without an `otherwise`
+    /// transition is sent to a rejecting sink, and acceptance is flipped. Chars
+    /// outside the alphabet take the `otherwise` edge, a negated class of the
+    /// alphabet, so no char needs enumerating. A class is used rather than
+    /// `Label::Any`, which does not match `'\n'` by default. The operand's states
+    /// are left in `delta`.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("a*").unwrap();
+    /// machine.complement_universal().unwrap();
+    /// assert!(machine.is_match("ab"));
+    /// assert!(!machine.is_match("aa"));
+    /// ```
+    pub fn complement_universal(&mut self) -> Result<(), CompileError> {
+        let machine_a = match self.automata_refs.pop() {
+            None => return Err(CompileError::Invalid("Complement requires one operand.")),
+            machine_a => machine_a,
+        };
+        let mut dfa = self.machine_to_dfa(machine_a);
+        let sink = dfa.accepting.len();
+        if dfa.otherwise.contains(&None) {
+            dfa.transitions.push(BTreeMap::new());
+            dfa.otherwise.push(Some(sink));
+            dfa.accepting.push(false);
+            for otherwise in dfa.otherwise.iter_mut() {
+                otherwise.get_or_insert(sink);
+            }
+        }
+        for accepting in dfa.accepting.iter_mut() {
+            *accepting = !*accepting;
+        }
+        self.push_dfa(&dfa);
+        Ok(())
+    }
+
+    /// Pushes the union of `operands`, splitting them in half at every level.
+    fn union_balanced(&mut self, operands: &[AutomataRef]) -> Result<(), CompileError> {
+        if operands.len() == 1 {
+            self.automata_refs.push(operands[0]);
+            return Ok(());
+        }
+        let (left, right) = operands.split_at(operands.len() / 2);
+        self.union_balanced(left)?;
+        self.union_balanced(right)?;
+        ForwardCompiler::union(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::error::CompileError;
+    use crate::parser::parse;
+    use crate::{transition, Label};
+    use alloc::vec;
+
+    #[test]
+    fn test_union_all_balanced() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        machine.union_all_balanced(3).unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Union of three machines leaves one machine"
+        );
+        assert_eq!(
+            machine.delta.len(),
+            10,
+            "Two binary unions push four states"
+        );
+        assert!(machine.is_match("a"), "a|b|c matches 'a'");
+        assert!(machine.is_match("b"), "a|b|c matches 'b'");
+        assert!(machine.is_match("c"), "a|b|c matches 'c'");
+        assert!(!machine.is_match("d"), "a|b|c does not match 'd'");
+        assert!(!machine.is_match(""), "a|b|c does not match ''");
+    }
+
+    #[test]
+    fn test_union_all_leaves_lower_machines() {
+        let mut machine = ForwardCompiler::from_expr_a('x').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        machine.union_all_balanced(2).unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            2,
+            "Only the top count machines are consumed"
+        );
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("xb"), "x(a|b) matches 'xb'");
+        machine.union_all_balanced(1).unwrap();
+        assert!(machine.is_match("xa"), "Union of one machine is itself");
+    }
+
+    #[test]
+    fn test_union_all_operands() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        assert!(
+            machine.union_all_balanced(3).is_err(),
+            "Union of three machines requires three operands"
+        );
+        assert!(
+            machine.union_all_balanced(0).is_err(),
+            "Union requires at least one operand"
+        );
+        assert_eq!(
+            machine.automata_refs.len(),
+            2,
+            "Failed unions leave the stack untouched"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let machine_a = ForwardCompiler::from_expr_a('a').unwrap();
+        let mut machine_b = ForwardCompiler::from_expr_a('b').unwrap();
+        ForwardCompiler::star(&mut machine_b).unwrap();
+        let mut machine = machine_a.merge(machine_b);
+        assert_eq!(machine.delta.len(), 7, "Merge keeps every state");
+        assert_eq!(machine.automata_refs, [[0, 1], [4, 6]], "Refs are shifted");
+        assert_eq!(
+            machine.delta[5].1,
+            [Some(2), Some(6)],
+            "Targets are shifted"
+        );
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert!(machine.is_match("a"), "Merged union accepts 'a'");
+        assert!(machine.is_match("bbb"), "Merged union accepts 'b'*");
+        assert!(!machine.is_match("ab"), "Merged union rejects 'ab'");
+    }
+
+    #[test]
+    fn test_concat_into_existing() {
+        // RE ax* where f has a residual transition along 'x' to itself
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        let [_machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        machine.delta[machine_a_f] = (Label::Char('x'), [Some(machine_a_f), None]);
+        assert!(machine.is_match("axx"), "Residual transition accepts 'axx'");
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        machine.concat_into_existing().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Concatenation leaves one machine"
+        );
+        assert!(
+            machine.equivalent(&parse("ax*b").unwrap()),
+            "Residual transition is preserved, i.e. ax*b"
+        );
+        assert!(machine.is_match("axxb"), "ax*b accepts 'axxb'");
+        assert!(!machine.is_match("ax"), "ax*b rejects 'ax'");
+    }
+
+    #[test]
+    fn test_concat_into_existing_operands() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert!(
+            machine.concat_into_existing().is_err(),
+            "Concatenation requires two operands"
+        );
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        machine.concat_into_existing().unwrap();
+        assert!(
+            machine.equivalent(&parse("ab").unwrap()),
+            "Without residual transitions it is plain concatenation"
+        );
+    }
+
+    #[test]
+    fn test_concatenate_or_push_epsilon() {
+        let mut machine = parse("ab").unwrap();
+        let delta = machine.delta.clone();
+        let automata_refs = machine.automata_refs.clone();
+        machine.concatenate_or_push_epsilon().unwrap();
+        assert_eq!(machine.delta, delta, "One machine keeps its states");
+        assert_eq!(
+            machine.automata_refs, automata_refs,
+            "One machine is concatenated with epsilon"
+        );
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        machine.concatenate_or_push_epsilon().unwrap();
+        assert!(machine.is_match("abc"), "Two machines are concatenated");
+        machine.automata_refs.clear();
+        machine.concatenate_or_push_epsilon().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "An empty stack becomes one machine"
+        );
+        assert!(machine.is_match(""), "An empty stack becomes epsilon");
+    }
+
+    #[test]
+    fn test_concat_reuse() {
+        let mut naive = ForwardCompiler::from_expr_a('a').unwrap();
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        for c in ['b', 'c', 'd', 'e'] {
+            ForwardCompiler::expr_a(&mut naive, c).unwrap();
+            ForwardCompiler::concatenate(&mut naive).unwrap();
+            ForwardCompiler::expr_a(&mut machine, c).unwrap();
+        }
+        machine.concat_reuse(5).unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Concatenation leaves one machine"
+        );
+        assert_eq!(machine.delta.len(), 6, "Five chars need six states");
+        assert!(
+            machine.delta.len() < naive.delta.len(),
+            "Splicing drops the epsilon bridges"
+        );
+        assert!(
+            machine.equivalent(&parse("abcde").unwrap()),
+            "Splicing preserves the language"
+        );
+    }
+
+    #[test]
+    fn test_concat_reuse_fallback() {
+        let mut machine = parse("a").unwrap().merge(parse("b*").unwrap());
+        // start b* at its loop state, which has incoming edges
+        machine.automata_refs[1][0] = machine.delta.iter().position(transition::is_union).unwrap();
+        ForwardCompiler::expr_1(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        machine.concat_reuse(4).unwrap();
+        assert!(
+            machine.equivalent(&parse("ab*c").unwrap()),
+            "Unspliceable pairs are concatenated"
+        );
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        machine.concat_reuse(2).unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            2,
+            "Machines below the operands are kept"
+        );
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        assert!(machine.is_match("abc"), "Renumbered machines still match");
+        assert!(
+            machine.concat_reuse(2).is_err(),
+            "Concatenation requires count operands"
+        );
+    }
+
+    #[test]
+    fn test_intersection() {
+        let mut machine = parse("(a|b)*").unwrap().merge(parse("(b|c)*").unwrap());
+        machine.intersection().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Intersection leaves one machine"
+        );
+        assert!(
+            machine.equivalent(&parse("b*").unwrap()),
+            "Shared chars remain"
+        );
+        let mut machine = parse(".").unwrap().merge(parse("a|😀").unwrap());
+        machine.intersection().unwrap();
+        assert!(
+            machine.is_match("😀"),
+            "'.' matches chars outside its alphabet"
+        );
+        assert!(!machine.is_match("b"), "Explicit chars must match both");
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert!(
+            machine.intersection().is_err(),
+            "Intersection requires two operands"
+        );
+    }
+
+    #[test]
+    fn test_intersection_disjoint_alphabets() {
+        let mut machine = parse("a").unwrap().merge(parse("b").unwrap());
+        machine.intersection().unwrap();
+        assert!(machine.is_empty_language(), "a ∩ b is empty");
+        assert!(!machine.is_match(""), "Empty intersection rejects ''");
+        let mut machine = parse("a*").unwrap().merge(parse("b*").unwrap());
+        machine.intersection().unwrap();
+        assert!(!machine.is_empty_language(), "a* ∩ b* is not empty");
+        assert!(machine.is_match(""), "a* ∩ b* accepts ''");
+        assert!(
+            machine.equivalent(&parse("()").unwrap()),
+            "a* ∩ b* is exactly epsilon"
+        );
+    }
+
+    #[test]
+    fn test_concatenate_refs() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        let stack = machine.automata_refs.clone();
+        let [machine_a, _, machine_c] = [stack[0], stack[1], stack[2]];
+        let machine_ac = machine.concatenate_refs(machine_a, machine_c).unwrap();
+        assert_eq!(machine.automata_refs, stack, "The stack is untouched");
+        machine.automata_refs.push(machine_ac);
+        assert!(
+            machine.equivalent(&parse("ac").unwrap()),
+            "Non-adjacent machines are concatenated"
+        );
+        machine.automata_refs.pop();
+        machine.automata_refs.truncate(1);
+        assert!(machine.is_match("a"), "Operands are unchanged");
+        assert!(!machine.is_match("ac"), "Operands are unchanged");
+    }
+
+    #[test]
+    fn test_union_refs() {
+        let mut machine = parse("ab").unwrap().merge(parse("c*").unwrap());
+        ForwardCompiler::expr_a(&mut machine, 'd').unwrap();
+        let [machine_a, machine_b] = [machine.automata_refs[0], machine.automata_refs[1]];
+        let machine_c = machine.union_refs(machine_a, machine_b).unwrap();
+        assert_eq!(machine.automata_refs.len(), 3, "The stack is untouched");
+        machine.automata_refs.push(machine_c);
+        assert!(
+            machine.equivalent(&parse("ab|c*").unwrap()),
+            "Machines are unioned by ref"
+        );
+        assert_eq!(
+            machine.union_refs(machine_a, [0, 99]),
+            Err(CompileError::StateOutOfBounds(99)),
+            "Refs must exist"
+        );
+    }
+
+    #[test]
+    fn test_difference() {
+        let mut machine = parse("a*").unwrap().merge(parse("aa").unwrap());
+        machine.difference().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Difference leaves one machine"
+        );
+        for input in ["", "a", "aaa"] {
+            assert!(machine.is_match(input), "a* \\ aa accepts {:?}", input);
+        }
+        assert!(!machine.is_match("aa"), "a* \\ aa rejects 'aa'");
+        assert!(!machine.is_match("b"), "a* \\ aa rejects chars outside a*");
+        let mut machine = parse("a").unwrap().merge(parse("b*").unwrap());
+        machine.difference().unwrap();
+        assert!(
+            machine.equivalent(&parse("a").unwrap()),
+            "Difference of disjoint languages is the first language"
+        );
+        assert!(
+            parse("a").unwrap().difference().is_err(),
+            "Difference requires two operands"
+        );
+    }
+
+    #[test]
+    fn test_symmetric_difference() {
+        let mut machine = parse("a*").unwrap().merge(parse("a+").unwrap());
+        machine.symmetric_difference().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Symmetric difference leaves one machine"
+        );
+        let strings: vec::Vec<_> = machine.iter_accepting_paths(4).map(|(s, _)| s).collect();
+        assert_eq!(strings, [""], "Only '' distinguishes a* from a+");
+        let mut machine = parse("a|b").unwrap().merge(parse("b|a").unwrap());
+        machine.symmetric_difference().unwrap();
+        assert!(
+            machine.is_empty_language(),
+            "Equivalent machines have no distinguishing string"
+        );
+        assert!(
+            parse("a").unwrap().symmetric_difference().is_err(),
+            "Symmetric difference requires two operands"
+        );
+    }
+
+    #[test]
+    fn test_complement_universal() {
+        let mut machine = parse("a").unwrap();
+        machine.complement_universal().unwrap();
+        for input in ["b", "😀", "", "aa", "\n"] {
+            assert!(machine.is_match(input), "¬a accepts {:?}", input);
+        }
+        assert!(!machine.is_match("a"), "¬a rejects 'a'");
+        machine.complement_universal().unwrap();
+        assert!(machine.equivalent(&parse("a").unwrap()), "¬¬a is a");
+        let mut machine = parse("a").unwrap();
+        machine.automata_refs.clear();
+        assert!(
+            machine.complement_universal().is_err(),
+            "Complement requires one operand"
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use crate::compilers::CompilerExt;
+use crate::error::CompileError;
+use crate::{AnchorKind, AutomataRef, ANFA};
+use alloc::vec;
+use core::iter::Peekable;
+use core::str::Chars;
+
+/// Returns true when `c` must be escaped with `\` to be matched literally.
+pub fn is_meta(c: char) -> bool {
+    matches!(
+        c,
+        '\\' | '|' | '(' | ')' | '*' | '.' | '[' | ']' | '+' | '?' | '{' | '}' | '^' | '$'
+    )
+}
+
+/// Compiles a regular expression with `ForwardCompiler`.
+///
+/// ```text
+/// union  := concat ('|' concat)*
+/// concat := repeat*
+/// repeat := atom ('*' | '+' | '?')*
+/// atom   := char | '\' char | '.' | '^' | '$' | '(' union ')' | '[]'
+/// ```
+///
+/// `^` and `$` match the empty string at the start and end of the input.
+///
+/// An empty expression, e.g. `()` or either side of `a|`, is epsilon.
+/// `[]` is the empty class, which accepts nothing.
+///
+/// ```rust
+/// use regexxx::parser::parse;
+/// let machine = parse("a(b|c)*d").unwrap();
+/// assert!(machine.is_match("abccbd"));
+/// ```
+pub fn parse(pattern: &str) -> Result<ANFA, CompileError> {
+    parse_groups(pattern).map(|(anfa, _groups)| anfa)
+}
+
+/// Parses like `parse`, and also returns the machines of each group, numbered
+/// from 1 by their open parenthesis.
+///
+/// A group has more than one machine when `+` copies it, and each copy accepts
+/// the group. Every group machine is inside `delta`, but a group discarded by
+/// a simplification, e.g. the second group of `(a)|(a)`, is unreachable from
+/// the parsed machine and never matches.
+pub(crate) fn parse_groups(
+    pattern: &str,
+) -> Result<(ANFA, vec::Vec<vec::Vec<AutomataRef>>), CompileError> {
+    // literals and operators each push two states, and concatenation holds two machines
+    let mut anfa = ANFA::with_capacity(2 * pattern.len(), 2);
+    let mut parser = Parser {
+        chars: pattern.chars().peekable(),
+        anfa: &mut anfa,
+        groups: vec::Vec::new(),
+    };
+    parser.union()?;
+    if parser.chars.next().is_some() {
+        return Err(CompileError::Invalid("Unbalanced parenthesis."));
+    }
+    let groups = parser.groups;
+    Ok((anfa, groups))
+}
+
+struct Parser<'a, 'b> {
+    chars: Peekable<Chars<'a>>,
+    anfa: &'b mut ANFA,
+    groups: vec::Vec<vec::Vec<AutomataRef>>,
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    /// Pushes one machine for an alternation
+    fn union(&mut self) -> Result<(), CompileError> {
+        self.concat()?;
+        while self.chars.peek() == Some(&'|') {
+            self.chars.next();
+            self.concat()?;
+            ForwardCompiler::union(self.anfa)?;
+        }
+        Ok(())
+    }
+
+    /// Pushes one machine for a sequence, or epsilon when the sequence is empty
+    fn concat(&mut self) -> Result<(), CompileError> {
+        let mut operands = 0;
+        while let Some(&c) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            self.repeat()?;
+            operands += 1;
+            if operands > 1 {
+                ForwardCompiler::concatenate(self.anfa)?;
+            }
+        }
+        if operands == 0 {
+            ForwardCompiler::expr_1(self.anfa)?;
+        }
+        Ok(())
+    }
+
+    /// Pushes one machine for an atom and its repetitions
+    fn repeat(&mut self) -> Result<(), CompileError> {
+        self.atom()?;
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '*' => ForwardCompiler::star(self.anfa)?,
+                '+' => self.plus()?,
+                '?' => ForwardCompiler::optional(self.anfa)?,
+                _ => break,
+            }
+            self.chars.next();
+        }
+        Ok(())
+    }
+
+    /// Pushes `a+ = a ⋅ a*` like `CompilerExt::plus`, and records the copies of
+    /// the groups inside `a`
+    fn plus(&mut self) -> Result<(), CompileError> {
+        let machine_a = self
+            .anfa
+            .top_machine()
+            .ok_or(CompileError::Invalid("Plus requires one operand."))?;
+        let states = self.anfa.copied_states(machine_a);
+        // the copy of states[i] is offset + i
+        let offset = self.anfa.delta.len();
+        let copy = self.anfa.copy_states(&states, machine_a);
+        self.anfa.automata_refs.push(copy);
+        for machines in self.groups.iter_mut() {
+            let copies: vec::Vec<AutomataRef> = machines
+                .iter()
+                .filter_map(|&[q0, f]| {
+                    let q0 = states.binary_search(&q0).ok()?;
+                    let f = states.binary_search(&f).ok()?;
+                    Some([offset + q0, offset + f])
+                })
+                .collect();
+            machines.extend(copies);
+        }
+        ForwardCompiler::star(self.anfa)?;
+        ForwardCompiler::concatenate(self.anfa)
+    }
+
+    /// Pushes one machine for a char, wildcard, anchor, group, or empty class
+    fn atom(&mut self) -> Result<(), CompileError> {
+        match self.chars.next() {
+            None => Err(CompileError::Invalid("Expected an expression.")),
+            Some('(') => {
+                let group = self.groups.len();
+                self.groups.push(vec::Vec::new());
+                self.union()?;
+                match self.chars.next() {
+                    Some(')') => {
+                        let mut machine = self.anfa.top_machine();
+                        if let Some([q0, f]) = machine.filter(|[q0, f]| q0 == f) {
+                            // an empty group gets its own f, so concatenation cannot drop it
+                            let group_f = self.anfa.push_state();
+                            self.anfa.set_epsilon(f, group_f)?;
+                            self.anfa.automata_refs.pop();
+                            self.anfa.mark_machine(q0, group_f)?;
+                            machine = Some([q0, group_f]);
+                        }
+                        self.groups[group].extend(machine);
+                        Ok(())
+                    }
+                    _ => Err(CompileError::Invalid("Unbalanced parenthesis.")),
+                }
+            }
+            Some('[') => match self.chars.next() {
+                Some(']') => ForwardCompiler::expr_0(self.anfa),
+                _ => Err(CompileError::Invalid("Only the empty class is supported.")),
+            },
+            Some('.') => ForwardCompiler::expr_any(self.anfa),
+            Some('^') => ForwardCompiler::expr_anchor(self.anfa, AnchorKind::Start),
+            Some('$') => ForwardCompiler::expr_anchor(self.anfa, AnchorKind::End),
+            Some('\\') => match self.chars.next() {
+                None => Err(CompileError::Invalid("Dangling escape.")),
+                Some(c) => ForwardCompiler::expr_a(self.anfa, c),
+            },
+            Some('*') => Err(CompileError::Invalid("Star requires one operand.")),
+            Some('+') => Err(CompileError::Invalid("Plus requires one operand.")),
+            Some('?') => Err(CompileError::Invalid("Optional requires one operand.")),
+            Some(c) if is_meta(c) => Err(CompileError::Invalid("Unescaped meta character.")),
+            Some(c) => ForwardCompiler::expr_a(self.anfa, c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, parse_groups};
+
+    #[test]
+    fn test_parse() {
+        let machine = parse("a(b|c)*d").unwrap();
+        assert_eq!(machine.automata_refs.len(), 1, "Parsing leaves one machine");
+        assert!(machine.is_match("ad"), "a(b|c)*d matches 'ad'");
+        assert!(machine.is_match("abcbd"), "a(b|c)*d matches 'abcbd'");
+        assert!(!machine.is_match("abc"), "a(b|c)*d rejects 'abc'");
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let machine = parse("ab|cd*").unwrap();
+        assert!(machine.is_match("ab"), "Union binds loosest");
+        assert!(machine.is_match("cddd"), "Star binds tightest");
+        assert!(!machine.is_match("abd"), "Star only repeats its atom");
+    }
+
+    #[test]
+    fn test_parse_epsilon_and_nothing() {
+        let machine = parse("a|").unwrap();
+        assert!(machine.is_match(""), "Empty alternative is epsilon");
+        assert!(machine.is_match("a"), "Non-empty alternative still matches");
+        let machine = parse("()").unwrap();
+        assert!(machine.is_match(""), "Empty group is epsilon");
+        let machine = parse("[]|a").unwrap();
+        assert!(!machine.is_match(""), "Empty class accepts nothing");
+        assert!(machine.is_match("a"), "Union with nothing is unchanged");
+    }
+
+    #[test]
+    fn test_parse_escapes() {
+        let machine = parse("\\(\\*\\).").unwrap();
+        assert!(
+            machine.is_match("(*)x"),
+            "Escaped meta characters are literal"
+        );
+        assert!(!machine.is_match("(*)"), "Unescaped '.' matches one char");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("(a").is_err(), "Missing close parenthesis");
+        assert!(parse("a)").is_err(), "Missing open parenthesis");
+        assert!(parse("*a").is_err(), "Star without operand");
+        assert!(parse("a\\").is_err(), "Dangling escape");
+        assert!(parse("+a").is_err(), "Plus without operand");
+        assert!(parse("a{2}").is_err(), "Unsupported meta character");
+    }
+
+    #[test]
+    fn test_parse_anchors() {
+        let machine = parse("^a$").unwrap();
+        assert!(machine.is_match("a"), "Anchors parse");
+        assert!(
+            parse("\\^\\$").unwrap().is_match("^$"),
+            "Escaped anchors are literal"
+        );
+    }
+
+    #[test]
+    fn test_parse_plus_and_optional() {
+        let machine = parse("ab+c?").unwrap();
+        for input in ["ab", "abbb", "abc"] {
+            assert!(machine.is_match(input), "ab+c? accepts each repetition");
+        }
+        for input in ["a", "ac", "abcc"] {
+            assert!(
+                !machine.is_match(input),
+                "ab+c? rejects missing or extra repetitions"
+            );
+        }
+        assert!(
+            parse("(ab)+")
+                .unwrap()
+                .equivalent(&parse("ab(ab)*").unwrap()),
+            "Plus repeats a group"
+        );
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        let (machine, groups) = parse_groups("((a)|b)+").unwrap();
+        assert_eq!(groups.len(), 2, "Groups are numbered by open parenthesis");
+        assert_eq!(groups[0].len(), 2, "Plus copies the outer group");
+        assert_eq!(groups[1].len(), 2, "Plus copies the inner group");
+        for &[q0, f] in groups.iter().flatten() {
+            assert!(
+                machine.reachable_from(q0)[f],
+                "Each group machine reaches its f"
+            );
+        }
+    }
+}
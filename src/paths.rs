@@ -0,0 +1,184 @@
+use crate::{transition, AnchorKind, Label, QId, ANFA};
+use alloc::string::String;
+use alloc::vec;
+
+/// Iterator over the accepting paths of the top machine. See `ANFA::iter_accepting_paths`.
+#[derive(Debug)]
+pub struct AcceptingPaths<'a> {
+    anfa: &'a ANFA,
+    f: QId,
+    max_len: usize,
+    stack: vec::Vec<Path>,
+}
+
+/// A partial path: the states visited, the input consumed, and where in `states`
+/// the last char was consumed.
+#[derive(Debug)]
+struct Path {
+    states: vec::Vec<QId>,
+    input: String,
+    len: usize,
+    last_char: usize,
+    at_end: bool,
+}
+
+impl ANFA {
+    /// Returns every path from `q0` to `f` of the top machine consuming at most
+    /// `max_len` chars, as the input it consumes and the states it visits.
+    ///
+    /// Paths are explored depth first, preferring the first target of a union, so
+    /// a string is yielded once per path accepting it. A path never revisits a state
+    /// without consuming a char, which bounds epsilon cycles. `Any` and classes
+    /// consume one representative char, the lowest printable ASCII char they match,
+    /// or for a negated class without one, the first char outside its ranges.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("ab").unwrap();
+    /// let paths: Vec<_> = machine.iter_accepting_paths(2).collect();
+    /// assert_eq!(paths.len(), 1);
+    /// assert_eq!(paths[0].0, "ab");
+    /// assert_eq!(paths[0].1.first(), machine.q0().as_ref());
+    /// ```
+    pub fn iter_accepting_paths(&self, max_len: usize) -> AcceptingPaths<'_> {
+        let mut stack = vec::Vec::new();
+        let f = match self.top_machine() {
+            Some([q0, f]) => {
+                stack.push(Path {
+                    states: vec![q0],
+                    input: String::new(),
+                    len: 0,
+                    last_char: 0,
+                    at_end: false,
+                });
+                f
+            }
+            None => 0,
+        };
+        AcceptingPaths {
+            anfa: self,
+            f,
+            max_len,
+            stack,
+        }
+    }
+}
+
+impl<'a> Iterator for AcceptingPaths<'a> {
+    type Item = (String, vec::Vec<QId>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(path) = self.stack.pop() {
+            let q = *path.states.last()?;
+            let transition = &self.anfa.delta[q];
+            let consumed = match &transition.0 {
+                Label::Epsilon => None,
+                Label::Anchor(AnchorKind::Start) if path.len > 0 => continue,
+                Label::Anchor(_) => None,
+                _ if path.at_end || path.len == self.max_len => continue,
+                label => match witness(label) {
+                    Some(c) => Some(c),
+                    None => continue,
+                },
+            };
+            let at_end = path.at_end || transition.0 == Label::Anchor(AnchorKind::End);
+            let targets: vec::Vec<QId> = transition::targets(transition).collect();
+            // push in reverse so the first target is explored first
+            for &target in targets.iter().rev() {
+                let mut next = Path {
+                    states: path.states.clone(),
+                    input: path.input.clone(),
+                    len: path.len,
+                    last_char: path.last_char,
+                    at_end,
+                };
+                match consumed {
+                    Some(c) => {
+                        next.input.push(c);
+                        next.len += 1;
+                        next.last_char = next.states.len();
+                    }
+                    None if path.states[path.last_char..].contains(&target) => continue,
+                    None => {}
+                }
+                next.states.push(target);
+                self.stack.push(next);
+            }
+            if q == self.f {
+                return Some((path.input, path.states));
+            }
+        }
+        None
+    }
+}
+
+/// Returns a char consumed by `label`, preferring printable ASCII.
+fn witness(label: &Label) -> Option<char> {
+    match label {
+        Label::Char(c) => Some(*c),
+        Label::Class(ranges, false) => ranges.first().map(|&(lo, _hi)| lo),
+        // the lowest char outside every range is '\0' or follows a range
+        Label::Class(ranges, true) => {
+            let after = ranges.iter().filter_map(|&(_lo, hi)| match hi {
+                '\u{d7ff}' => Some('\u{e000}'),
+                hi => char::from_u32(hi as u32 + 1),
+            });
+            (' '..='~')
+                .chain(['\0'])
+                .chain(after)
+                .find(|&c| label.matches(c))
+        }
+        label => (' '..='~').find(|&c| label.matches(c)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::parser::parse;
+    use crate::{Label, ANFA};
+    use alloc::vec;
+
+    #[test]
+    fn test_iter_accepting_paths() {
+        let machine = parse("a|b").unwrap();
+        let paths: vec::Vec<_> = machine.iter_accepting_paths(1).collect();
+        let strings: vec::Vec<&str> = paths.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(strings, ["a", "b"], "a|b accepts a then b");
+        let inner = |path: &[usize]| path[1..path.len() - 1].to_vec();
+        let (a, b) = (inner(&paths[0].1), inner(&paths[1].1));
+        assert!(!a.is_empty(), "The path of a visits intermediate states");
+        assert!(
+            a.iter().all(|q| !b.contains(q)),
+            "The paths of a and b visit disjoint intermediate states"
+        );
+    }
+
+    #[test]
+    fn test_iter_accepting_paths_star() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let strings: vec::Vec<_> = machine.iter_accepting_paths(3).map(|(s, _)| s).collect();
+        assert_eq!(
+            strings,
+            ["aaa", "aa", "a", ""],
+            "a* prefers another a until max_len"
+        );
+        assert_eq!(
+            parse("^.$").unwrap().iter_accepting_paths(4).count(),
+            1,
+            "Nothing is consumed after $"
+        );
+        // RE [^\0-~], every printable ASCII char is excluded
+        let mut machine = ANFA::new();
+        ForwardCompiler::expr_range(&mut machine, ' ', '~').unwrap();
+        let [q0, _f] = machine.automata_refs[0];
+        machine.delta[q0].0 = Label::Class(vec![('\0', '~')], true);
+        let strings: vec::Vec<_> = machine.iter_accepting_paths(1).map(|(s, _)| s).collect();
+        assert_eq!(
+            strings,
+            ["\u{7f}"],
+            "A negated class consumes a char after its ranges"
+        );
+    }
+}
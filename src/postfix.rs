@@ -0,0 +1,191 @@
+use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+use crate::compilers::CompilerExt;
+use crate::error::CompileError;
+use crate::ANFA;
+use alloc::vec;
+
+/// One step of a postfix expression. See `ANFA::compile_postfix`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token {
+    /// Pushes the machine accepting exactly this char
+    Char(char),
+    /// Pushes the machine accepting only the empty string
+    Epsilon,
+    /// Pushes the machine accepting nothing
+    Empty,
+    /// Pops `b` then `a` and pushes `ab`
+    Concat,
+    /// Pops `b` then `a` and pushes `a|b`
+    Union,
+    /// Pops `a` and pushes `a*`
+    Star,
+    /// Pops `a` and pushes `a+`
+    Plus,
+    /// Pops `a` and pushes `a?`
+    Optional,
+}
+
+impl Token {
+    /// Returns the number of machines the token pops.
+    pub fn operands(&self) -> usize {
+        match self {
+            Token::Char(_) | Token::Epsilon | Token::Empty => 0,
+            Token::Star | Token::Plus | Token::Optional => 1,
+            Token::Concat | Token::Union => 2,
+        }
+    }
+}
+
+impl ANFA {
+    /// Compiles a postfix expression with `ForwardCompiler`.
+    ///
+    /// Each token pushes a machine or replaces the machines it pops, so `a(b|c)`
+    /// is written `a b c | ·`. Fails when a token has too few operands, or with
+    /// `CompileError::UnbalancedStack` when the tokens do not leave exactly one machine.
+    ///
+    /// ```rust
+    /// use regexxx::postfix::Token;
+    /// use regexxx::ANFA;
+    /// let machine = ANFA::compile_postfix(&[
+    ///     Token::Char('a'),
+    ///     Token::Char('b'),
+    ///     Token::Union,
+    ///     Token::Star,
+    /// ])
+    /// .unwrap();
+    /// assert!(machine.is_match("abba"));
+    /// ```
+    pub fn compile_postfix(tokens: &[Token]) -> Result<ANFA, CompileError> {
+        let mut anfa = ANFA::with_capacity(2 * tokens.len(), 2);
+        for token in tokens.iter() {
+            if anfa.automata_refs.len() < token.operands() {
+                return Err(CompileError::Invalid(
+                    "Postfix operator has too few operands.",
+                ));
+            }
+            match token {
+                Token::Char(c) => ForwardCompiler::expr_a(&mut anfa, *c),
+                Token::Epsilon => ForwardCompiler::expr_1(&mut anfa),
+                Token::Empty => ForwardCompiler::expr_0(&mut anfa),
+                Token::Concat => ForwardCompiler::concatenate(&mut anfa),
+                Token::Union => ForwardCompiler::union(&mut anfa),
+                Token::Star => ForwardCompiler::star(&mut anfa),
+                Token::Plus => ForwardCompiler::plus(&mut anfa),
+                Token::Optional => ForwardCompiler::optional(&mut anfa),
+            }?;
+        }
+        anfa.assert_single_machine()?;
+        Ok(anfa)
+    }
+}
+
+impl ForwardCompiler {
+    /// Compiles a space-separated postfix expression. See `ANFA::compile_postfix`.
+    ///
+    /// A token of one char is that char, and the keywords `concat`, `union`,
+    /// `star`, `plus`, `optional`, `eps`, and `empty` are the matching `Token`.
+    /// Fails on any other token, or when the expression is malformed.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::ForwardCompiler;
+    /// let machine = ForwardCompiler::from_postfix_str("a b concat c star union").unwrap();
+    /// assert!(machine.is_match("ab"));
+    /// assert!(machine.is_match("ccc"));
+    /// ```
+    pub fn from_postfix_str(expression: &str) -> Result<ANFA, CompileError> {
+        let tokens = expression
+            .split_whitespace()
+            .map(|word| {
+                Ok(match word {
+                    "concat" => Token::Concat,
+                    "union" => Token::Union,
+                    "star" => Token::Star,
+                    "plus" => Token::Plus,
+                    "optional" => Token::Optional,
+                    "eps" => Token::Epsilon,
+                    "empty" => Token::Empty,
+                    _ => {
+                        let mut chars = word.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => Token::Char(c),
+                            _ => return Err(CompileError::Invalid("Unknown postfix token.")),
+                        }
+                    }
+                })
+            })
+            .collect::<Result<vec::Vec<Token>, CompileError>>()?;
+        ANFA::compile_postfix(&tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::ForwardCompiler;
+    use crate::parser::parse;
+    use crate::postfix::Token;
+    use crate::ANFA;
+
+    #[test]
+    fn test_compile_postfix() {
+        let machine = ANFA::compile_postfix(&[
+            Token::Char('a'),
+            Token::Char('b'),
+            Token::Char('c'),
+            Token::Union,
+            Token::Star,
+            Token::Concat,
+            Token::Char('d'),
+            Token::Concat,
+        ])
+        .unwrap();
+        assert!(
+            machine.equivalent(&parse("a(b|c)*d").unwrap()),
+            "a b c | * · d · is a(b|c)*d"
+        );
+        assert!(machine.is_match("abcbd"), "a(b|c)*d accepts abcbd");
+    }
+
+    #[test]
+    fn test_compile_postfix_errors() {
+        assert!(
+            ANFA::compile_postfix(&[Token::Char('a'), Token::Union]).is_err(),
+            "Union requires two operands"
+        );
+        assert!(
+            ANFA::compile_postfix(&[Token::Star]).is_err(),
+            "Star requires one operand"
+        );
+        assert!(
+            ANFA::compile_postfix(&[Token::Char('a'), Token::Char('b')]).is_err(),
+            "Two machines are left"
+        );
+        assert!(ANFA::compile_postfix(&[]).is_err(), "No machine is left");
+        let machine = ANFA::compile_postfix(&[Token::Epsilon, Token::Empty, Token::Union]).unwrap();
+        assert!(machine.is_match(""), "ε|∅ accepts ''");
+        assert!(!machine.is_match("a"), "ε|∅ rejects 'a'");
+    }
+
+    #[test]
+    fn test_from_postfix_str() {
+        let machine = ForwardCompiler::from_postfix_str("a b concat").unwrap();
+        assert!(machine.is_match("ab"), "a b concat accepts ab");
+        assert!(!machine.is_match("a"), "a b concat rejects a");
+        let machine = ForwardCompiler::from_postfix_str("a b union").unwrap();
+        assert!(machine.is_match("a"), "a b union accepts a");
+        assert!(machine.is_match("b"), "a b union accepts b");
+        let machine = ForwardCompiler::from_postfix_str("  eps   a plus optional union ").unwrap();
+        assert!(machine.is_match(""), "Extra spaces are ignored");
+        assert!(
+            ForwardCompiler::from_postfix_str("a b cat").is_err(),
+            "Unknown tokens fail"
+        );
+        assert!(
+            ForwardCompiler::from_postfix_str("a concat").is_err(),
+            "Operand underflow fails"
+        );
+        assert!(
+            ForwardCompiler::from_postfix_str("").is_err(),
+            "No machine is left"
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use crate::error::CompileError;
+use crate::matcher::Matcher;
+use crate::parser::parse_groups;
+use crate::{AnchorKind, Label, QId, ANFA};
+use alloc::vec;
+
+/// A finished pattern, ready to match. See `compile`.
+///
+/// The automaton is private, so it cannot change after it is validated.
+#[derive(Debug)]
+pub struct CompiledRegex {
+    anfa: ANFA,
+    /// Number of groups, not counting group 0
+    groups: usize,
+    /// The capture slots set on entering each state. Group `g` starts in slot
+    /// `2 * g` and ends in slot `2 * g + 1`.
+    slots: vec::Vec<vec::Vec<usize>>,
+}
+
+/// Parses `pattern` with `ForwardCompiler`, see `parser::parse`, and finalizes
+/// the single machine it builds.
+///
+/// Finalizing drops unreachable states, releases spare capacity, and checks that
+/// exactly one machine is left and every target exists.
+///
+/// ```rust
+/// let regex = regexxx::compile("a(b|c)*d").unwrap();
+/// assert!(regex.is_match("abccbd"));
+/// assert_eq!(regex.find("xxabdx"), Some((2, 5)));
+/// ```
+pub fn compile(pattern: &str) -> Result<CompiledRegex, CompileError> {
+    let (mut anfa, groups) = parse_groups(pattern)?;
+    anfa.assert_single_machine()?;
+    // group machines sit below the pattern while trimming, so they are renumbered too
+    let top = anfa.automata_refs.pop();
+    let counts: vec::Vec<usize> = groups.iter().map(|machines| machines.len()).collect();
+    anfa.automata_refs = groups.into_iter().flatten().chain(top).collect();
+    anfa.trim_unreachable_from_q0();
+    anfa.delta.shrink_to_fit();
+    let top = anfa.automata_refs.pop();
+    let mut slots = vec![vec::Vec::new(); anfa.delta.len()];
+    let mut machines = anfa.automata_refs.iter();
+    for (group, &count) in (1..).zip(counts.iter()) {
+        for &[q0, f] in machines.by_ref().take(count) {
+            slots[q0].push(2 * group);
+            slots[f].push(2 * group + 1);
+        }
+    }
+    anfa.automata_refs = top.into_iter().collect();
+    for (_, targets) in anfa.delta.iter() {
+        let targets: vec::Vec<QId> = targets.iter().flatten().copied().collect();
+        anfa.check_states(&targets)?;
+    }
+    Ok(CompiledRegex {
+        anfa,
+        groups: counts.len(),
+        slots,
+    })
+}
+
+impl CompiledRegex {
+    /// Returns true when the pattern accepts the whole input.
+    pub fn is_match(&self, input: &str) -> bool {
+        self.anfa.is_match(input)
+    }
+
+    /// Returns the byte range of the leftmost-longest match. See `Matcher::find`.
+    pub fn find(&self, input: &str) -> Option<(usize, usize)> {
+        self.anfa.find(input)
+    }
+
+    /// Returns the groups of the leftmost-first match, the match a backtracking
+    /// engine finds. See `Matcher::find_first`.
+    ///
+    /// Group 0 is the whole match, and groups are numbered from 1 by their open
+    /// parenthesis. A repeated group holds its last repetition, and a group that
+    /// did not participate is `None`. Named groups are not supported by the parser.
+    ///
+    /// ```rust
+    /// let regex = regexxx::compile("(a|b)+(c)?").unwrap();
+    /// let captures = regex.captures("xabc").unwrap();
+    /// assert_eq!(captures.get(0), Some((1, 4)));
+    /// assert_eq!(captures.get(1), Some((2, 3)));
+    /// assert_eq!(captures.get(2), Some((3, 4)));
+    /// ```
+    pub fn captures(&self, input: &str) -> Option<Captures> {
+        let [q0, f] = *self.anfa.automata_refs.last()?;
+        let matcher = Matcher::default();
+        let starts = input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(core::iter::once(input.len()));
+        let mut visited = vec![false; self.anfa.delta.len()];
+        for start in starts {
+            let mut slots = vec![None; 2 * self.groups + 2];
+            slots[0] = Some(start);
+            let mut threads = vec::Vec::new();
+            visited.iter_mut().for_each(|v| *v = false);
+            self.closure(q0, slots, start, input, &mut visited, &mut threads);
+            let mut best = None;
+            let mut at = start;
+            let mut chars = input[start..].chars();
+            loop {
+                if let Some(i) = threads.iter().position(|(q, _)| *q == f) {
+                    let mut slots = threads[i].1.clone();
+                    slots[1] = Some(at);
+                    best = Some(Captures { slots });
+                    threads.truncate(i);
+                }
+                let c = match chars.next() {
+                    Some(c) if !threads.is_empty() => c,
+                    _ => break,
+                };
+                at += c.len_utf8();
+                visited.iter_mut().for_each(|v| *v = false);
+                let mut next = vec::Vec::new();
+                for (q, slots) in threads.into_iter() {
+                    let (label, targets) = &self.anfa.delta[q];
+                    if matcher.label_matches(label, c) {
+                        for &target in targets.iter().flatten() {
+                            self.closure(target, slots.clone(), at, input, &mut visited, &mut next);
+                        }
+                    }
+                }
+                threads = next;
+            }
+            if best.is_some() {
+                return best;
+            }
+        }
+        None
+    }
+
+    /// Appends `q` and the states reachable from it along epsilon transitions to
+    /// `threads` in priority order, like `Matcher::find_first`, setting the capture
+    /// slots of each state entered to `at`.
+    fn closure(
+        &self,
+        q: QId,
+        slots: vec::Vec<Option<usize>>,
+        at: usize,
+        input: &str,
+        visited: &mut [bool],
+        threads: &mut vec::Vec<(QId, vec::Vec<Option<usize>>)>,
+    ) {
+        let mut stack = vec![(q, slots)];
+        while let Some((q, mut slots)) = stack.pop() {
+            if visited[q] {
+                continue;
+            }
+            visited[q] = true;
+            for &slot in self.slots[q].iter() {
+                slots[slot] = Some(at);
+            }
+            let (label, targets) = &self.anfa.delta[q];
+            let advances = match label {
+                Label::Epsilon => true,
+                Label::Anchor(AnchorKind::Start) => at == 0,
+                Label::Anchor(AnchorKind::End) => at == input.len(),
+                _ => false,
+            };
+            if advances {
+                // push in reverse so targets[0] is expanded first
+                for &target in targets.iter().flatten().rev() {
+                    stack.push((target, slots.clone()));
+                }
+            }
+            threads.push((q, slots));
+        }
+    }
+
+    /// Returns the byte ranges of successive non-overlapping leftmost-longest
+    /// matches.
+    ///
+    /// Each search starts where the last match ended. After an empty match the
+    /// search skips one char, so every position is tried once.
+    ///
+    /// ```rust
+    /// let regex = regexxx::compile("ab*").unwrap();
+    /// let matches: Vec<_> = regex.find_iter("abbxaab").collect();
+    /// assert_eq!(matches, [(0, 3), (4, 5), (5, 7)]);
+    /// ```
+    pub fn find_iter<'r, 'h>(&'r self, input: &'h str) -> Matches<'r, 'h> {
+        Matches {
+            regex: self,
+            input,
+            pos: Some(0),
+        }
+    }
+}
+
+/// The groups of a match. See `CompiledRegex::captures`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Captures {
+    slots: vec::Vec<Option<usize>>,
+}
+
+impl Captures {
+    /// Returns the byte range of group `index`, or `None` when the group did not
+    /// participate in the match or does not exist.
+    pub fn get(&self, index: usize) -> Option<(usize, usize)> {
+        match (self.slots.get(2 * index)?, self.slots.get(2 * index + 1)?) {
+            (Some(start), Some(end)) => Some((*start, *end)),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of groups, including group 0.
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    /// Returns false, because group 0 always exists.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+/// Iterator over the matches of a `CompiledRegex`. See `CompiledRegex::find_iter`.
+#[derive(Debug)]
+pub struct Matches<'r, 'h> {
+    regex: &'r CompiledRegex,
+    input: &'h str,
+    /// Where the next search starts, `None` once the input is exhausted
+    pos: Option<usize>,
+}
+
+impl<'r, 'h> Iterator for Matches<'r, 'h> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let matcher = Matcher::default();
+        let mut start = self.pos?;
+        loop {
+            let next = self.input[start..]
+                .chars()
+                .next()
+                .map(|c| start + c.len_utf8());
+            if let Some(end) = matcher.longest_from(&self.regex.anfa, start, self.input) {
+                self.pos = if end > start { Some(end) } else { next };
+                return Some((start, end));
+            }
+            start = match next {
+                Some(next) => next,
+                None => {
+                    self.pos = None;
+                    return None;
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile;
+    use alloc::vec;
+
+    #[test]
+    fn test_compile() {
+        let regex = compile("a(b|c)*d").unwrap();
+        assert!(regex.is_match("abccbd"), "a(b|c)*d accepts abccbd");
+        assert!(!regex.is_match("abc"), "a(b|c)*d rejects abc");
+        assert_eq!(
+            regex.find("xxadx"),
+            Some((2, 4)),
+            "find is leftmost-longest"
+        );
+        assert!(compile("a(").is_err(), "Parse errors are returned");
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let regex = compile("a*").unwrap();
+        let matches: vec::Vec<_> = regex.find_iter("baa").collect();
+        assert_eq!(
+            matches,
+            [(0, 0), (1, 3), (3, 3)],
+            "Empty matches are found once at each position"
+        );
+        let regex = compile("é").unwrap();
+        let matches: vec::Vec<_> = regex.find_iter("aéé").collect();
+        assert_eq!(matches, [(1, 3), (3, 5)], "Positions are bytes");
+        let regex = compile("^a").unwrap();
+        assert_eq!(
+            regex.find_iter("aaa").count(),
+            1,
+            "^ only matches at the start of the input"
+        );
+    }
+
+    #[test]
+    fn test_captures() {
+        let regex = compile("(a+)(b+)").unwrap();
+        let captures = regex.captures("aaabb").unwrap();
+        assert_eq!(captures.len(), 3, "Two groups and the whole match");
+        assert_eq!(captures.get(0), Some((0, 5)), "Group 0 is the whole match");
+        assert_eq!(captures.get(1), Some((0, 3)), "Group 1 is the a's");
+        assert_eq!(captures.get(2), Some((3, 5)), "Group 2 is the b's");
+        assert_eq!(captures.get(3), None, "Group 3 does not exist");
+        assert!(regex.captures("ccc").is_none(), "No match has no groups");
+    }
+
+    #[test]
+    fn test_captures_repetition() {
+        let regex = compile("(a|(b))+c").unwrap();
+        let captures = regex.captures("xbac").unwrap();
+        assert_eq!(captures.get(0), Some((1, 4)), "The match skips x");
+        assert_eq!(
+            captures.get(1),
+            Some((2, 3)),
+            "A repeated group holds its last repetition"
+        );
+        assert_eq!(
+            captures.get(2),
+            Some((1, 2)),
+            "A group keeps its last participation"
+        );
+        let captures = compile("(a)|(b)").unwrap().captures("b").unwrap();
+        assert_eq!(
+            captures.get(1),
+            None,
+            "The untaken branch does not participate"
+        );
+        assert_eq!(
+            captures.get(2),
+            Some((0, 1)),
+            "The taken branch participates"
+        );
+    }
+
+    #[test]
+    fn test_captures_left_alternative() {
+        let captures = compile("(a|ab)").unwrap().captures("ab").unwrap();
+        assert_eq!(captures.get(1), Some((0, 1)), "a|ab prefers a");
+        let captures = compile("(ab|a)").unwrap().captures("ab").unwrap();
+        assert_eq!(captures.get(1), Some((0, 2)), "ab|a prefers ab");
+        let captures = compile("(a)|(a)b").unwrap().captures("ab").unwrap();
+        assert_eq!(captures.get(1), Some((0, 1)), "The left group participates");
+        assert_eq!(captures.get(2), None, "The right group does not");
+    }
+
+    #[test]
+    fn test_captures_simplified_groups() {
+        let captures = compile("(a)()").unwrap().captures("a").unwrap();
+        assert_eq!(captures.get(2), Some((1, 1)), "An empty group participates");
+        let captures = compile("(a)|(a)").unwrap().captures("a").unwrap();
+        assert_eq!(
+            captures.get(1),
+            Some((0, 1)),
+            "The first duplicate participates"
+        );
+        assert_eq!(captures.get(2), None, "The merged duplicate does not");
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{transition, ANFA};
+
+/// Counts describing the size of an ANFA. See `ANFA::stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AutomatonStats {
+    /// Number of states in `delta`
+    pub states: usize,
+    /// Number of edges, i.e. targets, across every state
+    pub transitions: usize,
+    /// Number of edges leaving epsilon states
+    pub epsilon_transitions: usize,
+    /// Number of states with two targets
+    pub unions: usize,
+    /// Most targets of any one state, at most two
+    pub max_out_degree: usize,
+}
+
+impl ANFA {
+    /// Returns the size of every machine in `delta`, counted in one pass.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let stats = parse("a|b").unwrap().stats();
+    /// assert_eq!(stats.states, 6);
+    /// assert_eq!(stats.unions, 1);
+    /// ```
+    pub fn stats(&self) -> AutomatonStats {
+        let mut stats = AutomatonStats {
+            states: self.delta.len(),
+            ..AutomatonStats::default()
+        };
+        for t in self.delta.iter() {
+            let out_degree = transition::targets(t).count();
+            stats.transitions += out_degree;
+            if transition::is_epsilon(t) {
+                stats.epsilon_transitions += out_degree;
+            }
+            if transition::is_union(t) {
+                stats.unions += 1;
+            }
+            stats.max_out_degree = stats.max_out_degree.max(out_degree);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::compilers::CompilerExt;
+    use crate::parser::parse;
+    use crate::stats::AutomatonStats;
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(
+            parse("a(b|c)*d").unwrap().stats(),
+            AutomatonStats {
+                states: 13,
+                transitions: 14,
+                epsilon_transitions: 10,
+                unions: 2,
+                max_out_degree: 2,
+            },
+            "a(b|c)*d has one union for | and one for *"
+        );
+        assert_eq!(
+            ForwardCompiler::from_expr_1().unwrap().stats(),
+            AutomatonStats {
+                states: 1,
+                ..AutomatonStats::default()
+            },
+            "Epsilon is one state without transitions"
+        );
+    }
+
+    #[test]
+    fn test_stats_union_all() {
+        let mut balanced = ForwardCompiler::from_expr_a('a').unwrap();
+        let mut linear = ForwardCompiler::from_expr_a('a').unwrap();
+        for c in ['b', 'c', 'd'] {
+            ForwardCompiler::expr_a(&mut balanced, c).unwrap();
+            ForwardCompiler::expr_a(&mut linear, c).unwrap();
+        }
+        balanced.union_all_balanced(4).unwrap();
+        ForwardCompiler::union_all(&mut linear, 4).unwrap();
+        let (balanced, linear) = (balanced.stats(), linear.stats());
+        assert_eq!(balanced.unions, 3, "Four operands need three unions");
+        assert_eq!(balanced, linear, "Both strategies build the same size");
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{transition, ANFA};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use core::fmt::Write;
+
+impl ANFA {
+    /// Returns the Markdown state table drawn in the docs of each compiler.
+    ///
+    /// Each row is a state, its label, and its comma-separated targets. A state
+    /// without targets has no label, and every other epsilon state is labeled
+    /// `ε`. Rows are annotated with the `q0` and `f` of every machine on the stack.
+    /// Columns are padded to their widest cell.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// assert_eq!(
+    ///     machine.to_table_string(),
+    ///     "| Q | T | Q |\n\
+    ///      |---|---|---|\n\
+    ///      | 0 | a | 1 | (q0)\n\
+    ///      | 1 |   |   | (f)\n"
+    /// );
+    /// ```
+    pub fn to_table_string(&self) -> String {
+        let mut rows = vec::Vec::with_capacity(self.delta.len());
+        for (q, transition) in self.delta.iter().enumerate() {
+            let targets: vec::Vec<String> = transition::targets(transition)
+                .map(|target| target.to_string())
+                .collect();
+            let label = match &transition.0 {
+                _ if targets.is_empty() => String::new(),
+                label => label.to_string(),
+            };
+            let mut notes = vec::Vec::new();
+            for &[q0, f] in self.automata_refs.iter() {
+                let note = match (q0 == q, f == q) {
+                    (true, true) => "q0 = f",
+                    (true, false) => "q0",
+                    (false, true) => "f",
+                    (false, false) => continue,
+                };
+                if !notes.contains(&note) {
+                    notes.push(note);
+                }
+            }
+            rows.push([q.to_string(), label, targets.join(", "), notes.join(", ")]);
+        }
+        let width = |column: usize| {
+            rows.iter()
+                .map(|row: &[String; 4]| row[column].chars().count())
+                .fold(1, usize::max)
+        };
+        let widths = [width(0), width(1), width(2)];
+        let mut table = String::new();
+        // writing into a String cannot fail
+        let _ = writeln!(
+            table,
+            "| {:<a$} | {:<b$} | {:<c$} |",
+            "Q",
+            "T",
+            "Q",
+            a = widths[0],
+            b = widths[1],
+            c = widths[2]
+        );
+        let _ = writeln!(
+            table,
+            "|-{:-<a$}-|-{:-<b$}-|-{:-<c$}-|",
+            "",
+            "",
+            "",
+            a = widths[0],
+            b = widths[1],
+            c = widths[2]
+        );
+        for [q, label, targets, notes] in rows.iter() {
+            let _ = write!(
+                table,
+                "| {:<a$} | {:<b$} | {:<c$} |",
+                q,
+                label,
+                targets,
+                a = widths[0],
+                b = widths[1],
+                c = widths[2]
+            );
+            if !notes.is_empty() {
+                let _ = write!(table, " ({})", notes);
+            }
+            table.push('\n');
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+
+    #[test]
+    fn test_to_table_string() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert_eq!(
+            machine.to_table_string(),
+            concat!(
+                "| Q | T | Q |\n",
+                "|---|---|---|\n",
+                "| 0 | a | 1 | (q0)\n",
+                "| 1 |   |   | (f)\n",
+            ),
+            "Expression 'a' matches its documented table"
+        );
+        let machine = ForwardCompiler::from_expr_1().unwrap();
+        assert_eq!(
+            machine.to_table_string(),
+            concat!(
+                "| Q | T | Q |\n",
+                "|---|---|---|\n",
+                "| 0 |   |   | (q0 = f)\n"
+            ),
+            "Expression ε matches its documented table"
+        );
+    }
+
+    #[test]
+    fn test_to_table_string_star() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let table = machine.to_table_string();
+        assert!(
+            table.starts_with("| Q | T | Q    |\n|---|---|------|\n"),
+            "Columns widen to fit two targets"
+        );
+        assert!(
+            table.contains("| 3 | ε | 0, 4 |\n"),
+            "Targets are comma-separated"
+        );
+    }
+}
@@ -0,0 +1,1437 @@
+use crate::error::CompileError;
+use crate::matcher::Matcher;
+use crate::{transition, AnchorKind, AutomataRef, Label, QId, ANFA};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+
+impl ANFA {
+    /// Rewrites the top machine so labeled transitions lead directly to the next
+    /// labeled states, dropping epsilon transitions.
+    ///
+    /// Each labeled state's targets are replaced with the labeled states, and `f`,
+    /// found in the epsilon closure of its old targets. A state whose closure
+    /// contained `f` therefore transitions to `f` itself. Epsilon states of the
+    /// machine are left in `delta` without transitions.
+    ///
+    /// `Transition` holds at most two targets. When a closure contains more, or
+    /// when the machine must start in more than one state, the targets are split
+    /// across a balanced tree of new epsilon fan-out states. These are the only
+    /// epsilon transitions that remain.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// machine.remove_epsilons().unwrap();
+    /// assert!(machine.is_match("ab"));
+    /// ```
+    pub fn remove_epsilons(&mut self) -> Result<(), CompileError> {
+        let [machine_a_q0, machine_a_f] = match self.automata_refs.pop() {
+            None => {
+                return Err(CompileError::Invalid(
+                    "Removing epsilons requires one operand.",
+                ));
+            }
+            Some(machine_a) => machine_a,
+        };
+        let matcher = Matcher::default();
+        let significant_closure = |anfa: &ANFA, states: &[QId]| -> vec::Vec<QId> {
+            matcher
+                .epsilon_closure(anfa, states)
+                .into_iter()
+                .filter(|&q| q == machine_a_f || anfa.delta[q].0 != Label::Epsilon)
+                .collect()
+        };
+        let reachable = self.reachable_from(machine_a_q0);
+        let mut rewrites = vec::Vec::new();
+        for (q, _) in reachable.iter().enumerate().filter(|(_, &r)| r) {
+            let (label, targets) = &self.delta[q];
+            if *label != Label::Epsilon {
+                let targets: vec::Vec<QId> = targets.iter().flatten().copied().collect();
+                rewrites.push((q, label.clone(), significant_closure(self, &targets)));
+            }
+        }
+        let starts = significant_closure(self, &[machine_a_q0]);
+        for (q, _) in reachable.iter().enumerate().filter(|(_, &r)| r) {
+            if self.delta[q].0 == Label::Epsilon {
+                self.delta[q] = (
+                    // epsilon transitions are folded into labeled transitions
+                    Label::Epsilon,
+                    [None, None],
+                );
+            }
+        }
+        for (q, label, targets) in rewrites {
+            self.delta[q] = (label, self.max_outgoing_targets_guard(&targets));
+        }
+        let machine_b_q0 = match starts.len() {
+            // nothing is reachable, q0 is now a non-transitioning state
+            0 => machine_a_q0,
+            1 => starts[0],
+            _ => self.fan_out(&starts),
+        };
+        self.automata_refs.push([machine_b_q0, machine_a_f]);
+        Ok(())
+    }
+
+    /// Returns a new ANFA holding only an epsilon-free copy of the top machine,
+    /// leaving `self` unchanged. See `ANFA::remove_epsilons`.
+    ///
+    /// A labeled state whose closure holds more than the two targets a `Transition`
+    /// allows is split across epsilon fan-out states, so those are the only epsilon
+    /// transitions in the copy. States the copy no longer reaches are dropped.
+    /// Without a machine the copy is empty.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let machine = parse("a(b|c)*").unwrap();
+    /// let copy = machine.epsilon_free_clone();
+    /// assert!(copy.is_match("abcb"));
+    /// assert!(copy.delta.len() < machine.delta.len());
+    /// ```
+    pub fn epsilon_free_clone(&self) -> ANFA {
+        let mut copy = ANFA::new();
+        let machine = match self.top_machine() {
+            None => {
+                return copy;
+            }
+            Some(machine) => machine,
+        };
+        copy.delta = self.delta.clone();
+        copy.automata_refs.push(machine);
+        copy.trim_unreachable_from_q0();
+        // the copy holds one machine, so removing epsilons cannot fail
+        let _ = copy.remove_epsilons();
+        copy.trim_unreachable_from_q0();
+        copy
+    }
+
+    /// Pops the top machine and pushes its reversal, which accepts every string
+    /// of the machine backwards.
+    ///
+    /// Every state is mirrored by a new epsilon state fanning out to its incoming
+    /// edges, and each labeled edge becomes a new labeled state leading back to
+    /// the mirror of its source. The mirror of the old `f` is the new `q0`, and the
+    /// mirror of the old `q0` is the new `f`. Anchors swap, so `^` becomes `$`. Old
+    /// states are left in `delta`. Reversing, determinizing, and repeating both
+    /// steps is Brzozowski's minimization.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("ab").unwrap();
+    /// machine.reverse().unwrap();
+    /// assert!(machine.is_match("ba"));
+    /// assert!(!machine.is_match("ab"));
+    /// ```
+    pub fn reverse(&mut self) -> Result<(), CompileError> {
+        let machine = match self.automata_refs.pop() {
+            None => {
+                return Err(CompileError::Invalid("Reversing requires one operand."));
+            }
+            Some(machine_a) => machine_a,
+        };
+        let [machine_a_q0, machine_a_f] = machine;
+        let states = self.copied_states(machine);
+        let offset = self.delta.len();
+        let mirror = |q: QId| offset + states.binary_search(&q).expect("Targets are copied.");
+        for _ in states.iter() {
+            // push mirror, targets are set below
+            self.delta.push(transition::nothing());
+        }
+        let mut incoming = vec![vec::Vec::new(); states.len()];
+        for &q in states.iter() {
+            let (label, targets) = self.delta[q].clone();
+            for &target in targets.iter().flatten() {
+                let edge = match label {
+                    Label::Epsilon => mirror(q),
+                    Label::Anchor(AnchorKind::Start) => {
+                        self.push_edge(Label::Anchor(AnchorKind::End), mirror(q))
+                    }
+                    Label::Anchor(AnchorKind::End) => {
+                        self.push_edge(Label::Anchor(AnchorKind::Start), mirror(q))
+                    }
+                    ref label => self.push_edge(label.clone(), mirror(q)),
+                };
+                incoming[mirror(target) - offset].push(edge);
+            }
+        }
+        for (i, edges) in incoming.iter().enumerate() {
+            let targets = self.max_outgoing_targets_guard(edges);
+            self.delta[offset + i] = (Label::Epsilon, targets);
+        }
+        self.automata_refs
+            .push([mirror(machine_a_f), mirror(machine_a_q0)]);
+        Ok(())
+    }
+
+    /// Pushes a state labeled `label` transitioning to `to`. Returns the state.
+    fn push_edge(&mut self, label: Label, to: QId) -> QId {
+        self.delta.push(transition::labeled(label, to));
+        self.delta.len() - 1
+    }
+
+    /// Replaces every `Label::Char(c)` in `delta` with `Label::Char(map(c))`.
+    ///
+    /// Only labels change, so every machine keeps its states and transitions.
+    /// Epsilon, `Label::Any`, and `Label::Class` labels are left untouched because
+    /// a char mapping does not generally map a range onto a range.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("ab").unwrap();
+    /// machine.relabel(|c| c.to_ascii_uppercase());
+    /// assert!(machine.is_match("AB"));
+    /// ```
+    pub fn relabel(&mut self, map: impl Fn(char) -> char) {
+        for (label, _) in self.delta.iter_mut() {
+            if let Label::Char(c) = label {
+                *c = map(*c);
+            }
+        }
+    }
+
+    /// Drops every state that cannot be reached from `q0`, renumbering the rest
+    /// in their current order.
+    ///
+    /// Only forward reachability is checked, so states that are reached but can
+    /// never reach `f` are kept. See `ANFA::machine_states` for the states that
+    /// can also reach `f`. When several machines are stacked, states reachable
+    /// from the `q0` of any of them are kept so every machine stays valid. `f` is
+    /// always kept, even when it is unreachable.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// machine.automata_refs.remove(0);
+    /// machine.trim_unreachable_from_q0();
+    /// assert_eq!(machine.delta.len(), 2);
+    /// assert_eq!(machine.automata_refs, [[0, 1]]);
+    /// ```
+    pub fn trim_unreachable_from_q0(&mut self) {
+        let mut keep = vec![false; self.delta.len()];
+        for &[q0, f] in self.automata_refs.iter() {
+            for (q, _) in self
+                .reachable_from(q0)
+                .iter()
+                .enumerate()
+                .filter(|(_, &r)| r)
+            {
+                keep[q] = true;
+            }
+            keep[f] = true;
+        }
+        self.retain_states(&keep);
+    }
+
+    /// Renumbers states in breadth-first order from each machine's `q0`, bottom
+    /// machine first, so identical builds have identical layouts.
+    ///
+    /// Union targets are sorted by their new ids, and states no machine reaches
+    /// are dropped. Equivalent machines built differently still normalize to
+    /// different layouts; that needs minimization.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::coverage_compiler::CoverageCompiler;
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = CoverageCompiler::from_expr_a('a').unwrap();
+    /// machine.normalize();
+    /// assert_eq!(machine.delta, ForwardCompiler::from_expr_a('a').unwrap().delta);
+    /// ```
+    pub fn normalize(&mut self) {
+        let mut order = vec::Vec::with_capacity(self.delta.len());
+        let mut visited = vec![false; self.delta.len()];
+        for &[q0, f] in self.automata_refs.iter() {
+            for root in [q0, f] {
+                if visited[root] {
+                    continue;
+                }
+                visited[root] = true;
+                let mut frontier = order.len();
+                order.push(root);
+                while frontier < order.len() {
+                    let q = order[frontier];
+                    for &target in self.delta[q].1.iter().flatten() {
+                        if !visited[target] {
+                            visited[target] = true;
+                            order.push(target);
+                        }
+                    }
+                    frontier += 1;
+                }
+            }
+        }
+        self.renumber(&order);
+        for (_, targets) in self.delta.iter_mut() {
+            if let [Some(a), Some(b)] = targets {
+                if b < a {
+                    targets.swap(0, 1);
+                }
+            }
+        }
+    }
+
+    /// Merges states with identical transitions, repeating until no two states
+    /// match, then drops the merged states and renumbers the rest in order.
+    ///
+    /// States with the same label and targets accept the same inputs, so every
+    /// reference to one can point at the other. Final states are never merged,
+    /// because a final state accepts where an identical dead state does not. A
+    /// union whose targets merge keeps one target. This is cheaper than
+    /// minimization and never determinizes.
+    ///
+    /// Merging works back from the ends of chains, so alternatives with a common
+    /// suffix, e.g. `abc|xbc`, end up sharing one tail, and the union splits only
+    /// where they differ.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("ab|cb").unwrap();
+    /// let states = machine.count_states();
+    /// machine.dedup_states();
+    /// assert!(machine.count_states() < states);
+    /// assert!(machine.is_match("cb"));
+    /// ```
+    pub fn dedup_states(&mut self) {
+        let mut is_final = vec![false; self.delta.len()];
+        for &[_q0, f] in self.automata_refs.iter() {
+            is_final[f] = true;
+        }
+        let mut merged: vec::Vec<QId> = (0..self.delta.len()).collect();
+        loop {
+            let mut firsts = BTreeMap::new();
+            let mut changed = false;
+            for q in 0..self.delta.len() {
+                if merged[q] != q || is_final[q] {
+                    continue;
+                }
+                let first = *firsts.entry(&self.delta[q]).or_insert(q);
+                if first != q {
+                    merged[q] = first;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+            for (_, targets) in self.delta.iter_mut() {
+                for target in targets.iter_mut().flatten() {
+                    *target = merged[*target];
+                }
+                if targets[0] == targets[1] {
+                    targets[1] = None;
+                }
+            }
+            for machine in self.automata_refs.iter_mut() {
+                *machine = machine.map(|q| merged[q]);
+            }
+        }
+        let order: vec::Vec<QId> = (0..self.delta.len()).filter(|&q| merged[q] == q).collect();
+        self.renumber(&order);
+    }
+
+    /// Shares the common suffix of the two branches of each epsilon union of the
+    /// top machine, then drops the states no longer used and renumbers the rest
+    /// in order.
+    ///
+    /// Each branch must be a chain: every state has one target and one incoming
+    /// edge and is no machine's state, and both chains lead to the same state.
+    /// Working back from it, labeled states of the second branch with the same
+    /// labels as the first are dropped, and the second branch moves to the first
+    /// branch where they diverge, so `abc|xbc` becomes `(a|x)bc` with one `bc`
+    /// tail. Epsilon states between labels are skipped. Unlike
+    /// `ANFA::dedup_states`, which merges every pair of identical transitions,
+    /// only the unions of the top machine are rewired.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("abc|xbc").unwrap();
+    /// let states = machine.count_states();
+    /// machine.longest_common_suffix_merge();
+    /// assert!(machine.count_states() < states);
+    /// assert!(machine.is_match("xbc"));
+    /// ```
+    pub fn longest_common_suffix_merge(&mut self) {
+        let [q0, _f] = match self.top_machine() {
+            None => return,
+            Some(machine) => machine,
+        };
+        let mut incoming = self.incoming();
+        let mut dropped = vec![false; self.delta.len()];
+        let reachable = self.reachable_from(q0);
+        for u in (0..self.delta.len()).filter(|&q| reachable[q]) {
+            let (x, y) = match self.delta[u] {
+                (Label::Epsilon, [Some(x), Some(y)]) => (x, y),
+                _ => continue,
+            };
+            let ((chain_x, join_x), (chain_y, join_y)) =
+                match (self.chain(x, &incoming), self.chain(y, &incoming)) {
+                    (Some(chain_x), Some(chain_y)) => (chain_x, chain_y),
+                    _ => continue,
+                };
+            if join_x != join_y {
+                continue;
+            }
+            // labeled states of each chain, last first
+            let labeled = |chain: &[QId]| -> vec::Vec<QId> {
+                chain
+                    .iter()
+                    .rev()
+                    .copied()
+                    .filter(|&q| self.delta[q].0 != Label::Epsilon)
+                    .collect()
+            };
+            let (labeled_x, labeled_y) = (labeled(&chain_x), labeled(&chain_y));
+            let shared = labeled_x
+                .iter()
+                .zip(labeled_y.iter())
+                .take_while(|(&a, &b)| self.delta[a].0 == self.delta[b].0)
+                .count();
+            if shared == 0 {
+                continue;
+            }
+            let keep = labeled_x[shared - 1];
+            let split = match chain_y.iter().position(|&q| q == labeled_y[shared - 1]) {
+                None => continue,
+                Some(split) => split,
+            };
+            for &q in chain_y[split..].iter() {
+                dropped[q] = true;
+            }
+            // the second branch moves to the shared tail of the first
+            let from = if split == 0 { u } else { chain_y[split - 1] };
+            for target in self.delta[from].1.iter_mut().flatten() {
+                if *target == chain_y[split] {
+                    *target = keep;
+                }
+            }
+            incoming[keep] += 1;
+            if let [Some(a), Some(b)] = self.delta[u].1 {
+                if a == b {
+                    self.delta[u].1 = [Some(a), None];
+                }
+            }
+        }
+        let order: vec::Vec<QId> = (0..self.delta.len()).filter(|&q| !dropped[q]).collect();
+        self.renumber(&order);
+        self.debug_assert_invariants();
+    }
+
+    /// Returns the chain from `q` and the state it leads to, see
+    /// `ANFA::longest_common_suffix_merge`, or `None` when `q` starts no chain.
+    fn chain(&self, q: QId, incoming: &[usize]) -> Option<(vec::Vec<QId>, QId)> {
+        let mut chain = vec::Vec::new();
+        let mut q = q;
+        while incoming[q] == 1 && chain.len() < self.delta.len() {
+            match self.delta[q].1 {
+                [Some(next), None] => {
+                    chain.push(q);
+                    q = next;
+                }
+                _ => break,
+            }
+        }
+        if chain.is_empty() {
+            None
+        } else {
+            Some((chain, q))
+        }
+    }
+
+    /// Returns the number of edges into each state, counting each machine's
+    /// `q0` and `f` as one more.
+    fn incoming(&self) -> vec::Vec<usize> {
+        let mut incoming = vec![0; self.delta.len()];
+        for (_, targets) in self.delta.iter() {
+            for &target in targets.iter().flatten() {
+                incoming[target] += 1;
+            }
+        }
+        for &[q0, f] in self.automata_refs.iter() {
+            incoming[q0] += 1;
+            incoming[f] += 1;
+        }
+        incoming
+    }
+
+    /// Moves every state `q` to id `f(q)`, rewriting every target and machine and
+    /// reordering `delta` to match, e.g. to adopt ids assigned by another system.
+    ///
+    /// `f` must be a permutation of the states: an id outside `delta` fails with
+    /// `CompileError::StateOutOfBounds`, and two states mapped to one id fail too.
+    /// On failure the ANFA is unchanged.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// machine.map_states(|q| 1 - q).unwrap();
+    /// assert_eq!(machine.automata_refs, [[1, 0]]);
+    /// assert!(machine.is_match("a"));
+    /// ```
+    pub fn map_states(&mut self, f: impl Fn(QId) -> QId) -> Result<(), CompileError> {
+        let len = self.delta.len();
+        let mut order = vec![None; len];
+        for q in 0..len {
+            let id = f(q);
+            match order.get_mut(id) {
+                None => return Err(CompileError::StateOutOfBounds(id)),
+                Some(Some(_)) => {
+                    return Err(CompileError::Invalid(
+                        "Renumbering maps two states to one id.",
+                    ))
+                }
+                Some(slot) => *slot = Some(q),
+            }
+        }
+        // n distinct ids below n cover every id
+        let order: vec::Vec<QId> = order.into_iter().flatten().collect();
+        self.renumber(&order);
+        Ok(())
+    }
+
+    /// Moves state `order[i]` to id `i`. States missing from `order` are removed
+    /// along with every target pointing at them, keeping the remaining targets in
+    /// order. Machines must only start and end on states in `order`.
+    pub(crate) fn renumber(&mut self, order: &[QId]) {
+        let mut ids = vec![None; self.delta.len()];
+        for (id, &q) in order.iter().enumerate() {
+            ids[q] = Some(id);
+        }
+        let mut delta: vec::Vec<_> = core::mem::take(&mut self.delta)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.delta = order
+            .iter()
+            .map(|&q| {
+                let (label, targets) = delta[q].take().expect("Each state is ordered once.");
+                let mut targets = targets.iter().flatten().filter_map(|&q| ids[q]);
+                (label, [targets.next(), targets.next()])
+            })
+            .collect();
+        for machine in self.automata_refs.iter_mut() {
+            *machine = machine.map(|q| ids[q].expect("Machines start and end on ordered states."));
+        }
+    }
+
+    /// Removes every state `q` where `keep[q]` is false and renumbers the rest in
+    /// order. See `ANFA::renumber`.
+    pub(crate) fn retain_states(&mut self, keep: &[bool]) {
+        let order: vec::Vec<QId> = (0..self.delta.len()).filter(|&q| keep[q]).collect();
+        self.renumber(&order);
+    }
+
+    /// Returns a bitset of every state reachable from `q0` along any transition.
+    pub(crate) fn reachable_from(&self, q0: QId) -> vec::Vec<bool> {
+        let mut reachable = vec![false; self.delta.len()];
+        let mut stack = vec![q0];
+        while let Some(q) = stack.pop() {
+            if reachable[q] {
+                continue;
+            }
+            reachable[q] = true;
+            stack.extend(self.delta[q].1.iter().flatten());
+        }
+        reachable
+    }
+
+    /// Returns the sorted states of the machine `m`, i.e. every state reachable from
+    /// `m[0]` that can also reach `m[1]`.
+    ///
+    /// Operators link machines together in place, so the ids of one machine are
+    /// not a contiguous range. States that cannot reach `f` are dead and are not
+    /// part of the machine's language.
+    ///
+    /// ```rust
+    /// use regexxx::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    /// let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+    /// ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+    /// let machine_b = machine.automata_refs[1];
+    /// ForwardCompiler::concatenate(&mut machine).unwrap();
+    /// assert_eq!(machine.machine_states(machine_b), vec![2, 3]);
+    /// assert_eq!(machine.machine_states(machine.automata_refs[0]), vec![0, 1, 2, 3]);
+    /// ```
+    pub fn machine_states(&self, m: AutomataRef) -> vec::Vec<QId> {
+        let [q0, f] = m;
+        let reachable = self.reachable_from(q0);
+        let mut predecessors = vec![vec::Vec::new(); self.delta.len()];
+        for (q, (_, targets)) in self.delta.iter().enumerate() {
+            for &target in targets.iter().flatten() {
+                predecessors[target].push(q);
+            }
+        }
+        let mut co_reachable = vec![false; self.delta.len()];
+        let mut stack = vec![f];
+        while let Some(q) = stack.pop() {
+            if co_reachable[q] {
+                continue;
+            }
+            co_reachable[q] = true;
+            stack.extend(predecessors[q].iter());
+        }
+        (0..self.delta.len())
+            .filter(|&q| reachable[q] && co_reachable[q])
+            .collect()
+    }
+
+    /// Returns true when a cycle is reachable from `q0` of the top machine, i.e.
+    /// the machine may loop, e.g. after `Compiler::star`.
+    ///
+    /// Every transition counts, so the epsilon loop of a star is a cycle. Cycles
+    /// that cannot reach `f` count too.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert!(parse("ab*").unwrap().contains_cycle());
+    /// assert!(!parse("ab|c").unwrap().contains_cycle());
+    /// ```
+    pub fn contains_cycle(&self) -> bool {
+        match self.top_machine() {
+            None => false,
+            Some([q0, _f]) => self.has_cycle(&self.reachable_from(q0)),
+        }
+    }
+
+    /// Returns true when the top machine accepts finitely many strings.
+    ///
+    /// The language is infinite exactly when a cycle consuming input lies on a
+    /// path from `q0` to `f`, so only states of `ANFA::machine_states` are
+    /// searched. Cycles on dead branches and epsilon-only cycles, e.g. `()*`,
+    /// leave the language finite. Anchors are assumed to pass, so a cycle that
+    /// consumes after `$` still counts.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert!(parse("a|bc").unwrap().accepts_only_finite_language());
+    /// assert!(!parse("ab*").unwrap().accepts_only_finite_language());
+    /// ```
+    pub fn accepts_only_finite_language(&self) -> bool {
+        let machine = match self.top_machine() {
+            None => return true,
+            Some(machine) => machine,
+        };
+        let mut live = vec![false; self.delta.len()];
+        for q in self.machine_states(machine) {
+            live[q] = true;
+        }
+        let components = self.components(&live);
+        !self.delta.iter().enumerate().any(|(q, (label, targets))| {
+            let consumes = !matches!(label, Label::Epsilon | Label::Anchor(_));
+            consumes
+                && targets
+                    .iter()
+                    .flatten()
+                    .any(|&t| live[q] && live[t] && components[q] == components[t])
+        })
+    }
+
+    /// Returns the chars every string accepted by the top machine begins with, e.g.
+    /// `abc` for `abc(d|e)*`, so a caller can scan for it before simulating.
+    ///
+    /// The walk starts at `q0` and follows states with exactly one target,
+    /// collecting the char of each `Label::Char`, and stops at a union, at `f`,
+    /// or at any other label. Epsilon and anchor transitions consume nothing, so
+    /// they are followed.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert_eq!(parse("ab(c|d)").unwrap().literal_prefix(), "ab");
+    /// assert_eq!(parse("a*b").unwrap().literal_prefix(), "");
+    /// ```
+    pub fn literal_prefix(&self) -> String {
+        let mut prefix = String::new();
+        let [mut q, f] = match self.top_machine() {
+            None => return prefix,
+            Some(machine) => machine,
+        };
+        let mut visited = vec![false; self.delta.len()];
+        while q != f && !visited[q] {
+            visited[q] = true;
+            let next = match &self.delta[q] {
+                (Label::Char(c), [Some(next), None]) => {
+                    prefix.push(*c);
+                    *next
+                }
+                (Label::Epsilon | Label::Anchor(_), [Some(next), None]) => *next,
+                _ => break,
+            };
+            q = next;
+        }
+        prefix
+    }
+
+    /// Returns the char every string accepted by the top machine begins with, when
+    /// matching can only start one way, e.g. `a` for `a(b|c)`.
+    ///
+    /// Every consuming state in the closure of `q0` must be a `Label::Char` of
+    /// the same char. `None` when the closure holds `.`, a class, or two chars,
+    /// when it reaches `f`, i.e. the empty string is accepted, or when nothing is
+    /// consumed at all. `^` is followed, since matching starts at the input start,
+    /// and so is `$` when looking for `f`, since the empty input also ends there.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// assert_eq!(parse("a|ab").unwrap().first_char(), Some('a'));
+    /// assert_eq!(parse("a|b").unwrap().first_char(), None);
+    /// ```
+    pub fn first_char(&self) -> Option<char> {
+        let [q0, f] = self.top_machine()?;
+        let matcher = Matcher::default();
+        // `$` holds on the empty input, so reaching `f` through it accepts ''
+        if matcher
+            .anchored_closure(self, &[q0], true, true)
+            .contains(&f)
+        {
+            return None;
+        }
+        let closure = matcher.anchored_closure(self, &[q0], true, false);
+        let mut first = None;
+        for &q in closure.iter() {
+            match (&self.delta[q].0, first) {
+                (Label::Epsilon | Label::Anchor(_), _) => {}
+                (Label::Char(c), None) => first = Some(*c),
+                (Label::Char(c), Some(first)) if *c == first => {}
+                _ => return None,
+            }
+        }
+        first
+    }
+
+    /// Rewires the tree of epsilon unions at `q0` of the top machine into one
+    /// chain and returns its branches, highest priority first.
+    ///
+    /// A union target joins the tree when it is also an epsilon union, its only
+    /// incoming edge is from the tree, and it is no machine's state. Each union
+    /// then splits into the next branch and the next union, so `(a|b)|(c|d)`
+    /// becomes `a|(b|(c|d))` with the same states and the same language. A `q0`
+    /// that is not a union is the only branch, and no machine has no branches.
+    ///
+    /// ```rust
+    /// use regexxx::parser::parse;
+    /// let mut machine = parse("(a|b)|c").unwrap();
+    /// assert_eq!(machine.flatten_unions().len(), 3);
+    /// assert!(machine.is_match("b"));
+    /// ```
+    pub fn flatten_unions(&mut self) -> vec::Vec<QId> {
+        let [q0, _f] = match self.top_machine() {
+            None => return vec::Vec::new(),
+            Some(machine) => machine,
+        };
+        let incoming = self.incoming();
+        let is_split =
+            |q: QId| self.delta[q].0 == Label::Epsilon && transition::is_union(&self.delta[q]);
+        if !is_split(q0) {
+            return vec![q0];
+        }
+        let mut unions = vec![q0];
+        let mut branches = vec::Vec::new();
+        // targets are pushed right first, so branches are found left first
+        let mut stack: vec::Vec<QId> = self.delta[q0].1.iter().flatten().rev().copied().collect();
+        while let Some(q) = stack.pop() {
+            if q != q0 && incoming[q] == 1 && is_split(q) {
+                unions.push(q);
+                stack.extend(self.delta[q].1.iter().flatten().rev());
+            } else {
+                branches.push(q);
+            }
+        }
+        // a tree of n branches has n - 1 unions, the first being q0
+        for (i, &q) in unions.iter().enumerate() {
+            let next = unions.get(i + 1).copied().unwrap_or(branches[i + 1]);
+            self.delta[q].1 = [Some(branches[i]), Some(next)];
+        }
+        self.debug_assert_touched(&unions);
+        branches
+    }
+
+    /// Returns true when an edge between two states in `within` closes a cycle
+    /// through states in `within`.
+    fn has_cycle(&self, within: &[bool]) -> bool {
+        let components = self.components(within);
+        self.delta.iter().enumerate().any(|(q, (_, targets))| {
+            targets
+                .iter()
+                .flatten()
+                .any(|&t| within[q] && within[t] && components[q] == components[t])
+        })
+    }
+
+    /// Returns the strongly connected component of each state in `within`,
+    /// following only edges inside `within`. States outside `within` are `None`.
+    ///
+    /// Kosaraju's algorithm: states are ordered by when a depth first search
+    /// finishes them, then searched again in reverse order on reversed edges.
+    fn components(&self, within: &[bool]) -> vec::Vec<Option<usize>> {
+        let mut finished = vec::Vec::with_capacity(self.delta.len());
+        let mut visited = vec![false; self.delta.len()];
+        for root in (0..self.delta.len()).filter(|&q| within[q]) {
+            if visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            // each frame is a state and the index of its next target
+            let mut stack = vec![(root, 0)];
+            while let Some((q, i)) = stack.pop() {
+                match self.delta[q].1.get(i) {
+                    Some(&target) => {
+                        stack.push((q, i + 1));
+                        if let Some(t) = target.filter(|&t| within[t] && !visited[t]) {
+                            visited[t] = true;
+                            stack.push((t, 0));
+                        }
+                    }
+                    None => finished.push(q),
+                }
+            }
+        }
+        let mut predecessors = vec![vec::Vec::new(); self.delta.len()];
+        for &q in finished.iter() {
+            for &t in self.delta[q].1.iter().flatten() {
+                if within[t] {
+                    predecessors[t].push(q);
+                }
+            }
+        }
+        let mut components = vec![None; self.delta.len()];
+        for (component, &root) in finished.iter().rev().enumerate() {
+            if components[root].is_some() {
+                continue;
+            }
+            let mut stack = vec![root];
+            while let Some(q) = stack.pop() {
+                if components[q].is_some() {
+                    continue;
+                }
+                components[q] = Some(component);
+                stack.extend(predecessors[q].iter());
+            }
+        }
+        components
+    }
+
+    /// Packs `targets` into the two slots of a `Transition`, pushing epsilon
+    /// fan-out states when there are more than two.
+    ///
+    /// This is the canonical way to give a state any number of targets. Every
+    /// operator that may need more than two, e.g. epsilon removal, reversal, and
+    /// DFA import, routes its targets through this guard. Fan-out states form a
+    /// balanced tree and preserve the order of `targets`. See `ANFA::set_targets`.
+    ///
+    /// ```rust
+    /// use regexxx::ANFA;
+    /// let mut machine = ANFA::with_capacity(3, 0);
+    /// let states = [machine.push_state(), machine.push_state(), machine.push_state()];
+    /// let targets = machine.max_outgoing_targets_guard(&states);
+    /// assert_eq!(machine.count_states(), 4);
+    /// assert_eq!(targets, [Some(0), Some(3)]);
+    /// ```
+    pub fn max_outgoing_targets_guard(&mut self, targets: &[QId]) -> [Option<QId>; 2] {
+        match targets.len() {
+            0 => [None, None],
+            1 => [Some(targets[0]), None],
+            2 => [Some(targets[0]), Some(targets[1])],
+            len => {
+                let (left, right) = targets.split_at(len / 2);
+                [Some(self.fan_out(left)), Some(self.fan_out(right))]
+            }
+        }
+    }
+
+    /// Returns a state that transitions to every one of `targets` along epsilon,
+    /// preserving their order. A single target is returned as is.
+    fn fan_out(&mut self, targets: &[QId]) -> QId {
+        if targets.len() == 1 {
+            return targets[0];
+        }
+        let split = self.max_outgoing_targets_guard(targets);
+        self.delta.push((
+            // push epsilon fan-out
+            Label::Epsilon,
+            split,
+        ));
+        self.delta.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compilers::forward_compiler::{Compiler, ForwardCompiler};
+    use crate::compilers::CompilerExt;
+    use crate::error::CompileError;
+    use crate::parser::parse;
+    use crate::{transition, Label, ANFA};
+    use alloc::vec;
+
+    #[test]
+    fn test_remove_epsilons() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        let inputs = ["", "a", "aaaa", "b", "ab", "aab"];
+        let expected: [bool; 6] = inputs.map(|input| machine.is_match(input));
+        machine.remove_epsilons().unwrap();
+        assert_eq!(
+            machine.automata_refs.len(),
+            1,
+            "Removing epsilons replaces the top machine"
+        );
+        assert_eq!(
+            inputs.map(|input| machine.is_match(input)),
+            expected,
+            "Epsilon-free 'a'* accepts the same strings"
+        );
+        let [machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        let reachable = machine.reachable_from(machine_a_q0);
+        for (q, transition) in machine.delta.iter().enumerate() {
+            if reachable[q] && q != machine_a_f && transition.0 == Label::Epsilon {
+                assert!(
+                    transition.1[1].is_some(),
+                    "Only fan-out states keep epsilon transitions"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_epsilons_fan_out() {
+        // RE (a|b|c)(a|b|c)
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'c').unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        machine.remove_epsilons().unwrap();
+        for input in ["aa", "ab", "ac", "ba", "cc"] {
+            assert!(machine.is_match(input), "Three-way branches are split");
+        }
+        for input in ["", "a", "abc", "ad"] {
+            assert!(!machine.is_match(input), "Language is preserved");
+        }
+    }
+
+    #[test]
+    fn test_remove_epsilons_empty_stack() {
+        let mut machine = ForwardCompiler::from_expr_0().unwrap();
+        machine.automata_refs.clear();
+        assert!(
+            machine.remove_epsilons().is_err(),
+            "Removing epsilons requires a machine"
+        );
+    }
+
+    #[test]
+    fn test_machine_states() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        let [machine_a, machine_b] = [machine.automata_refs[0], machine.automata_refs[1]];
+        ForwardCompiler::union(&mut machine).unwrap();
+        let machine_c = machine.automata_refs[0];
+        assert_eq!(
+            machine.machine_states(machine_a),
+            [0, 1],
+            "Operand 'a' owns only its own states"
+        );
+        assert_eq!(
+            machine.machine_states(machine_b),
+            [2, 3],
+            "Operand 'b' owns only its own states"
+        );
+        assert_eq!(
+            machine.machine_states(machine_c),
+            [0, 1, 2, 3, 4, 5],
+            "Union owns both operands and its new states"
+        );
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        let machine_d = machine.automata_refs[1];
+        assert_eq!(
+            machine.machine_states(machine_d),
+            [],
+            "Nothing cannot reach f, so it owns no states"
+        );
+    }
+
+    #[test]
+    fn test_relabel() {
+        let mut machine = parse("a(b|c)*d").unwrap();
+        let targets: vec::Vec<_> = machine.delta.iter().map(|(_, targets)| *targets).collect();
+        machine.relabel(|c| c.to_ascii_uppercase());
+        assert_eq!(
+            machine
+                .delta
+                .iter()
+                .map(|(_, targets)| *targets)
+                .collect::<vec::Vec<_>>(),
+            targets,
+            "Relabeling does not alter structure"
+        );
+        for input in ["AD", "ABCD", "ACCBD"] {
+            assert!(
+                machine.is_match(input),
+                "Relabeled machine matches {:?}",
+                input
+            );
+        }
+        for input in ["ad", "abcd", "aBCD"] {
+            assert!(
+                !machine.is_match(input),
+                "Relabeled machine rejects {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_trim_unreachable_from_q0() {
+        let mut machine = parse("a(b|c)*").unwrap();
+        let [_machine_a_q0, machine_a_f] = machine.automata_refs[0];
+        // orphan a state that transitions into the machine
+        let orphan = machine.push_state();
+        machine.set_labeled(orphan, 'x', machine_a_f).unwrap();
+        let states = machine.delta.len();
+        machine.trim_unreachable_from_q0();
+        assert_eq!(machine.delta.len(), states - 1, "The orphan is removed");
+        assert!(
+            machine
+                .delta
+                .iter()
+                .all(|(label, _)| *label != Label::Char('x')),
+            "No state is labeled 'x'"
+        );
+        assert!(
+            machine.equivalent(&parse("a(b|c)*").unwrap()),
+            "Language is unchanged"
+        );
+    }
+
+    #[test]
+    fn test_trim_unreachable_from_q0_keeps_machines() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        machine.trim_unreachable_from_q0();
+        assert_eq!(machine.delta.len(), 4, "Lower machines are kept");
+        assert_eq!(
+            machine.automata_refs,
+            [[0, 1], [2, 3]],
+            "Unreachable f of nothing is kept"
+        );
+        ForwardCompiler::union(&mut machine).unwrap();
+        assert!(machine.is_match("a"), "a|[] matches 'a'");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut machine_a = parse("ab").unwrap();
+        let mut machine_b = parse("ab").unwrap();
+        machine_a.normalize();
+        machine_b.normalize();
+        assert_eq!(
+            machine_a.delta, machine_b.delta,
+            "Identical builds are identical"
+        );
+        assert_eq!(
+            machine_a.automata_refs, machine_b.automata_refs,
+            "Identical builds have identical machines"
+        );
+        assert_eq!(machine_a.automata_refs, [[0, 3]], "q0 is renumbered first");
+    }
+
+    #[test]
+    fn test_normalize_drops_unreachable() {
+        let mut machine = parse("a|b").unwrap();
+        let orphan = machine.push_state();
+        machine.set_epsilon(orphan, 0).unwrap();
+        machine.normalize();
+        assert_eq!(machine.delta.len(), 6, "The orphan is dropped");
+        assert!(
+            machine.delta.iter().all(|(_, targets)| match targets {
+                [Some(a), Some(b)] => a < b,
+                _ => true,
+            }),
+            "Union targets are sorted"
+        );
+        assert!(
+            machine.equivalent(&parse("a|b").unwrap()),
+            "Language is unchanged"
+        );
+    }
+
+    #[test]
+    fn test_epsilon_free_clone() {
+        let machine = parse("a(b|c)*d|e").unwrap();
+        let copy = machine.epsilon_free_clone();
+        assert_eq!(copy.automata_refs.len(), 1, "The copy holds one machine");
+        for input in ["ad", "abcbd", "e", "", "abc", "ed"] {
+            assert_eq!(
+                copy.is_match(input),
+                machine.is_match(input),
+                "The copy and original agree on {:?}",
+                input
+            );
+        }
+        assert!(
+            copy.equivalent(&machine),
+            "The copy accepts the same language"
+        );
+        assert!(
+            machine.delta.iter().any(transition::is_epsilon),
+            "The original still has epsilon transitions"
+        );
+        assert_eq!(
+            machine.delta,
+            parse("a(b|c)*d|e").unwrap().delta,
+            "The original is unchanged"
+        );
+        assert!(
+            ForwardCompiler::from_expr_0()
+                .unwrap()
+                .epsilon_free_clone()
+                .is_empty_language(),
+            "Nothing stays nothing"
+        );
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::expr_a(&mut machine, 'b').unwrap();
+        ForwardCompiler::concatenate(&mut machine).unwrap();
+        machine.reverse().unwrap();
+        assert!(machine.is_match("ba"), "The reversal of ab accepts ba");
+        assert!(!machine.is_match("ab"), "The reversal of ab rejects ab");
+        let mut machine = parse("^a(b|cd)*").unwrap();
+        machine.reverse().unwrap();
+        assert!(
+            machine.equivalent(&parse("(b|dc)*a$").unwrap()),
+            "Reversal reverses concatenation and swaps anchors"
+        );
+        machine.reverse().unwrap();
+        assert!(
+            machine.equivalent(&parse("^a(b|cd)*").unwrap()),
+            "Reversing twice preserves the language"
+        );
+        let mut machine = ForwardCompiler::from_expr_1().unwrap();
+        machine.automata_refs.clear();
+        assert!(machine.reverse().is_err(), "Reversing requires one operand");
+    }
+
+    #[test]
+    fn test_dedup_states() {
+        let mut machine = parse("(a|a)(b|b)*").unwrap();
+        let expected = parse("(a|a)(b|b)*").unwrap();
+        let states = machine.count_states();
+        machine.dedup_states();
+        assert!(
+            machine.count_states() < states,
+            "Each duplicated branch shares its states"
+        );
+        assert!(
+            machine.equivalent(&expected),
+            "Deduplication preserves the language"
+        );
+        let mut machine = ForwardCompiler::from_expr_1().unwrap();
+        ForwardCompiler::expr_0(&mut machine).unwrap();
+        machine.dedup_states();
+        assert_eq!(machine.count_states(), 3, "Final states are never merged");
+        assert!(
+            !machine.is_match(""),
+            "The dead state of nothing stays dead"
+        );
+    }
+
+    #[test]
+    fn test_dedup_states_common_suffix() {
+        let mut machine = ANFA::new();
+        ForwardCompiler::literal_str(&mut machine, "abc").unwrap();
+        ForwardCompiler::literal_str(&mut machine, "xbc").unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let expected = parse("abc|xbc").unwrap();
+        let states = machine.count_states();
+        machine.dedup_states();
+        assert_eq!(
+            machine.count_states(),
+            states - 5,
+            "b, c, and the states between them and f are shared"
+        );
+        for c in ['b', 'c'] {
+            assert_eq!(
+                machine
+                    .delta
+                    .iter()
+                    .filter(|(label, _)| *label == Label::Char(c))
+                    .count(),
+                1,
+                "One {} state is left",
+                c
+            );
+        }
+        assert!(
+            machine.equivalent(&expected),
+            "Sharing the tail preserves the language"
+        );
+    }
+
+    #[test]
+    fn test_longest_common_suffix_merge() {
+        let mut machine = ANFA::new();
+        ForwardCompiler::literal_str(&mut machine, "abc").unwrap();
+        ForwardCompiler::literal_str(&mut machine, "xbc").unwrap();
+        ForwardCompiler::union(&mut machine).unwrap();
+        let expected = parse("abc|xbc").unwrap();
+        let states = machine.count_states();
+        machine.longest_common_suffix_merge();
+        assert_eq!(
+            machine.count_states(),
+            states - 4,
+            "The b and c states of xbc and the epsilons after them are dropped"
+        );
+        for c in ['b', 'c'] {
+            assert_eq!(
+                machine
+                    .delta
+                    .iter()
+                    .filter(|(label, _)| *label == Label::Char(c))
+                    .count(),
+                1,
+                "One {} state is left",
+                c
+            );
+        }
+        assert!(
+            machine.equivalent(&expected),
+            "Sharing the tail preserves the language"
+        );
+        // RE bc|abc, the whole first branch is the shared tail
+        let mut machine = parse("bc|abc").unwrap();
+        machine.longest_common_suffix_merge();
+        assert_eq!(
+            machine
+                .delta
+                .iter()
+                .filter(|(label, _)| *label == Label::Char('b'))
+                .count(),
+            1,
+            "abc moves to bc after a"
+        );
+        assert!(
+            machine.equivalent(&parse("bc|abc").unwrap()),
+            "bc|abc keeps its language"
+        );
+        // RE ab|cd, nothing is shared
+        let mut machine = parse("ab|cd").unwrap();
+        let states = machine.count_states();
+        machine.longest_common_suffix_merge();
+        assert_eq!(
+            machine.count_states(),
+            states,
+            "Different suffixes are kept"
+        );
+        // RE (ab|b)*, the tail is shared inside a loop
+        let mut machine = parse("(ab|b)*").unwrap();
+        machine.longest_common_suffix_merge();
+        assert!(
+            machine.equivalent(&parse("(ab|b)*").unwrap()),
+            "Loops keep their language"
+        );
+    }
+
+    #[test]
+    fn test_map_states() {
+        let mut machine = parse("a(b|c)*d").unwrap();
+        let len = machine.delta.len();
+        machine.map_states(|q| len - 1 - q).unwrap();
+        assert_eq!(
+            machine.top_machine(),
+            Some([len - 1, 0]),
+            "q0 and f are reversed"
+        );
+        assert!(
+            machine.equivalent(&parse("a(b|c)*d").unwrap()),
+            "Renumbering preserves the language"
+        );
+        assert_eq!(
+            machine.map_states(|q| q / 2),
+            Err(CompileError::Invalid(
+                "Renumbering maps two states to one id."
+            )),
+            "Collisions fail"
+        );
+        assert_eq!(
+            machine.map_states(|q| q + 1),
+            Err(CompileError::StateOutOfBounds(len)),
+            "Ids past delta fail"
+        );
+        assert!(
+            machine.is_match("abcd"),
+            "Failures leave the ANFA unchanged"
+        );
+    }
+
+    #[test]
+    fn test_contains_cycle() {
+        let machine = ForwardCompiler::from_expr_a('a').unwrap();
+        assert!(!machine.contains_cycle(), "A literal has no cycle");
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        ForwardCompiler::star(&mut machine).unwrap();
+        assert!(machine.contains_cycle(), "A star loops");
+        assert!(
+            !parse("a|bc|d").unwrap().contains_cycle(),
+            "A union of literals has no cycle"
+        );
+        assert!(
+            parse("()*").unwrap().contains_cycle(),
+            "Epsilon-only cycles count"
+        );
+        let mut machine = ForwardCompiler::from_expr_a('a').unwrap();
+        machine.delta[1] = transition::epsilon(0);
+        assert!(machine.contains_cycle(), "f may be on the cycle");
+    }
+
+    #[test]
+    fn test_accepts_only_finite_language() {
+        assert!(
+            parse("a|b").unwrap().accepts_only_finite_language(),
+            "a|b accepts two strings"
+        );
+        assert!(
+            !parse("a*").unwrap().accepts_only_finite_language(),
+            "a* accepts every repetition"
+        );
+        assert!(
+            parse("a*[]|b").unwrap().accepts_only_finite_language(),
+            "A star on a dead branch does not count"
+        );
+        assert!(
+            parse("a()*").unwrap().accepts_only_finite_language(),
+            "An epsilon-only cycle consumes nothing"
+        );
+        assert!(
+            !parse("a(b|c*)d").unwrap().accepts_only_finite_language(),
+            "A live star inside a union counts"
+        );
+    }
+
+    #[test]
+    fn test_literal_prefix() {
+        assert_eq!(
+            parse("abc(d)*").unwrap().literal_prefix(),
+            "abc",
+            "The prefix stops at the star"
+        );
+        assert_eq!(
+            parse("(a|b)c").unwrap().literal_prefix(),
+            "",
+            "A leading union has no prefix"
+        );
+        assert_eq!(
+            parse("^ab").unwrap().literal_prefix(),
+            "ab",
+            "Anchors consume nothing"
+        );
+        assert_eq!(
+            parse("a.b").unwrap().literal_prefix(),
+            "a",
+            "The prefix stops at ."
+        );
+        assert_eq!(ANFA::new().literal_prefix(), "", "No machine has no prefix");
+    }
+
+    #[test]
+    fn test_first_char() {
+        assert_eq!(
+            parse("abc").unwrap().first_char(),
+            Some('a'),
+            "abc can only start with a"
+        );
+        assert_eq!(
+            parse("(a|b)c").unwrap().first_char(),
+            None,
+            "(a|b)c can start with a or b"
+        );
+        assert_eq!(
+            parse("(ab|ac)*d").unwrap().first_char(),
+            None,
+            "(ab|ac)*d can start with a or d"
+        );
+        assert_eq!(
+            parse("^(ab|ac)").unwrap().first_char(),
+            Some('a'),
+            "Both branches start with a"
+        );
+        assert_eq!(
+            parse("a?").unwrap().first_char(),
+            None,
+            "'' starts with nothing"
+        );
+        assert_eq!(parse(".a").unwrap().first_char(), None, ". is any char");
+        assert_eq!(
+            parse("$|a").unwrap().first_char(),
+            None,
+            "$|a accepts '' through $"
+        );
+        assert_eq!(
+            parse("a$").unwrap().first_char(),
+            Some('a'),
+            "$ after a consumed char does not accept ''"
+        );
+    }
+
+    #[test]
+    fn test_flatten_unions() {
+        let mut machine = parse("((a|b)|(c|d))").unwrap();
+        let inputs = ["", "a", "b", "c", "d", "ab", "e"];
+        let expected = inputs.map(|input| machine.is_match(input));
+        let branches = machine.flatten_unions();
+        let labels: vec::Vec<Label> = branches
+            .iter()
+            .map(|&q| machine.delta[q].0.clone())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                Label::Char('a'),
+                Label::Char('b'),
+                Label::Char('c'),
+                Label::Char('d')
+            ],
+            "Four branches in priority order"
+        );
+        assert_eq!(
+            inputs.map(|input| machine.is_match(input)),
+            expected,
+            "Flattening preserves the language"
+        );
+        let [q0, _f] = machine.automata_refs[0];
+        assert_eq!(
+            machine.delta[q0].1[0],
+            Some(branches[0]),
+            "q0 splits into the first branch"
+        );
+    }
+
+    #[test]
+    fn test_flatten_unions_stops_at_loops() {
+        let mut machine = parse("(a|b*)").unwrap();
+        assert_eq!(
+            machine.flatten_unions().len(),
+            2,
+            "The star loop is a branch, not a union"
+        );
+        assert!(machine.is_match("bb"), "(a|b*) accepts bb");
+        assert_eq!(
+            parse("ab").unwrap().flatten_unions().len(),
+            1,
+            "A machine without a union is one branch"
+        );
+        assert!(
+            ANFA::new().flatten_unions().is_empty(),
+            "No machine has no branches"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Helpers for building and reading a `Transition` without indexing its tuple.
+//!
+//! The constructors uphold the ordering invariant of `Transition`: the second
+//! target is only set when the first one is.
+use crate::error::CompileError;
+use crate::{Label, QId, Transition};
+
+/// Returns a transition that never advances, e.g. a final state.
+pub fn nothing() -> Transition {
+    (Label::Epsilon, [None, None])
+}
+
+/// Returns a transition along `label` to `to`.
+///
+/// ```rust
+/// use regexxx::{transition, Label};
+/// assert_eq!(transition::labeled(Label::Char('a'), 1), (Label::Char('a'), [Some(1), None]));
+/// ```
+pub fn labeled(label: Label, to: QId) -> Transition {
+    (label, [Some(to), None])
+}
+
+/// Returns an epsilon transition to `to`.
+pub fn epsilon(to: QId) -> Transition {
+    (Label::Epsilon, [Some(to), None])
+}
+
+/// Returns an epsilon transition to both targets, preferring `targets[0]`.
+/// A union requires two targets.
+///
+/// ```rust
+/// use regexxx::transition;
+/// assert!(transition::union([Some(0), Some(2)]).is_ok());
+/// assert!(transition::union([Some(0), None]).is_err());
+/// ```
+pub fn union(targets: [Option<QId>; 2]) -> Result<Transition, CompileError> {
+    match targets {
+        [Some(_), Some(_)] => Ok((Label::Epsilon, targets)),
+        _ => Err(CompileError::Invalid("Union requires two targets.")),
+    }
+}
+
+/// Returns the targets of `transition` in order.
+pub fn targets(transition: &Transition) -> impl Iterator<Item = QId> + '_ {
+    transition.1.iter().flatten().copied()
+}
+
+/// Returns true when `transition` advances without consuming input.
+/// A transition without targets never advances, so it is not an epsilon transition.
+pub fn is_epsilon(transition: &Transition) -> bool {
+    transition.0 == Label::Epsilon && transition.1[0].is_some()
+}
+
+/// Returns true when `transition` advances to two targets.
+pub fn is_union(transition: &Transition) -> bool {
+    matches!(transition.1, [Some(_), Some(_)])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transition;
+    use crate::Label;
+    use alloc::vec;
+
+    #[test]
+    fn test_constructors() {
+        assert_eq!(
+            transition::nothing(),
+            (Label::Epsilon, [None, None]),
+            "Nothing never advances"
+        );
+        assert_eq!(
+            transition::epsilon(3),
+            (Label::Epsilon, [Some(3), None]),
+            "Epsilon fills the first target"
+        );
+        assert_eq!(
+            transition::labeled(Label::Any, 3),
+            (Label::Any, [Some(3), None]),
+            "Labeled fills the first target"
+        );
+        assert_eq!(
+            transition::union([Some(1), Some(2)]),
+            Ok((Label::Epsilon, [Some(1), Some(2)])),
+            "Union keeps the order of its targets"
+        );
+    }
+
+    #[test]
+    fn test_union_ordering() {
+        assert!(
+            transition::union([Some(1), None]).is_err(),
+            "Union rejects a missing second target"
+        );
+        assert!(
+            transition::union([None, Some(1)]).is_err(),
+            "Union rejects a second target without a first"
+        );
+        assert!(
+            transition::union([None, None]).is_err(),
+            "Union rejects no targets"
+        );
+    }
+
+    #[test]
+    fn test_accessors() {
+        let union = transition::union([Some(1), Some(2)]).unwrap();
+        assert_eq!(
+            transition::targets(&union).collect::<vec::Vec<_>>(),
+            [1, 2],
+            "Targets are in order"
+        );
+        assert!(transition::is_epsilon(&union), "Union is epsilon");
+        assert!(transition::is_union(&union), "Union has two targets");
+        let labeled = transition::labeled(Label::Char('a'), 1);
+        assert!(!transition::is_epsilon(&labeled), "Labeled is not epsilon");
+        assert!(!transition::is_union(&labeled), "Labeled has one target");
+        assert!(
+            !transition::is_epsilon(&transition::nothing()),
+            "Nothing does not advance"
+        );
+        assert_eq!(
+            transition::targets(&transition::nothing()).count(),
+            0,
+            "Nothing has no targets"
+        );
+    }
+}